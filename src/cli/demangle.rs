@@ -0,0 +1,65 @@
+use crate::cli::error::CliError;
+use crate::cli::{CommandResult, GlobalOptions};
+
+use clap::Args;
+use darklua_core::NameMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+#[derive(Debug, Args)]
+pub struct Options {
+    /// Path to the name map written by the `rename_variables` rule.
+    mapping: PathBuf,
+    /// Path to a file containing the stack trace. If not provided, the stack trace is read
+    /// from the standard input.
+    input_path: Option<PathBuf>,
+    /// Where to write the demangled stack trace. If not provided, it is printed.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(options: &Options, _global: &GlobalOptions) -> CommandResult {
+    log::debug!("running `demangle`: {:?}", options);
+
+    let mapping = fs::read_to_string(&options.mapping).map_err(|error| {
+        eprintln!(
+            "-> unable to read `{}`: {}",
+            options.mapping.display(),
+            error
+        );
+        CliError::new(1)
+    })?;
+
+    let name_map = NameMap::from_json(&mapping).map_err(|error| {
+        eprintln!("-> {}", error);
+        CliError::new(1)
+    })?;
+
+    let trace = if let Some(input_path) = options.input_path.as_ref() {
+        fs::read_to_string(input_path).map_err(|error| {
+            eprintln!("-> unable to read `{}`: {}", input_path.display(), error);
+            CliError::new(1)
+        })?
+    } else {
+        let mut trace = String::new();
+        io::stdin().read_to_string(&mut trace).map_err(|error| {
+            eprintln!("-> unable to read standard input: {}", error);
+            CliError::new(1)
+        })?;
+        trace
+    };
+
+    let content = name_map.demangle(&trace);
+
+    if let Some(output) = options.output.as_ref() {
+        fs::write(output, content).map_err(|error| {
+            eprintln!("-> unable to write `{}`: {}", output.display(), error);
+            CliError::new(1)
+        })?;
+    } else {
+        print!("{}", content);
+    }
+
+    Ok(())
+}
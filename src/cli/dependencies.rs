@@ -0,0 +1,97 @@
+use crate::cli::error::CliError;
+use crate::cli::{CommandResult, GlobalOptions};
+
+use clap::Args;
+use darklua_core::Resources;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Args)]
+pub struct Options {
+    /// Path to the lua file or directory to scan.
+    input_path: PathBuf,
+    /// Where to write the dependency graph. If not provided, the graph is printed.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// Choose the format of the dependency graph ('json' or 'dot').
+    #[arg(long, default_value = "json")]
+    format: GraphFormat,
+    /// Path to a Rojo project file, used to resolve the Roblox instances given to
+    /// `require` calls.
+    #[arg(long)]
+    project: Option<PathBuf>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum GraphFormat {
+    Json,
+    Dot,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "json" => Ok(Self::Json),
+            "dot" => Ok(Self::Dot),
+            _ => Err(format!(
+                "format '{}' does not exist! (possible options are: 'json' or 'dot')",
+                format
+            )),
+        }
+    }
+}
+
+pub fn run(options: &Options, _global: &GlobalOptions) -> CommandResult {
+    log::debug!("running `dependencies`: {:?}", options);
+
+    let resources = Resources::from_file_system();
+    let mut graph_options = darklua_core::Options::new(&options.input_path);
+
+    if let Some(project) = options.project.as_ref() {
+        graph_options = graph_options.with_project(project);
+    }
+
+    let graph = darklua_core::dependency_graph(&resources, &graph_options).map_err(|error| {
+        eprintln!("-> {}", error);
+        CliError::new(1)
+    })?;
+
+    let content = match options.format {
+        GraphFormat::Json => graph.to_json(),
+        GraphFormat::Dot => graph.to_dot(),
+    };
+
+    if let Some(output) = options.output.as_ref() {
+        fs::write(output, content).map_err(|error| {
+            eprintln!("-> unable to write `{}`: {}", output.display(), error);
+            CliError::new(1)
+        })?;
+    } else {
+        print!("{}", content);
+    }
+
+    let cycles = graph.find_cycles();
+
+    if cycles.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "{} circular dependenc{} found:",
+        cycles.len(),
+        if cycles.len() == 1 { "y" } else { "ies" }
+    );
+    for cycle in cycles.iter() {
+        let chain: Vec<_> = cycle
+            .iter()
+            .chain(cycle.first())
+            .map(|path| path.display().to_string())
+            .collect();
+        eprintln!("-> {}", chain.join(" -> "));
+    }
+
+    Err(CliError::new(1))
+}
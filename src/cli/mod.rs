@@ -1,3 +1,5 @@
+pub mod demangle;
+pub mod dependencies;
 pub mod error;
 pub mod minify;
 pub mod process;
@@ -30,6 +32,15 @@ impl GlobalOptions {
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
+    /// Replace the renamed variables of a stack trace with their original names
+    ///
+    /// Uses the name map written by the `rename_variables` rule when its
+    /// `mapping` property is set.
+    Demangle(demangle::Options),
+    /// Export the dependency graph of lua files found by following `require` calls
+    ///
+    /// Reports every circular dependency with the chain of files involved.
+    Dependencies(dependencies::Options),
     /// Minify lua files without applying any transformation
     Minify(minify::Options),
     /// Process lua files with rules
@@ -43,6 +54,8 @@ pub enum Command {
 impl Command {
     pub fn run(&self, global_options: &GlobalOptions) -> CommandResult {
         match self {
+            Command::Demangle(options) => demangle::run(options, global_options),
+            Command::Dependencies(options) => dependencies::run(options, global_options),
             Command::Minify(options) => minify::run(options, global_options),
             Command::Process(options) => process::run(options, global_options),
         }
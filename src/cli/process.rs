@@ -21,6 +21,10 @@ pub struct Options {
     /// This will override the format given by the configuration file.
     #[arg(long)]
     format: Option<LuaFormat>,
+    /// Path to a Rojo project file, used to resolve the Roblox instances given to
+    /// `require` calls.
+    #[arg(long)]
+    project: Option<PathBuf>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -57,6 +61,10 @@ pub fn run(options: &Options, _global: &GlobalOptions) -> CommandResult {
         process_options = process_options.with_configuration_at(config);
     }
 
+    if let Some(project) = options.project.as_ref() {
+        process_options = process_options.with_project(project);
+    }
+
     if let Some(format) = options.format.as_ref() {
         process_options = process_options.with_generator_override(match format {
             LuaFormat::Dense => GeneratorParameters::default_dense(),
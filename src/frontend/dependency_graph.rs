@@ -0,0 +1,579 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::rules::require_path::{collect_require_arguments, find_module_candidates};
+use crate::utils::normalize_path;
+use crate::Parser;
+
+use super::{
+    rojo_project::read_project, DarkluaError, DarkluaResult, Options, Resources, RojoProject,
+};
+
+// the maximum number of cycles returned by `find_cycles`
+const MAX_REPORTED_CYCLES: usize = 100;
+
+/// The modules found by following the `require` calls of Lua files, with the modules that
+/// each of them requires.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    modules: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl DependencyGraph {
+    /// Adds a module to the graph, without any dependency.
+    pub fn insert_module(&mut self, module: impl Into<PathBuf>) {
+        self.modules.entry(module.into()).or_default();
+    }
+
+    /// Adds a dependency from `module` to `dependency`. Both modules are added to the graph.
+    pub fn insert_dependency(
+        &mut self,
+        module: impl Into<PathBuf>,
+        dependency: impl Into<PathBuf>,
+    ) {
+        let dependency = dependency.into();
+        self.insert_module(dependency.clone());
+        self.modules
+            .entry(module.into())
+            .or_default()
+            .insert(dependency);
+    }
+
+    /// Returns an iterator over all the modules of the graph, sorted by path.
+    pub fn iter_modules(&self) -> impl Iterator<Item = &Path> {
+        self.modules.keys().map(PathBuf::as_path)
+    }
+
+    /// Returns an iterator over the modules directly required by the given module.
+    pub fn iter_dependencies(&self, module: impl AsRef<Path>) -> impl Iterator<Item = &Path> {
+        self.modules
+            .get(module.as_ref())
+            .into_iter()
+            .flat_map(|dependencies| dependencies.iter().map(PathBuf::as_path))
+    }
+
+    /// Returns the circular dependencies of the graph, with every elementary cycle (a chain of
+    /// modules where no module appears twice). A module that requires itself is a cycle of a
+    /// single module. Each cycle starts with its smallest module path and lists the modules in
+    /// the order they require each other, so the first module is required by the last one.
+    ///
+    /// Since densely connected modules can form a very large number of cycles, at most 100
+    /// cycles are returned.
+    pub fn find_cycles(&self) -> Vec<Vec<PathBuf>> {
+        let modules: Vec<&PathBuf> = self.modules.keys().collect();
+        let dependencies = self.dependency_indexes(&modules);
+
+        let mut cycles: Vec<Vec<PathBuf>> = Vec::new();
+
+        for component in strongly_connected_components(&dependencies) {
+            let remaining = MAX_REPORTED_CYCLES - cycles.len();
+            if remaining == 0 {
+                break;
+            }
+
+            cycles.extend(
+                find_component_cycles(&component, &dependencies, remaining)
+                    .into_iter()
+                    .map(|cycle| {
+                        cycle
+                            .into_iter()
+                            .map(|index| modules[index].clone())
+                            .collect()
+                    }),
+            );
+        }
+
+        cycles.sort();
+        cycles
+    }
+
+    /// Returns the dependencies of each module as indexes in the given list of modules.
+    fn dependency_indexes(&self, modules: &[&PathBuf]) -> Vec<Vec<usize>> {
+        let indexes: BTreeMap<&PathBuf, usize> = modules
+            .iter()
+            .enumerate()
+            .map(|(index, module)| (*module, index))
+            .collect();
+
+        modules
+            .iter()
+            .map(|module| {
+                self.modules[*module]
+                    .iter()
+                    .filter_map(|dependency| indexes.get(dependency).copied())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Serializes the graph to JSON. The result contains the list of modules with their
+    /// dependencies and the list of cycles.
+    pub fn to_json(&self) -> String {
+        let graph = SerializedGraph {
+            modules: self
+                .modules
+                .iter()
+                .map(|(module, dependencies)| SerializedModule {
+                    path: display_path(module),
+                    dependencies: dependencies.iter().map(|path| display_path(path)).collect(),
+                })
+                .collect(),
+            cycles: self
+                .find_cycles()
+                .iter()
+                .map(|cycle| cycle.iter().map(|path| display_path(path)).collect())
+                .collect(),
+        };
+
+        let mut json =
+            serde_json::to_string_pretty(&graph).expect("dependency graph should be serializable");
+        json.push('\n');
+        json
+    }
+
+    /// Serializes the graph to the DOT format used by Graphviz. Dependencies that are part of
+    /// a cycle are colored in red.
+    pub fn to_dot(&self) -> String {
+        let modules: Vec<&PathBuf> = self.modules.keys().collect();
+        let dependencies = self.dependency_indexes(&modules);
+
+        // every dependency between two modules of the same component is part of a cycle
+        let mut components = vec![None; modules.len()];
+        for (component_index, component) in strongly_connected_components(&dependencies)
+            .into_iter()
+            .enumerate()
+        {
+            for module in component {
+                components[module] = Some(component_index);
+            }
+        }
+
+        let mut dot = String::from("digraph dependencies {\n");
+
+        for (module, module_dependencies) in dependencies.iter().enumerate() {
+            let module_id = dot_id(modules[module]);
+
+            if module_dependencies.is_empty() {
+                writeln!(dot, "    {};", module_id).expect("unable to write to string");
+            }
+            for dependency in module_dependencies.iter().copied() {
+                let in_cycle = components[module] == components[dependency];
+
+                writeln!(
+                    dot,
+                    "    {} -> {}{};",
+                    module_id,
+                    dot_id(modules[dependency]),
+                    if in_cycle { " [color=red]" } else { "" }
+                )
+                .expect("unable to write to string");
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[derive(Serialize)]
+struct SerializedGraph {
+    modules: Vec<SerializedModule>,
+    cycles: Vec<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct SerializedModule {
+    path: String,
+    dependencies: Vec<String>,
+}
+
+/// Finds the strongly connected components of a graph with Tarjan's algorithm. The graph is
+/// given as the list of dependencies of each node. The recursion is replaced with an explicit
+/// stack, so that large graphs can not overflow the call stack.
+fn strongly_connected_components(dependencies: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let count = dependencies.len();
+    let mut indexes: Vec<Option<usize>> = vec![None; count];
+    let mut low_links = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut components = Vec::new();
+
+    for root in 0..count {
+        if indexes[root].is_some() {
+            continue;
+        }
+
+        // each entry is a node with the position of the next dependency to visit
+        let mut visits = vec![(root, 0)];
+        indexes[root] = Some(next_index);
+        low_links[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&(node, position)) = visits.last() {
+            if let Some(&next) = dependencies[node].get(position) {
+                if let Some(visit) = visits.last_mut() {
+                    visit.1 += 1;
+                }
+
+                match indexes[next] {
+                    None => {
+                        indexes[next] = Some(next_index);
+                        low_links[next] = next_index;
+                        next_index += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        visits.push((next, 0));
+                    }
+                    Some(next_index) if on_stack[next] => {
+                        low_links[node] = low_links[node].min(next_index);
+                    }
+                    Some(_) => {}
+                }
+            } else {
+                visits.pop();
+
+                if let Some(&(parent, _)) = visits.last() {
+                    low_links[parent] = low_links[parent].min(low_links[node]);
+                }
+
+                if indexes[node] == Some(low_links[node]) {
+                    let mut component = Vec::new();
+
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+
+                        if member == node {
+                            break;
+                        }
+                    }
+
+                    components.push(component);
+                }
+            }
+        }
+    }
+
+    components
+}
+
+/// Returns the elementary cycles of a component with Johnson's algorithm, stopping after
+/// `limit` cycles. Each cycle starts with its smallest node. Like for the strongly connected
+/// components, the recursion is replaced with an explicit stack.
+fn find_component_cycles(
+    component: &[usize],
+    dependencies: &[Vec<usize>],
+    limit: usize,
+) -> Vec<Vec<usize>> {
+    let mut starts = component.to_vec();
+    starts.sort_unstable();
+
+    let members: BTreeSet<usize> = component.iter().copied().collect();
+    let mut cycles = Vec::new();
+
+    for start in starts {
+        // only the cycles where `start` is the smallest node are searched
+        let is_candidate = |node: usize| node >= start && members.contains(&node);
+
+        let mut blocked: BTreeSet<usize> = BTreeSet::new();
+        let mut blocked_by: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        let mut path = vec![start];
+        // each entry is a node with the position of the next dependency to visit and whether
+        // a cycle was found from that node
+        let mut visits = vec![(start, 0, false)];
+        blocked.insert(start);
+
+        while let Some(&(node, position, _)) = visits.last() {
+            if let Some(&next) = dependencies[node].get(position) {
+                if let Some(visit) = visits.last_mut() {
+                    visit.1 += 1;
+                }
+
+                if !is_candidate(next) {
+                    continue;
+                }
+
+                if next == start {
+                    cycles.push(path.clone());
+                    if cycles.len() >= limit {
+                        return cycles;
+                    }
+                    if let Some(visit) = visits.last_mut() {
+                        visit.2 = true;
+                    }
+                } else if !blocked.contains(&next) {
+                    blocked.insert(next);
+                    path.push(next);
+                    visits.push((next, 0, false));
+                }
+            } else if let Some((node, _, found)) = visits.pop() {
+                if found {
+                    unblock(node, &mut blocked, &mut blocked_by);
+                } else {
+                    for dependency in dependencies[node].iter().copied() {
+                        if is_candidate(dependency) {
+                            blocked_by.entry(dependency).or_default().insert(node);
+                        }
+                    }
+                }
+
+                path.pop();
+
+                if found {
+                    if let Some(parent) = visits.last_mut() {
+                        parent.2 = true;
+                    }
+                }
+            }
+        }
+    }
+
+    cycles
+}
+
+fn unblock(
+    node: usize,
+    blocked: &mut BTreeSet<usize>,
+    blocked_by: &mut BTreeMap<usize, BTreeSet<usize>>,
+) {
+    let mut queue = vec![node];
+
+    while let Some(node) = queue.pop() {
+        if blocked.remove(&node) {
+            if let Some(nodes) = blocked_by.remove(&node) {
+                queue.extend(nodes);
+            }
+        }
+    }
+}
+
+/// Formats a list of paths into a cycle chain like `a.lua -> b.lua -> a.lua`.
+pub(crate) fn format_cycle(cycle: &[PathBuf]) -> String {
+    cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Quoted identifiers in the DOT format use the same escaping rules as JSON strings.
+fn dot_id(path: &Path) -> String {
+    serde_json::to_string(&display_path(path)).expect("path should be serializable")
+}
+
+/// Builds the dependency graph of the Lua files found at the input of the given options, by
+/// statically resolving their `require` calls. Required modules outside of the input are also
+/// scanned. When the options have a Rojo project path, it is used to resolve Roblox instance
+/// paths.
+pub fn dependency_graph(
+    resources: &Resources,
+    options: &Options,
+) -> DarkluaResult<DependencyGraph> {
+    let project = options
+        .project_path()
+        .map(|project_path| read_project(resources, project_path))
+        .transpose()?;
+
+    let mut graph = DependencyGraph::default();
+    let mut queue: Vec<PathBuf> = resources
+        .collect_work(options.input())
+        .map(normalize_path)
+        .collect();
+
+    if queue.is_empty() && !resources.exists(options.input())? {
+        return Err(DarkluaError::resource_not_found(options.input()));
+    }
+
+    queue.sort();
+    queue.reverse();
+
+    let mut visited = BTreeSet::new();
+
+    while let Some(source) = queue.pop() {
+        if !visited.insert(source.clone()) {
+            continue;
+        }
+
+        graph.insert_module(source.clone());
+
+        for dependency in find_dependencies(resources, &source, project.as_ref())? {
+            if !visited.contains(&dependency) {
+                queue.push(dependency.clone());
+            }
+            graph.insert_dependency(source.clone(), dependency);
+        }
+    }
+
+    Ok(graph)
+}
+
+fn find_dependencies(
+    resources: &Resources,
+    source: &Path,
+    project: Option<&RojoProject>,
+) -> DarkluaResult<Vec<PathBuf>> {
+    let content = resources.get(source)?;
+    let block = Parser::default()
+        .parse(&content)
+        .map_err(|parser_error| DarkluaError::parser_error(source, parser_error))?;
+
+    let mut dependencies = Vec::new();
+
+    for argument in collect_require_arguments(&block) {
+        let mut found = false;
+
+        for candidate in find_module_candidates(&argument, source, project) {
+            let candidate = normalize_path(candidate);
+            if resources.exists(&candidate)? {
+                dependencies.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            log::debug!("unable to resolve a require call in `{}`", source.display());
+        }
+    }
+
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph(dependencies: &[(&str, &str)]) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for (module, dependency) in dependencies {
+            graph.insert_dependency(*module, *dependency);
+        }
+        graph
+    }
+
+    fn cycle(modules: &[&str]) -> Vec<PathBuf> {
+        modules.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn find_no_cycles() {
+        let graph = graph(&[("a.lua", "b.lua"), ("b.lua", "c.lua"), ("a.lua", "c.lua")]);
+
+        assert!(graph.find_cycles().is_empty());
+    }
+
+    #[test]
+    fn find_cycle_between_two_modules() {
+        let graph = graph(&[("b.lua", "a.lua"), ("a.lua", "b.lua")]);
+
+        pretty_assertions::assert_eq!(graph.find_cycles(), vec![cycle(&["a.lua", "b.lua"])]);
+    }
+
+    #[test]
+    fn find_every_cycle_of_component() {
+        let graph = graph(&[
+            ("a.lua", "b.lua"),
+            ("b.lua", "c.lua"),
+            ("c.lua", "a.lua"),
+            ("c.lua", "b.lua"),
+            ("d.lua", "d.lua"),
+        ]);
+
+        pretty_assertions::assert_eq!(
+            graph.find_cycles(),
+            vec![
+                cycle(&["a.lua", "b.lua", "c.lua"]),
+                cycle(&["b.lua", "c.lua"]),
+                cycle(&["d.lua"])
+            ]
+        );
+    }
+
+    #[test]
+    fn format_cycle_chain() {
+        pretty_assertions::assert_eq!(
+            format_cycle(&cycle(&["a.lua", "b.lua"])),
+            "a.lua -> b.lua -> a.lua"
+        );
+    }
+
+    #[test]
+    fn empty_graph_to_json() {
+        pretty_assertions::assert_eq!(
+            DependencyGraph::default().to_json(),
+            "{\n  \"modules\": [],\n  \"cycles\": []\n}\n"
+        );
+    }
+
+    #[test]
+    fn graph_to_json() {
+        let graph = graph(&[("a.lua", "b.lua"), ("b.lua", "a.lua")]);
+
+        insta::assert_snapshot!("dependency_graph_to_json", graph.to_json());
+    }
+
+    #[test]
+    fn graph_to_dot() {
+        let graph = graph(&[("a.lua", "b.lua"), ("b.lua", "a.lua"), ("a.lua", "c.lua")]);
+
+        insta::assert_snapshot!("dependency_graph_to_dot", graph.to_dot());
+    }
+
+    #[test]
+    fn find_cycles_sharing_modules() {
+        let graph = graph(&[
+            ("a.lua", "b.lua"),
+            ("b.lua", "c.lua"),
+            ("c.lua", "d.lua"),
+            ("d.lua", "a.lua"),
+            ("b.lua", "a.lua"),
+        ]);
+
+        pretty_assertions::assert_eq!(
+            graph.find_cycles(),
+            vec![
+                cycle(&["a.lua", "b.lua"]),
+                cycle(&["a.lua", "b.lua", "c.lua", "d.lua"])
+            ]
+        );
+    }
+
+    #[test]
+    fn find_cycles_in_densely_connected_graph() {
+        let modules: Vec<_> = (0..40).map(|index| format!("{:02}.lua", index)).collect();
+        let mut graph = DependencyGraph::default();
+        for module in modules.iter() {
+            for dependency in modules.iter() {
+                graph.insert_dependency(module, dependency);
+            }
+        }
+
+        let cycles = graph.find_cycles();
+
+        pretty_assertions::assert_eq!(cycles.len(), MAX_REPORTED_CYCLES);
+        assert!(cycles.contains(&cycle(&["00.lua"])));
+    }
+
+    #[test]
+    fn dot_id_escapes_quotes() {
+        pretty_assertions::assert_eq!(dot_id(Path::new("a\"b.lua")), "\"a\\\"b.lua\"");
+    }
+
+    #[test]
+    fn graph_to_json_escapes_quotes() {
+        let graph = graph(&[("a\"b.lua", "c.lua")]);
+
+        assert!(graph.to_json().contains("\"path\": \"a\\\"b.lua\""));
+    }
+}
@@ -6,9 +6,10 @@ use std::{
     path::PathBuf,
 };
 
-use crate::{rules::Rule, ParserError};
+use crate::{rules::Rule, utils::normalize_path, ParserError};
 
 use super::{
+    dependency_graph::{format_cycle, DependencyGraph},
     resources::ResourceError,
     work_item::{WorkData, WorkItem, WorkStatus},
 };
@@ -19,6 +20,10 @@ enum ErrorKind {
         path: PathBuf,
         error: ParserError,
     },
+    Preprocessor {
+        path: PathBuf,
+        message: String,
+    },
     ResourceNotFound {
         path: PathBuf,
     },
@@ -28,6 +33,13 @@ enum ErrorKind {
     MultipleConfigurationFound {
         paths: Vec<PathBuf>,
     },
+    InvalidProject {
+        path: PathBuf,
+    },
+    InvalidDataFile {
+        path: PathBuf,
+        message: String,
+    },
     IO {
         path: PathBuf,
         error: String,
@@ -44,6 +56,10 @@ enum ErrorKind {
     CyclicWork {
         work: Vec<(WorkData, Vec<PathBuf>)>,
     },
+    RequiredFileNotInInputs {
+        path: PathBuf,
+        required_by: PathBuf,
+    },
     Custom {
         message: Cow<'static, str>,
     },
@@ -92,6 +108,13 @@ impl DarkluaError {
         })
     }
 
+    pub(crate) fn preprocessor_error(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Preprocessor {
+            path: path.into(),
+            message: message.into(),
+        })
+    }
+
     pub(crate) fn resource_not_found(path: impl Into<PathBuf>) -> Self {
         Self::new(ErrorKind::ResourceNotFound { path: path.into() })
     }
@@ -100,6 +123,17 @@ impl DarkluaError {
         Self::new(ErrorKind::InvalidConfiguration { path: path.into() })
     }
 
+    pub(crate) fn invalid_project_file(path: impl Into<PathBuf>) -> Self {
+        Self::new(ErrorKind::InvalidProject { path: path.into() })
+    }
+
+    pub(crate) fn invalid_data_file(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidDataFile {
+            path: path.into(),
+            message: message.into(),
+        })
+    }
+
     pub(crate) fn uncached_work(path: impl Into<PathBuf>) -> Self {
         Self::new(ErrorKind::UncachedWork { path: path.into() })
     }
@@ -166,6 +200,16 @@ impl DarkluaError {
         })
     }
 
+    pub(crate) fn required_file_not_in_inputs(
+        path: impl Into<PathBuf>,
+        required_by: impl Into<PathBuf>,
+    ) -> Self {
+        Self::new(ErrorKind::RequiredFileNotInInputs {
+            path: path.into(),
+            required_by: required_by.into(),
+        })
+    }
+
     pub(crate) fn custom(message: impl Into<Cow<'static, str>>) -> Self {
         Self::new(ErrorKind::Custom {
             message: message.into(),
@@ -188,6 +232,9 @@ impl Display for DarkluaError {
             ErrorKind::Parser { path, error } => {
                 write!(f, "unable to parse `{}`: {}", path.display(), error)?;
             }
+            ErrorKind::Preprocessor { path, message } => {
+                write!(f, "unable to preprocess `{}`: {}", path.display(), message)?;
+            }
             ErrorKind::ResourceNotFound { path } => {
                 write!(f, "unable to find `{}`", path.display())?;
             }
@@ -205,6 +252,12 @@ impl Display for DarkluaError {
                         .join(", ")
                 )?;
             }
+            ErrorKind::InvalidProject { path } => {
+                write!(f, "invalid Rojo project file at `{}`", path.display())?;
+            }
+            ErrorKind::InvalidDataFile { path, message } => {
+                write!(f, "invalid data file at `{}`: {}", path.display(), message)?;
+            }
             ErrorKind::IO { path, error } => {
                 write!(f, "IO error with `{}`: {}", path.display(), error)?;
             }
@@ -249,17 +302,42 @@ impl Display for DarkluaError {
                     })
                     .collect();
 
+                let mut graph = DependencyGraph::default();
+                for (data, required) in work.iter() {
+                    for path in required {
+                        graph.insert_dependency(normalize_path(data.source()), path);
+                    }
+                }
+                let cycles: Vec<_> = graph
+                    .find_cycles()
+                    .iter()
+                    .map(|cycle| format!("\n    {}", format_cycle(cycle)))
+                    .collect();
+
                 write!(
                     f,
-                    "cyclic work detected:\n{}{}",
+                    "cyclic work detected:\n{}{}{}",
                     list.join("\n"),
                     if total <= MAX_PRINTED_WORK {
                         "".to_owned()
                     } else {
                         format!("\n    and {} more", total - MAX_PRINTED_WORK)
+                    },
+                    if cycles.is_empty() {
+                        "".to_owned()
+                    } else {
+                        format!("\n  circular dependencies:{}", cycles.join(""))
                     }
                 )?;
             }
+            ErrorKind::RequiredFileNotInInputs { path, required_by } => {
+                write!(
+                    f,
+                    "required file `{}` not found in inputs (required by `{}`)",
+                    path.display(),
+                    required_by.display()
+                )?;
+            }
             ErrorKind::Custom { message } => {
                 write!(f, "{}", message)?;
             }
@@ -1,8 +1,12 @@
 mod configuration;
+mod dependency_graph;
 mod error;
+mod name_map;
 mod options;
+mod preprocessor;
 mod process_result;
 mod resources;
+mod rojo_project;
 mod utils;
 mod work_cache;
 mod work_item;
@@ -11,10 +15,13 @@ mod worker;
 use std::path::Path;
 
 pub use configuration::{Configuration, GeneratorParameters};
+pub use dependency_graph::{dependency_graph, DependencyGraph};
 pub use error::{DarkluaError, DarkluaResult};
+pub use name_map::NameMap;
 pub use options::Options;
 pub use process_result::ProcessResult;
 pub use resources::Resources;
+pub use rojo_project::RojoProject;
 use work_item::WorkItem;
 use worker::Worker;
 
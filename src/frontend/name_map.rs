@@ -0,0 +1,577 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::process::utils::is_valid_identifier;
+use crate::rules::{RenamedVariable, MAIN_SCOPE_NAME};
+use crate::utils::normalize_path;
+
+use super::{DarkluaError, DarkluaResult};
+
+const LUA_EXTENSIONS: [&str; 2] = [".lua", ".luau"];
+// in stack traces, the names of variables follow one of these words
+const NAME_KINDS: [(&str, NameKind); 3] = [
+    ("local '", NameKind::Local),
+    ("upvalue '", NameKind::Upvalue),
+    ("function '", NameKind::Function),
+];
+// in Lua stack traces, the function running at a line follows one of these words
+const FRAME_KINDS: [&str; 5] = [
+    "in function '",
+    "in local '",
+    "in upvalue '",
+    "in method '",
+    "in field '",
+];
+const MAIN_CHUNK: &str = "in main chunk";
+const ROBLOX_LINE: &str = ", Line ";
+const ROBLOX_FUNCTION: &str = " - function ";
+
+/// The kind of variable named in a stack trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NameKind {
+    /// A local variable of the function running at the line.
+    Local,
+    /// A variable declared in an enclosing function.
+    Upvalue,
+    /// A variable holding a called function.
+    Function,
+}
+
+/// The original names of the variables renamed by the `rename_variables` rule, grouped by the
+/// output file where they are written. It is used to convert stack traces from processed code
+/// back to the original names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameMap {
+    files: BTreeMap<String, Vec<RenamedVariable>>,
+}
+
+impl NameMap {
+    pub fn from_json(content: &str) -> DarkluaResult<Self> {
+        json5::from_str(content)
+            .map_err(|err| DarkluaError::custom(format!("unable to read name map: {}", err)))
+    }
+
+    pub fn to_json(&self) -> String {
+        json5::to_string(self).expect("name map should be serializable")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn get_variables(&self, path: impl AsRef<Path>) -> Option<&[RenamedVariable]> {
+        self.files.get(&path_key(path.as_ref())).map(Vec::as_slice)
+    }
+
+    pub(crate) fn insert(&mut self, path: &Path, variables: Vec<RenamedVariable>) {
+        self.files
+            .entry(path_key(path))
+            .or_default()
+            .extend(variables);
+    }
+
+    /// Replaces the variable names found in the given stack trace with their original names.
+    /// Lines that do not point to a file from the name map are left unchanged.
+    ///
+    /// Variables are matched using the line where they are declared and the function running
+    /// at the line of the stack trace, so the result is only exact when the processed code was
+    /// generated with the `retain_lines` generator. Otherwise, a name is only replaced when all
+    /// the variables with that name have the same original name.
+    pub fn demangle(&self, trace: &str) -> String {
+        let lines: Vec<&str> = trace.split_inclusive('\n').collect();
+
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| self.demangle_line(line, find_line_function(&lines[index..])))
+            .collect()
+    }
+
+    /// Replaces the variable names of a line. The function running at that line is `None` when
+    /// it is unknown, and `Some(None)` for the main chunk.
+    fn demangle_line(&self, line: &str, function: Option<Option<&str>>) -> String {
+        let (variables, line_number) = match find_location(line)
+            .and_then(|(source, line_number)| Some((self.find_file(source)?, line_number)))
+        {
+            Some(location) => location,
+            None => return line.to_owned(),
+        };
+
+        let scope = function.map(|function| match function {
+            Some(name) => find_original(variables, name, line_number, |_| true).unwrap_or(name),
+            None => MAIN_SCOPE_NAME,
+        });
+
+        let mut replacements = Vec::new();
+
+        for (pattern, kind) in NAME_KINDS {
+            for (start, _) in line.match_indices(pattern) {
+                let name_start = start + pattern.len();
+                // in Lua stack traces, the function of each line is named with its variable
+                let kind = if line[..start].ends_with("in ") {
+                    NameKind::Function
+                } else {
+                    kind
+                };
+                if let Some(length) = line[name_start..].find('\'') {
+                    replacements.push((name_start, name_start + length, kind));
+                }
+            }
+        }
+
+        if line.contains(ROBLOX_LINE) {
+            if let Some(start) = line.rfind(ROBLOX_FUNCTION) {
+                let name_start = start + ROBLOX_FUNCTION.len();
+                let name_end = name_start + line[name_start..].trim_end().len();
+                replacements.push((name_start, name_end, NameKind::Function));
+            }
+        }
+
+        replacements.sort_unstable_by_key(|(start, _, _)| *start);
+
+        let mut result = String::with_capacity(line.len());
+        let mut last_end = 0;
+
+        for (start, end, kind) in replacements {
+            let name = &line[start..end];
+            if start < last_end || !is_valid_identifier(name) {
+                continue;
+            }
+
+            let original = find_original(variables, name, line_number, |variable| {
+                match (kind, scope) {
+                    (NameKind::Local, Some(scope)) => is_declared_in(variable, scope),
+                    (NameKind::Upvalue, Some(scope)) => !is_declared_in(variable, scope),
+                    _ => true,
+                }
+            });
+
+            if let Some(original) = original {
+                result.push_str(&line[last_end..start]);
+                result.push_str(original);
+                last_end = end;
+            }
+        }
+
+        result.push_str(&line[last_end..]);
+        result
+    }
+
+    /// Finds the file that matches the most path components of the given source. Roblox paths
+    /// are separated with periods, and a module named `init` is identified by its parent.
+    fn find_file(&self, source: &str) -> Option<&[RenamedVariable]> {
+        let source_components = path_components(source);
+        let mut best_match = None;
+        let mut best_count = 0;
+        let mut is_ambiguous = false;
+
+        for (path, variables) in self.files.iter() {
+            let count = path_components(path)
+                .iter()
+                .rev()
+                .zip(source_components.iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+
+            if count == 0 || count < best_count {
+                continue;
+            }
+            is_ambiguous = count == best_count;
+            best_count = count;
+            best_match = Some(variables.as_slice());
+        }
+
+        if is_ambiguous {
+            None
+        } else {
+            best_match
+        }
+    }
+}
+
+fn path_key(path: &Path) -> String {
+    normalize_path(path).to_string_lossy().replace('\\', "/")
+}
+
+fn path_components(path: &str) -> Vec<&str> {
+    let path = LUA_EXTENSIONS
+        .iter()
+        .find_map(|extension| path.strip_suffix(extension))
+        .unwrap_or(path);
+
+    let mut components: Vec<_> = path
+        .split(['/', '\\', '.'])
+        .filter(|component| !component.is_empty())
+        .collect();
+
+    if components.len() > 1 && components.last() == Some(&"init") {
+        components.pop();
+    }
+
+    components
+}
+
+/// Finds the source and the line number from a stack trace line, either in the Lua format
+/// (`path/to/file.lua:12:`) or in the Roblox format (`Script 'Path.To.Module', Line 12`).
+fn find_location(line: &str) -> Option<(&str, usize)> {
+    if let Some(index) = line.find(ROBLOX_LINE) {
+        let before = line[..index].strip_suffix('\'')?;
+        let source_start = before.rfind('\'')? + 1;
+        let line_number = parse_line_number(&line[index + ROBLOX_LINE.len()..])?;
+        return Some((&before[source_start..], line_number));
+    }
+
+    for (index, _) in line.match_indices(':') {
+        let after = &line[index + 1..];
+        let digits = after
+            .find(|character: char| !character.is_ascii_digit())
+            .unwrap_or(after.len());
+
+        if digits == 0 || !after[digits..].starts_with(':') {
+            continue;
+        }
+
+        let before = &line[..index];
+        let source_start = before
+            .rfind(|character: char| character.is_whitespace())
+            .map(|start| start + 1)
+            .unwrap_or(0);
+
+        if let Ok(line_number) = after[..digits].parse() {
+            return Some((&before[source_start..], line_number));
+        }
+    }
+
+    None
+}
+
+fn parse_line_number(content: &str) -> Option<usize> {
+    let digits = content
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(content.len());
+    content[..digits].parse().ok()
+}
+
+/// Finds the function running at the first of the given lines. Error messages do not name the
+/// function, so it is taken from the next line of the stack trace at the same location.
+fn find_line_function<'a>(lines: &[&'a str]) -> Option<Option<&'a str>> {
+    let location = find_location(lines.first()?)?;
+
+    lines
+        .iter()
+        .filter(|line| find_location(line) == Some(location))
+        .find_map(|line| find_frame_function(line))
+}
+
+/// Finds the name of the function running at a line of a stack trace. Returns `Some(None)` for
+/// the main chunk, and `None` when the line does not name the function.
+fn find_frame_function(line: &str) -> Option<Option<&str>> {
+    if line.contains(MAIN_CHUNK) {
+        return Some(None);
+    }
+
+    if line.contains(ROBLOX_LINE) {
+        let start = line.rfind(ROBLOX_FUNCTION)? + ROBLOX_FUNCTION.len();
+        return Some(Some(line[start..].trim_end()));
+    }
+
+    FRAME_KINDS.iter().find_map(|kind| {
+        let start = line.find(kind)? + kind.len();
+        let length = line[start..].find('\'')?;
+        Some(Some(&line[start..start + length]))
+    })
+}
+
+/// Returns true if the variable is declared in the given function. The scope of a method or of
+/// a function stored in a field also contains the name of its table (like `Counter:add`).
+fn is_declared_in(variable: &RenamedVariable, function: &str) -> bool {
+    let scope = variable.get_scope();
+
+    scope == function
+        || scope
+            .strip_suffix(function)
+            .filter(|prefix| prefix.ends_with(':') || prefix.ends_with('.'))
+            .is_some()
+}
+
+/// Finds the original name of a variable among the variables accepted by the given filter.
+/// When lines are available, the closest declaration before the given line is used. Otherwise,
+/// the name is only replaced if all the variables with that name have the same original name.
+fn find_original<'a>(
+    variables: &'a [RenamedVariable],
+    name: &str,
+    line_number: usize,
+    accept: impl Fn(&RenamedVariable) -> bool,
+) -> Option<&'a str> {
+    let mut candidates = variables
+        .iter()
+        .filter(|variable| variable.get_name() == name && accept(variable))
+        .peekable();
+
+    let first = candidates.peek().copied()?;
+
+    if variables
+        .iter()
+        .any(|variable| variable.get_line().is_some())
+    {
+        candidates
+            .filter(|variable| {
+                variable
+                    .get_line()
+                    .filter(|line| *line <= line_number)
+                    .is_some()
+            })
+            .max_by_key(|variable| variable.get_line())
+            .map(RenamedVariable::get_original)
+    } else if candidates.all(|variable| variable.get_original() == first.get_original()) {
+        Some(first.get_original())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn name_map(path: &str, variables: Vec<RenamedVariable>) -> NameMap {
+        let mut name_map = NameMap::default();
+        name_map.insert(Path::new(path), variables);
+        name_map
+    }
+
+    fn variable(name: &str, original: &str, line: usize) -> RenamedVariable {
+        RenamedVariable::new(name, original, "<main>").with_line(line)
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let name_map = name_map(
+            "out/counter.lua",
+            vec![
+                variable("a", "count", 1),
+                RenamedVariable::new("b", "value", "increment"),
+            ],
+        );
+
+        pretty_assertions::assert_eq!(NameMap::from_json(&name_map.to_json()).unwrap(), name_map);
+    }
+
+    #[test]
+    fn demangle_lua_error() {
+        let name_map = name_map("out/counter.lua", vec![variable("a", "count", 2)]);
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle(
+                "out/counter.lua:5: attempt to perform arithmetic on a nil value (local 'a')"
+            ),
+            "out/counter.lua:5: attempt to perform arithmetic on a nil value (local 'count')"
+        );
+    }
+
+    #[test]
+    fn demangle_lua_stack_trace() {
+        let name_map = name_map(
+            "out/counter.lua",
+            vec![variable("a", "increment", 1), variable("b", "value", 2)],
+        );
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle(concat!(
+                "stack traceback:\n",
+                "\t[C]: in ?\n",
+                "\tout/counter.lua:3: in upvalue 'a'\n",
+                "\tout/counter.lua:8: in function 'a'\n",
+                "\tout/counter.lua:10: in main chunk\n",
+            )),
+            concat!(
+                "stack traceback:\n",
+                "\t[C]: in ?\n",
+                "\tout/counter.lua:3: in upvalue 'increment'\n",
+                "\tout/counter.lua:8: in function 'increment'\n",
+                "\tout/counter.lua:10: in main chunk\n",
+            )
+        );
+    }
+
+    #[test]
+    fn demangle_roblox_stack_trace() {
+        let name_map = name_map(
+            "build/shared/Counter/init.lua",
+            vec![variable("a", "increment", 4)],
+        );
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle(concat!(
+                "ReplicatedStorage.Shared.Counter:6: attempt to index nil (upvalue 'a')\n",
+                "Script 'ReplicatedStorage.Shared.Counter', Line 6 - function a\n",
+            )),
+            concat!(
+                "ReplicatedStorage.Shared.Counter:6: attempt to index nil (upvalue 'increment')\n",
+                "Script 'ReplicatedStorage.Shared.Counter', Line 6 - function increment\n",
+            )
+        );
+    }
+
+    #[test]
+    fn demangle_uses_closest_declaration_before_line() {
+        let name_map = name_map(
+            "main.lua",
+            vec![variable("a", "first", 1), variable("a", "second", 10)],
+        );
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle("main.lua:5: error (local 'a')\nmain.lua:12: error (local 'a')"),
+            "main.lua:5: error (local 'first')\nmain.lua:12: error (local 'second')"
+        );
+    }
+
+    fn scoped_variable(name: &str, original: &str, scope: &str, line: usize) -> RenamedVariable {
+        RenamedVariable::new(name, original, scope).with_line(line)
+    }
+
+    #[test]
+    fn demangle_local_of_main_chunk_after_function() {
+        let name_map = name_map(
+            "main.lua",
+            vec![
+                variable("a", "count", 1),
+                variable("b", "run", 2),
+                scoped_variable("a", "item", "run", 3),
+            ],
+        );
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle(concat!(
+                "main.lua:6: attempt to index a nil value (local 'a')\n",
+                "stack traceback:\n",
+                "\tmain.lua:6: in main chunk\n",
+            )),
+            concat!(
+                "main.lua:6: attempt to index a nil value (local 'count')\n",
+                "stack traceback:\n",
+                "\tmain.lua:6: in main chunk\n",
+            )
+        );
+    }
+
+    #[test]
+    fn demangle_local_of_function_in_roblox_trace() {
+        let name_map = name_map(
+            "Module.lua",
+            vec![
+                variable("c", "run", 1),
+                scoped_variable("b", "value", "run", 2),
+                variable("b", "other", 3),
+            ],
+        );
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle(concat!(
+                "ReplicatedStorage.Module:4: attempt to index nil (local 'b')\n",
+                "Script 'ReplicatedStorage.Module', Line 4 - function c\n",
+            )),
+            concat!(
+                "ReplicatedStorage.Module:4: attempt to index nil (local 'value')\n",
+                "Script 'ReplicatedStorage.Module', Line 4 - function run\n",
+            )
+        );
+    }
+
+    #[test]
+    fn demangle_upvalue_declared_in_enclosing_function() {
+        let name_map = name_map(
+            "main.lua",
+            vec![
+                variable("a", "count", 1),
+                variable("b", "run", 2),
+                scoped_variable("a", "item", "run", 3),
+            ],
+        );
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle(concat!(
+                "main.lua:5: attempt to index a nil value (upvalue 'a')\n",
+                "stack traceback:\n",
+                "\tmain.lua:5: in function 'b'\n",
+            )),
+            concat!(
+                "main.lua:5: attempt to index a nil value (upvalue 'count')\n",
+                "stack traceback:\n",
+                "\tmain.lua:5: in function 'run'\n",
+            )
+        );
+    }
+
+    #[test]
+    fn method_scope_contains_its_name() {
+        let variable = RenamedVariable::new("a", "amount", "Counter:add");
+
+        assert!(is_declared_in(&variable, "Counter:add"));
+        assert!(is_declared_in(&variable, "add"));
+        assert!(!is_declared_in(&variable, "dd"));
+    }
+
+    #[test]
+    fn demangle_without_lines_keeps_ambiguous_names() {
+        let name_map = name_map(
+            "main.lua",
+            vec![
+                RenamedVariable::new("a", "first", "<main>"),
+                RenamedVariable::new("a", "second", "run"),
+                RenamedVariable::new("b", "value", "run"),
+            ],
+        );
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle("main.lua:5: error (local 'a') (upvalue 'b')"),
+            "main.lua:5: error (local 'a') (upvalue 'value')"
+        );
+    }
+
+    #[test]
+    fn demangle_does_not_replace_globals_and_fields() {
+        let name_map = name_map("main.lua", vec![variable("a", "value", 1)]);
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle("main.lua:5: error (global 'a') (field 'a')"),
+            "main.lua:5: error (global 'a') (field 'a')"
+        );
+    }
+
+    #[test]
+    fn demangle_ignores_unknown_files() {
+        let name_map = name_map("main.lua", vec![variable("a", "value", 1)]);
+
+        pretty_assertions::assert_eq!(
+            name_map.demangle("other.lua:5: error (local 'a')"),
+            "other.lua:5: error (local 'a')"
+        );
+    }
+
+    #[test]
+    fn find_location_in_lua_trace() {
+        pretty_assertions::assert_eq!(
+            find_location("\tsrc/main.lua:12: in main chunk"),
+            Some(("src/main.lua", 12))
+        );
+    }
+
+    #[test]
+    fn find_location_in_roblox_trace() {
+        pretty_assertions::assert_eq!(
+            find_location("Script 'Workspace.Script', Line 3"),
+            Some(("Workspace.Script", 3))
+        );
+    }
+
+    #[test]
+    fn path_components_ignore_extension_and_init() {
+        pretty_assertions::assert_eq!(
+            path_components("out/Counter/init.luau"),
+            vec!["out", "Counter"]
+        );
+    }
+}
@@ -8,6 +8,7 @@ pub struct Options {
     config_path: Option<PathBuf>,
     config: Option<Configuration>,
     config_generator_override: Option<GeneratorParameters>,
+    project_path: Option<PathBuf>,
     output: Option<PathBuf>,
     fail_fast: bool,
 }
@@ -21,6 +22,7 @@ impl Options {
             output: None,
             fail_fast: false,
             config_generator_override: None,
+            project_path: None,
         }
     }
 
@@ -49,6 +51,11 @@ impl Options {
         self
     }
 
+    pub fn with_project(mut self, project: impl Into<PathBuf>) -> Self {
+        self.project_path = Some(project.into());
+        self
+    }
+
     pub fn input(&self) -> &Path {
         &self.input
     }
@@ -65,6 +72,10 @@ impl Options {
         self.config_path.as_ref().map(AsRef::as_ref)
     }
 
+    pub fn project_path(&self) -> Option<&Path> {
+        self.project_path.as_ref().map(AsRef::as_ref)
+    }
+
     pub fn generator_override(&self) -> Option<&GeneratorParameters> {
         self.config_generator_override.as_ref()
     }
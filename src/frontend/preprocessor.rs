@@ -0,0 +1,493 @@
+use std::borrow::Cow;
+
+use crate::nodes::{Expression, LastStatement};
+use crate::process::{DefaultVisitor, Evaluator, NodeProcessor, NodeVisitor};
+use crate::rules::{Context, INJECT_GLOBAL_VALUE_RULE_NAME};
+use crate::Parser;
+
+use super::configuration::Configuration;
+
+const DIRECTIVE_PREFIX: &str = "--#";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    If,
+    ElseIf,
+    Else,
+    End,
+}
+
+impl Directive {
+    fn parse(line: &str) -> Option<(Self, &str)> {
+        let rest = line.trim_start().strip_prefix(DIRECTIVE_PREFIX)?;
+        let (name, argument) = match rest.find(char::is_whitespace) {
+            Some(index) => (&rest[..index], rest[index..].trim()),
+            None => (rest.trim_end(), ""),
+        };
+
+        let directive = match name {
+            "if" => Self::If,
+            "elseif" => Self::ElseIf,
+            "else" => Self::Else,
+            "end" => Self::End,
+            _ => return None,
+        };
+
+        Some((directive, argument))
+    }
+}
+
+/// An `--#if` section that is not closed yet.
+#[derive(Debug)]
+struct Section {
+    line: usize,
+    parent_active: bool,
+    branch_taken: bool,
+    active: bool,
+    has_else: bool,
+}
+
+/// Replaces the remaining global variables with `nil`, so that values that are not injected
+/// are considered undefined.
+struct UndefinedValues;
+
+impl NodeProcessor for UndefinedValues {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if matches!(expression, Expression::Identifier(_)) {
+            *expression = Expression::nil();
+        }
+    }
+}
+
+/// Tracks the long strings and long comments of the kept lines, so that directives written
+/// inside of them are ignored.
+#[derive(Debug, Default)]
+struct LongBracketScanner {
+    // the level of the long bracket that is not closed yet
+    open_level: Option<usize>,
+}
+
+impl LongBracketScanner {
+    fn is_inside_long_bracket(&self) -> bool {
+        self.open_level.is_some()
+    }
+
+    fn scan_line(&mut self, line: &str) {
+        let bytes = line.as_bytes();
+        let mut index = 0;
+
+        while index < bytes.len() {
+            if let Some(level) = self.open_level {
+                let closing_bracket = format!("]{}]", "=".repeat(level));
+                match line[index..].find(&closing_bracket) {
+                    Some(position) => {
+                        self.open_level = None;
+                        index += position + closing_bracket.len();
+                    }
+                    None => return,
+                }
+                continue;
+            }
+
+            match bytes[index] {
+                b'-' if bytes.get(index + 1) == Some(&b'-') => {
+                    match parse_opening_long_bracket(bytes, index + 2) {
+                        Some((level, end)) => {
+                            self.open_level = Some(level);
+                            index = end;
+                        }
+                        // the rest of the line is a comment
+                        None => return,
+                    }
+                }
+                b'[' => match parse_opening_long_bracket(bytes, index) {
+                    Some((level, end)) => {
+                        self.open_level = Some(level);
+                        index = end;
+                    }
+                    None => index += 1,
+                },
+                quote @ (b'\'' | b'"') => {
+                    index += 1;
+                    while index < bytes.len() {
+                        match bytes[index] {
+                            b'\\' => index += 2,
+                            character if character == quote => {
+                                index += 1;
+                                break;
+                            }
+                            _ => index += 1,
+                        }
+                    }
+                }
+                _ => index += 1,
+            }
+        }
+    }
+}
+
+/// Returns the level of the long bracket that opens at the given index and the index
+/// following it.
+fn parse_opening_long_bracket(bytes: &[u8], start: usize) -> Option<(usize, usize)> {
+    if bytes.get(start) != Some(&b'[') {
+        return None;
+    }
+
+    let level = bytes[start + 1..]
+        .iter()
+        .take_while(|byte| **byte == b'=')
+        .count();
+
+    if bytes.get(start + level + 1) == Some(&b'[') {
+        Some((level, start + level + 2))
+    } else {
+        None
+    }
+}
+
+struct Preprocessor<'a, 'b> {
+    configuration: &'a Configuration,
+    context: &'a Context<'b>,
+    sections: Vec<Section>,
+}
+
+impl<'a, 'b> Preprocessor<'a, 'b> {
+    fn is_active(&self) -> bool {
+        !matches!(self.sections.last(), Some(section) if !section.active)
+    }
+
+    fn evaluate_condition(&self, condition: &str) -> Result<bool, String> {
+        if condition.is_empty() {
+            return Err("missing condition".to_owned());
+        }
+
+        let mut block = Parser::default()
+            .parse(&format!("return {}", condition))
+            .map_err(|err| format!("unable to parse condition `{}`: {}", condition, err))?;
+
+        let mut context = self.context.clone();
+        for rule in self
+            .configuration
+            .rules()
+            .filter(|rule| rule.get_name() == INJECT_GLOBAL_VALUE_RULE_NAME)
+        {
+            rule.process(&mut block, &mut context)?;
+        }
+
+        DefaultVisitor::visit_block(&mut block, &mut UndefinedValues);
+
+        let value = match block.get_last_statement() {
+            Some(LastStatement::Return(statement))
+                if statement.len() == 1 && block.iter_statements().next().is_none() =>
+            {
+                statement
+                    .iter_expressions()
+                    .next()
+                    .map(|expression| Evaluator::default().evaluate(expression).is_truthy())
+            }
+            _ => return Err(format!("invalid condition `{}`", condition)),
+        };
+
+        value
+            .flatten()
+            .ok_or_else(|| format!("unable to evaluate condition `{}`", condition))
+    }
+
+    fn apply(&mut self, directive: Directive, argument: &str, line: usize) -> Result<(), String> {
+        match directive {
+            Directive::If => {
+                let parent_active = self.is_active();
+                let active = parent_active && self.evaluate_condition(argument)?;
+                self.sections.push(Section {
+                    line,
+                    parent_active,
+                    branch_taken: active,
+                    active,
+                    has_else: false,
+                });
+            }
+            Directive::ElseIf => {
+                let (parent_active, branch_taken) = match self.sections.last() {
+                    Some(section) if !section.has_else => {
+                        (section.parent_active, section.branch_taken)
+                    }
+                    Some(_) => return Err("`--#elseif` after `--#else`".to_owned()),
+                    None => return Err("`--#elseif` without matching `--#if`".to_owned()),
+                };
+
+                let active = parent_active && !branch_taken && self.evaluate_condition(argument)?;
+
+                let section = self.sections.last_mut().unwrap();
+                section.active = active;
+                section.branch_taken |= active;
+            }
+            Directive::Else => {
+                let section = match self.sections.last_mut() {
+                    Some(section) if !section.has_else => section,
+                    Some(_) => return Err("multiple `--#else` in the same section".to_owned()),
+                    None => return Err("`--#else` without matching `--#if`".to_owned()),
+                };
+
+                section.active = section.parent_active && !section.branch_taken;
+                section.branch_taken = true;
+                section.has_else = true;
+            }
+            Directive::End => {
+                if self.sections.pop().is_none() {
+                    return Err("`--#end` without matching `--#if`".to_owned());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies the `--#if`, `--#elseif`, `--#else` and `--#end` comment directives of a Lua source.
+/// Conditions are Lua expressions evaluated with the values of the `inject_global_value` rules
+/// of the configuration. The lines of excluded sections are emptied, so that the line numbers
+/// of the remaining code do not change. Directives inside long strings or long comments are
+/// ignored. The context contains the data files read by the `inject_global_value` rules.
+pub(crate) fn preprocess<'a>(
+    content: &'a str,
+    configuration: &Configuration,
+    context: &Context,
+) -> Result<Cow<'a, str>, String> {
+    if !content.contains(DIRECTIVE_PREFIX) {
+        return Ok(Cow::Borrowed(content));
+    }
+
+    let mut preprocessor = Preprocessor {
+        configuration,
+        context,
+        sections: Vec::new(),
+    };
+    let mut scanner = LongBracketScanner::default();
+    let mut result = String::with_capacity(content.len());
+    let mut has_directive = false;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+
+        let directive = if scanner.is_inside_long_bracket() {
+            None
+        } else {
+            Directive::parse(line)
+        };
+
+        if let Some((directive, argument)) = directive {
+            has_directive = true;
+            preprocessor
+                .apply(directive, argument, line_number)
+                .map_err(|message| format!("{} (line {})", message, line_number))?;
+            result.push_str(line);
+        } else if preprocessor.is_active() {
+            scanner.scan_line(line);
+            result.push_str(line);
+        } else {
+            let line_ending_start = line.trim_end_matches(['\n', '\r']).len();
+            result.push_str(&line[line_ending_start..]);
+        }
+    }
+
+    if let Some(section) = preprocessor.sections.last() {
+        return Err(format!(
+            "missing `--#end` for `--#if` (line {})",
+            section.line
+        ));
+    }
+
+    if has_directive {
+        Ok(Cow::Owned(result))
+    } else {
+        Ok(Cow::Borrowed(content))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::{InjectGlobalValue, Rule};
+
+    fn configuration() -> Configuration {
+        let debug: Box<dyn Rule> = Box::new(InjectGlobalValue::boolean("DEBUG", true));
+        let platform: Box<dyn Rule> = Box::new(InjectGlobalValue::string("PLATFORM", "mobile"));
+
+        Configuration::empty().with_rule(debug).with_rule(platform)
+    }
+
+    fn preprocess_code(content: &str) -> Result<String, String> {
+        preprocess(content, &configuration(), &Context::default()).map(Cow::into_owned)
+    }
+
+    #[test]
+    fn content_without_directive_is_borrowed() {
+        let content = "-- comment\nreturn 1\n";
+
+        assert!(matches!(
+            preprocess(content, &configuration(), &Context::default()),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn keeps_active_section() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if DEBUG\nprint('debug')\n--#end\nreturn 1").unwrap(),
+            "--#if DEBUG\nprint('debug')\n--#end\nreturn 1"
+        );
+    }
+
+    #[test]
+    fn empties_inactive_section() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if not DEBUG\nprint('release')\n--#end\nreturn 1").unwrap(),
+            "--#if not DEBUG\n\n--#end\nreturn 1"
+        );
+    }
+
+    #[test]
+    fn else_section() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if PLATFORM == 'pc'\na()\n--#else\nb()\n--#end\n").unwrap(),
+            "--#if PLATFORM == 'pc'\n\n--#else\nb()\n--#end\n"
+        );
+    }
+
+    #[test]
+    fn elseif_section() {
+        pretty_assertions::assert_eq!(
+            preprocess_code(concat!(
+                "--#if PLATFORM == 'pc'\na()\n",
+                "--#elseif PLATFORM == 'mobile'\nb()\n",
+                "--#elseif DEBUG\nc()\n",
+                "--#else\nd()\n--#end\n"
+            ))
+            .unwrap(),
+            concat!(
+                "--#if PLATFORM == 'pc'\n\n",
+                "--#elseif PLATFORM == 'mobile'\nb()\n",
+                "--#elseif DEBUG\n\n",
+                "--#else\n\n--#end\n"
+            )
+        );
+    }
+
+    #[test]
+    fn nested_sections() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if not DEBUG\n--#if DEBUG\na()\n--#else\nb()\n--#end\n--#end\n")
+                .unwrap(),
+            "--#if not DEBUG\n--#if DEBUG\n\n--#else\n\n--#end\n--#end\n"
+        );
+    }
+
+    #[test]
+    fn excluded_code_can_be_invalid() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if UNDEFINED\nlocal = =\n--#end\r\nreturn 1").unwrap(),
+            "--#if UNDEFINED\n\n--#end\r\nreturn 1"
+        );
+    }
+
+    #[test]
+    fn preserves_windows_line_endings() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if false\r\na()\r\n--#end\r\n").unwrap(),
+            "--#if false\r\n\r\n--#end\r\n"
+        );
+    }
+
+    #[test]
+    fn other_comments_are_not_directives() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#region\n--#iffy\nreturn 1").unwrap(),
+            "--#region\n--#iffy\nreturn 1"
+        );
+    }
+
+    #[test]
+    fn directives_in_long_string_are_ignored() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if DEBUG\nlocal s = [[\n--#end\n]]\n--#end\nreturn s").unwrap(),
+            "--#if DEBUG\nlocal s = [[\n--#end\n]]\n--#end\nreturn s"
+        );
+    }
+
+    #[test]
+    fn directives_in_long_string_with_level_are_ignored() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("local s = [==[\n]]\n--#if false\n]==]\nreturn s").unwrap(),
+            "local s = [==[\n]]\n--#if false\n]==]\nreturn s"
+        );
+    }
+
+    #[test]
+    fn directives_in_long_comment_are_ignored() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--[[\n--#if false\n]]\nreturn 1").unwrap(),
+            "--[[\n--#if false\n]]\nreturn 1"
+        );
+    }
+
+    #[test]
+    fn directives_after_long_string_are_applied() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("local s = [[\n]] --#if false\n--#if false\na()\n--#end\n").unwrap(),
+            "local s = [[\n]] --#if false\n--#if false\n\n--#end\n"
+        );
+    }
+
+    #[test]
+    fn long_bracket_in_short_string_is_not_opened() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("local s = '[['\n--#if false\na()\n--#end\n").unwrap(),
+            "local s = '[['\n--#if false\n\n--#end\n"
+        );
+    }
+
+    #[test]
+    fn missing_end_errors() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("return 1\n--#if DEBUG\na()").unwrap_err(),
+            "missing `--#end` for `--#if` (line 2)"
+        );
+    }
+
+    #[test]
+    fn else_without_if_errors() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#else\n").unwrap_err(),
+            "`--#else` without matching `--#if` (line 1)"
+        );
+    }
+
+    #[test]
+    fn end_without_if_errors() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#end\n").unwrap_err(),
+            "`--#end` without matching `--#if` (line 1)"
+        );
+    }
+
+    #[test]
+    fn elseif_after_else_errors() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if DEBUG\n--#else\n--#elseif true\n--#end").unwrap_err(),
+            "`--#elseif` after `--#else` (line 3)"
+        );
+    }
+
+    #[test]
+    fn missing_condition_errors() {
+        pretty_assertions::assert_eq!(
+            preprocess_code("--#if\n--#end").unwrap_err(),
+            "missing condition (line 1)"
+        );
+    }
+
+    #[test]
+    fn unknown_condition_value_errors() {
+        assert!(preprocess_code("--#if value()\n--#end").is_err());
+    }
+}
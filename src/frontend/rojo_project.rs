@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::utils::normalize_path;
+
+use super::{DarkluaError, DarkluaResult, Resources};
+
+const DATA_MODEL_CLASS_NAME: &str = "DataModel";
+
+#[derive(Debug, Deserialize)]
+struct ProjectFile {
+    name: String,
+    tree: ProjectNode,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ProjectPath {
+    Required(PathBuf),
+    Optional { optional: PathBuf },
+}
+
+impl ProjectPath {
+    fn path(&self) -> &Path {
+        match self {
+            Self::Required(path) | Self::Optional { optional: path } => path,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ProjectEntry {
+    Node(ProjectNode),
+    Other(IgnoredAny),
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectNode {
+    #[serde(rename = "$className")]
+    class_name: Option<String>,
+    #[serde(rename = "$path")]
+    path: Option<ProjectPath>,
+    #[serde(flatten)]
+    entries: BTreeMap<String, ProjectEntry>,
+}
+
+impl ProjectNode {
+    fn iter_children(&self) -> impl Iterator<Item = (&String, &ProjectNode)> {
+        self.entries
+            .iter()
+            .filter(|(name, _)| !name.starts_with('$'))
+            .filter_map(|(name, entry)| match entry {
+                ProjectEntry::Node(node) => Some((name, node)),
+                ProjectEntry::Other(_) => None,
+            })
+    }
+}
+
+/// The instance tree described by a Rojo project file, used to map Roblox instances to the
+/// files that create them.
+///
+/// When the root of the project is a `DataModel`, instance paths start with `game`. Otherwise,
+/// they start with the name of the project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RojoProject {
+    mappings: Vec<(Vec<String>, PathBuf)>,
+}
+
+impl RojoProject {
+    /// Parses the content of a Rojo project file. The `$path` values are relative to the given
+    /// `location`, which is the directory containing the project file.
+    pub fn from_json(content: &str, location: impl AsRef<Path>) -> Result<Self, String> {
+        let project: ProjectFile = json5::from_str(content).map_err(|err| err.to_string())?;
+
+        let root_name = if project.tree.class_name.as_deref() == Some(DATA_MODEL_CLASS_NAME) {
+            "game".to_owned()
+        } else {
+            project.name
+        };
+
+        let mut mappings = Vec::new();
+        collect_mappings(
+            &project.tree,
+            vec![root_name],
+            location.as_ref(),
+            &mut mappings,
+        );
+
+        // longer instance paths first, so that the most specific mapping is found first
+        mappings.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        Ok(Self { mappings })
+    }
+
+    /// Returns the file path (without extension) associated with the given instance path, like
+    /// `["game", "ReplicatedStorage", "Module"]`.
+    pub fn get_file_path(&self, instance: &[String]) -> Option<PathBuf> {
+        self.mappings.iter().find_map(|(instance_path, file_path)| {
+            instance.strip_prefix(instance_path.as_slice()).map(|rest| {
+                rest.iter()
+                    .fold(file_path.clone(), |path, name| path.join(name))
+            })
+        })
+    }
+
+    /// Returns the instance path associated with the given file path (without extension). This
+    /// is the inverse of [`get_file_path`](Self::get_file_path).
+    pub fn get_instance_path(&self, path: impl AsRef<Path>) -> Option<Vec<String>> {
+        let path = normalize_path(path);
+
+        self.mappings
+            .iter()
+            .filter_map(|(instance_path, file_path)| {
+                let rest = path.strip_prefix(file_path).ok()?;
+                let rest = rest
+                    .components()
+                    .map(|component| component.as_os_str().to_str().map(ToOwned::to_owned))
+                    .collect::<Option<Vec<_>>>()?;
+                Some((file_path.components().count(), instance_path, rest))
+            })
+            .max_by_key(|(depth, _, _)| *depth)
+            .map(|(_, instance_path, rest)| {
+                let mut instance = instance_path.clone();
+                instance.extend(rest);
+                instance
+            })
+    }
+}
+
+/// Reads and parses the Rojo project file at the given path.
+pub(crate) fn read_project(
+    resources: &Resources,
+    project_path: &Path,
+) -> DarkluaResult<RojoProject> {
+    if !resources.exists(project_path)? {
+        return Err(DarkluaError::resource_not_found(project_path)
+            .context("expected to find Rojo project file as provided by the options"));
+    }
+
+    let content = resources.get(project_path)?;
+    let location = normalize_path(project_path.parent().unwrap_or_else(|| Path::new("")));
+
+    RojoProject::from_json(&content, location)
+        .map_err(|err| DarkluaError::invalid_project_file(project_path).context(err))
+}
+
+fn collect_mappings(
+    node: &ProjectNode,
+    instance: Vec<String>,
+    location: &Path,
+    mappings: &mut Vec<(Vec<String>, PathBuf)>,
+) {
+    if let Some(path) = &node.path {
+        mappings.push((instance.clone(), normalize_path(location.join(path.path()))));
+    }
+
+    for (name, child) in node.iter_children() {
+        let mut child_instance = instance.clone();
+        child_instance.push(name.clone());
+        collect_mappings(child, child_instance, location, mappings);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PLACE_PROJECT: &str = r#"{
+        "name": "place",
+        "tree": {
+            "$className": "DataModel",
+            "ReplicatedStorage": {
+                "$className": "ReplicatedStorage",
+                "Shared": { "$path": "src/shared" },
+                "Packages": { "$path": { "optional": "Packages" } }
+            },
+            "ServerScriptService": {
+                "$className": "ServerScriptService",
+                "$ignoreUnknownInstances": true,
+                "Server": { "$path": "src/server" }
+            },
+            "Workspace": {
+                "$className": "Workspace",
+                "$properties": { "Gravity": 100 }
+            }
+        }
+    }"#;
+
+    fn instance(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    fn place_project() -> RojoProject {
+        RojoProject::from_json(PLACE_PROJECT, "").expect("unable to parse project")
+    }
+
+    #[test]
+    fn get_file_path_of_mapped_instance() {
+        assert_eq!(
+            place_project().get_file_path(&instance(&["game", "ReplicatedStorage", "Shared"])),
+            Some(PathBuf::from("src/shared"))
+        );
+    }
+
+    #[test]
+    fn get_file_path_of_instance_in_mapped_directory() {
+        assert_eq!(
+            place_project().get_file_path(&instance(&[
+                "game",
+                "ReplicatedStorage",
+                "Packages",
+                "Promise"
+            ])),
+            Some(PathBuf::from("Packages/Promise"))
+        );
+    }
+
+    #[test]
+    fn get_file_path_of_unmapped_instance() {
+        assert_eq!(
+            place_project().get_file_path(&instance(&["game", "Workspace", "Part"])),
+            None
+        );
+    }
+
+    #[test]
+    fn get_instance_path_of_file() {
+        assert_eq!(
+            place_project().get_instance_path("src/server/main"),
+            Some(instance(&["game", "ServerScriptService", "Server", "main"]))
+        );
+    }
+
+    #[test]
+    fn get_instance_path_of_unmapped_file() {
+        assert_eq!(place_project().get_instance_path("tests/main"), None);
+    }
+
+    #[test]
+    fn mappings_are_relative_to_project_location() {
+        let project = RojoProject::from_json(PLACE_PROJECT, "game").unwrap();
+
+        assert_eq!(
+            project.get_file_path(&instance(&["game", "ReplicatedStorage", "Shared"])),
+            Some(PathBuf::from("game/src/shared"))
+        );
+    }
+
+    #[test]
+    fn library_project_root_is_named_after_project() {
+        let project =
+            RojoProject::from_json(r#"{ "name": "Library", "tree": { "$path": "src" } }"#, "")
+                .unwrap();
+
+        assert_eq!(
+            project.get_instance_path("src/Module"),
+            Some(instance(&["Library", "Module"]))
+        );
+    }
+
+    #[test]
+    fn invalid_project_errors() {
+        assert!(RojoProject::from_json("{ \"tree\": {} }", "").is_err());
+    }
+}
@@ -6,7 +6,9 @@ use std::{
 
 use elsa::FrozenMap;
 
-use crate::{nodes::Block, DarkluaError, Parser, Resources};
+use crate::{
+    nodes::Block, rules::embed_data_files::parse_data_file, DarkluaError, Parser, Resources,
+};
 
 use super::DarkluaResult;
 
@@ -70,6 +72,23 @@ impl<'a> WorkCache<'a> {
         }
     }
 
+    /// Returns the block created from the content of a data file (like JSON or TOML files),
+    /// which returns the equivalent Lua value.
+    pub fn get_data_block(&self, source: impl AsRef<Path>) -> DarkluaResult<&Block> {
+        let source = source.as_ref();
+        if let Some(block) = self.input_to_block.get(source) {
+            Ok(block)
+        } else {
+            log::trace!("caching data file `{}`", source.display());
+            let content = self.resources.get(source)?;
+            let block = parse_data_file(source, &content)
+                .map_err(|message| DarkluaError::invalid_data_file(source, message))?;
+            Ok(self
+                .input_to_block
+                .insert(source.to_path_buf(), Box::new(block)))
+        }
+    }
+
     fn read_block(&self, source: &Path, parser: &Parser) -> DarkluaResult<Block> {
         if let Some(output_path) = self.input_to_output.get(source) {
             let content = self.resources.get(output_path)?;
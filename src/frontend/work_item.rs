@@ -136,4 +136,13 @@ impl WorkItem {
             WorkStatus::InProgress(progress) => progress.required.len(),
         }
     }
+
+    pub fn required_content(&self) -> impl Iterator<Item = &Path> {
+        match &self.status {
+            WorkStatus::NotStarted => None,
+            WorkStatus::InProgress(progress) => Some(progress.required_content()),
+        }
+        .into_iter()
+        .flatten()
+    }
 }
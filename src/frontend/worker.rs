@@ -1,14 +1,22 @@
-use std::path::Path;
-
-use crate::{rules::ContextBuilder, utils::normalize_path, GeneratorParameters};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    nodes::Block,
+    rules::{require_path::is_data_file, Context, ContextBuilder, INJECT_GLOBAL_VALUE_RULE_NAME},
+    utils::normalize_path,
+    GeneratorParameters, RojoProject,
+};
 
 use super::{
     configuration::Configuration,
+    preprocessor::preprocess,
     resources::Resources,
+    rojo_project::read_project,
     utils::{self, Timer},
     work_cache::WorkCache,
     work_item::{Progress, WorkData, WorkItem, WorkStatus},
-    DarkluaError, DarkluaResult, Options, ProcessResult,
+    DarkluaError, DarkluaResult, NameMap, Options, ProcessResult,
 };
 
 const DEFAULT_CONFIG_PATHS: [&str; 2] = [".darklua.json", ".darklua.json5"];
@@ -18,6 +26,9 @@ pub struct Worker<'a> {
     resources: &'a Resources,
     cache: WorkCache<'a>,
     configuration: Configuration,
+    configuration_location: Option<PathBuf>,
+    project: Option<RojoProject>,
+    name_maps: BTreeMap<PathBuf, NameMap>,
 }
 
 impl<'a> Worker<'a> {
@@ -26,6 +37,9 @@ impl<'a> Worker<'a> {
             resources,
             cache: WorkCache::new(resources),
             configuration: Configuration::default(),
+            configuration_location: None,
+            project: None,
+            name_maps: BTreeMap::new(),
         }
     }
 
@@ -52,6 +66,7 @@ impl<'a> Worker<'a> {
         } else if let Some(config) = options.configuration_path() {
             if self.resources.exists(config)? {
                 self.configuration = self.read_configuration(config)?;
+                self.configuration_location = config.parent().map(Path::to_path_buf);
                 log::info!("using configuration file `{}`", config.display());
             } else {
                 return Err(DarkluaError::resource_not_found(config)
@@ -75,6 +90,8 @@ impl<'a> Worker<'a> {
                     self.configuration = self
                         .read_configuration(configuration_file_path)
                         .map_err(element_to_vec)?;
+                    self.configuration_location =
+                        configuration_file_path.parent().map(Path::to_path_buf);
                     log::info!(
                         "using configuration file `{}`",
                         configuration_file_path.display()
@@ -103,6 +120,11 @@ impl<'a> Worker<'a> {
             self.configuration = self.configuration.with_generator(generator.clone());
         }
 
+        if let Some(project_path) = options.project_path() {
+            self.project = Some(read_project(self.resources, project_path)?);
+            log::info!("using Rojo project file `{}`", project_path.display());
+        }
+
         log::trace!(
             "configuration setup in {}",
             configuration_setup_timer.duration_label()
@@ -162,7 +184,29 @@ impl<'a> Worker<'a> {
             }
 
             if work_left.len() >= work_length {
-                errors.push(DarkluaError::cyclic_work(work_left));
+                // the work can also be blocked by files that are not part of the inputs, so they
+                // will never be processed
+                let sources: HashSet<_> = work_left
+                    .iter()
+                    .map(|work| normalize_path(work.source()))
+                    .collect();
+                let missing_inputs: Vec<_> = work_left
+                    .iter()
+                    .flat_map(|work| {
+                        work.required_content()
+                            .filter(|path| !sources.contains(*path) && !self.cache.contains(path))
+                            .map(|path| {
+                                DarkluaError::required_file_not_in_inputs(path, work.source())
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+
+                if missing_inputs.is_empty() {
+                    errors.push(DarkluaError::cyclic_work(work_left));
+                } else {
+                    errors.extend(missing_inputs);
+                }
                 return ProcessResult::new(success_count, errors).into();
             }
 
@@ -171,6 +215,13 @@ impl<'a> Worker<'a> {
 
         log::info!("executed work in {}", work_timer.duration_label());
 
+        for (path, name_map) in self.name_maps.iter() {
+            match self.resources.write(path, &name_map.to_json()) {
+                Ok(()) => log::info!("name map written to `{}`", path.display()),
+                Err(err) => errors.push(err.into()),
+            }
+        }
+
         ProcessResult::new(success_count, errors).into()
     }
 
@@ -181,6 +232,42 @@ impl<'a> Worker<'a> {
         })
     }
 
+    fn create_context_builder(&self, source: &Path) -> ContextBuilder<'_> {
+        let mut context_builder = ContextBuilder::new(source);
+
+        if let Some(project) = &self.project {
+            context_builder = context_builder.with_project(project);
+        }
+        if let Some(location) = &self.configuration_location {
+            context_builder = context_builder.with_configuration_location(location);
+        }
+
+        context_builder
+    }
+
+    /// Creates the context used to evaluate the preprocessor conditions, which contains the
+    /// data files read by the `inject_global_value` rules.
+    fn create_preprocessor_context(&self, source: &Path) -> DarkluaResult<Context<'_>> {
+        let mut context_builder = self.create_context_builder(source);
+        let empty_block = Block::default();
+
+        for rule in self
+            .configuration
+            .rules()
+            .filter(|rule| rule.get_name() == INJECT_GLOBAL_VALUE_RULE_NAME)
+        {
+            let required_content =
+                rule.require_content(&self.create_context_builder(source).build(), &empty_block);
+
+            for path in required_content.into_iter().map(normalize_path) {
+                let block = self.cache.get_data_block(&path)?;
+                context_builder.insert_block(path, block);
+            }
+        }
+
+        Ok(context_builder.build())
+    }
+
     fn do_work(&mut self, work: WorkItem) -> DarkluaResult<Option<WorkItem>> {
         let (status, data) = work.extract();
         match status {
@@ -189,6 +276,12 @@ impl<'a> Worker<'a> {
 
                 let source = data.source();
                 let content = self.resources.get(source)?;
+                let content = {
+                    let context = self.create_preprocessor_context(source)?;
+                    preprocess(&content, &self.configuration, &context)
+                        .map_err(|message| DarkluaError::preprocessor_error(source, message))?
+                        .into_owned()
+                };
 
                 let parser = self.configuration.build_parser();
 
@@ -225,7 +318,7 @@ impl<'a> Worker<'a> {
             .enumerate()
             .skip(progress.next_rule())
         {
-            let mut context_builder = ContextBuilder::new(&normalized_source);
+            let mut context_builder = self.create_context_builder(&normalized_source);
             log::trace!(
                 "[{}] apply rule `{}`{}",
                 source_display,
@@ -237,7 +330,10 @@ impl<'a> Worker<'a> {
                 }
             );
             let mut required_content: Vec<_> = rule
-                .require_content(&normalized_source, progress.block())
+                .require_content(
+                    &self.create_context_builder(&normalized_source).build(),
+                    progress.block(),
+                )
                 .into_iter()
                 .map(normalize_path)
                 .filter(|path| {
@@ -252,6 +348,29 @@ impl<'a> Worker<'a> {
             required_content.sort();
             required_content.dedup();
 
+            let mut existing_content = Vec::with_capacity(required_content.len());
+            for path in required_content {
+                if self.resources.exists(&path)? {
+                    existing_content.push(path);
+                } else {
+                    log::trace!(
+                        "filtering out `{}` because it does not exist",
+                        path.display()
+                    );
+                }
+            }
+            let required_content = existing_content;
+
+            // data files are not processed, so they can be loaded directly
+            let (data_files, required_content): (Vec<_>, Vec<_>) = required_content
+                .into_iter()
+                .partition(|path| is_data_file(path));
+
+            for path in data_files.iter() {
+                let block = self.cache.get_data_block(path)?;
+                context_builder.insert_block(path, block);
+            }
+
             if !required_content.is_empty() {
                 if required_content
                     .iter()
@@ -306,6 +425,13 @@ impl<'a> Worker<'a> {
 
                     error
                 })?;
+
+            for (mapping, variables) in context.take_renamed_variables() {
+                self.name_maps
+                    .entry(mapping)
+                    .or_default()
+                    .insert(data.output(), variables);
+            }
         }
 
         let rule_time = progress.duration().duration_label();
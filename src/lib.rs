@@ -9,5 +9,8 @@ pub mod process;
 pub mod rules;
 mod utils;
 
-pub use frontend::{process, Configuration, DarkluaError, GeneratorParameters, Options, Resources};
+pub use frontend::{
+    dependency_graph, process, Configuration, DarkluaError, DependencyGraph, GeneratorParameters,
+    NameMap, Options, Resources, RojoProject,
+};
 pub use parser::{Parser, ParserError};
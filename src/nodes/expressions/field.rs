@@ -44,6 +44,11 @@ impl FieldExpression {
         &self.field
     }
 
+    #[inline]
+    pub fn mutate_field(&mut self) -> &mut Identifier {
+        &mut self.field
+    }
+
     pub fn mutate_prefix(&mut self) -> &mut Prefix {
         &mut self.prefix
     }
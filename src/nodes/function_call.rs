@@ -95,6 +95,11 @@ impl FunctionCall {
         self.method.as_ref()
     }
 
+    #[inline]
+    pub fn mutate_method(&mut self) -> Option<&mut Identifier> {
+        self.method.as_mut()
+    }
+
     #[inline]
     pub fn get_prefix(&self) -> &Prefix {
         &self.prefix
@@ -93,6 +93,11 @@ impl FunctionName {
         self.method.as_ref()
     }
 
+    #[inline]
+    pub fn mutate_method(&mut self) -> Option<&mut Identifier> {
+        self.method.as_mut()
+    }
+
     #[inline]
     pub fn get_name(&self) -> &Identifier {
         &self.name
@@ -108,6 +113,11 @@ impl FunctionName {
         &self.field_names
     }
 
+    #[inline]
+    pub fn mutate_field_names(&mut self) -> &mut Vec<Identifier> {
+        &mut self.field_names
+    }
+
     #[inline]
     pub fn mutate_identifier(&mut self) -> &mut Identifier {
         &mut self.name
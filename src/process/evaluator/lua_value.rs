@@ -1,5 +1,7 @@
 use crate::nodes::{Expression, NumberExpression, StringExpression};
 
+use super::TableValue;
+
 /// Represents an evaluated Expression result.
 #[derive(Debug, Clone, PartialEq)]
 pub enum LuaValue {
@@ -8,7 +10,7 @@ pub enum LuaValue {
     Nil,
     Number(f64),
     String(String),
-    Table,
+    Table(TableValue),
     True,
     Unknown,
 }
@@ -17,7 +19,7 @@ impl LuaValue {
     /// As defined in Lua, all values are considered true, except for false and nil. An option is
     /// returned as the LuaValue may be unknown, so it would return none.
     /// ```rust
-    /// # use darklua_core::process::LuaValue;
+    /// # use darklua_core::process::{LuaValue, TableValue};
     ///
     /// // the values considered false
     /// assert!(!LuaValue::False.is_truthy().unwrap());
@@ -25,7 +27,7 @@ impl LuaValue {
     ///
     /// // all the others are true
     /// assert!(LuaValue::True.is_truthy().unwrap());
-    /// assert!(LuaValue::Table.is_truthy().unwrap());
+    /// assert!(LuaValue::from(TableValue::default()).is_truthy().unwrap());
     /// assert!(LuaValue::Number(0.0).is_truthy().unwrap());
     /// assert!(LuaValue::String("hello".to_owned()).is_truthy().unwrap());
     ///
@@ -151,6 +153,12 @@ impl From<f64> for LuaValue {
     }
 }
 
+impl From<TableValue> for LuaValue {
+    fn from(value: TableValue) -> Self {
+        Self::Table(value)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -187,7 +195,7 @@ mod test {
 
     #[test]
     fn table_value_is_truthy() {
-        assert!(LuaValue::Table.is_truthy().unwrap());
+        assert!(LuaValue::from(TableValue::default()).is_truthy().unwrap());
     }
 
     mod number_coercion {
@@ -1,6 +1,10 @@
 mod lua_value;
+mod table_value;
+mod tuple_value;
 
 pub use lua_value::*;
+pub use table_value::*;
+pub use tuple_value::*;
 
 use crate::nodes::*;
 
@@ -8,6 +12,7 @@ use crate::nodes::*;
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Evaluator {
     pure_metamethods: bool,
+    standard_library: bool,
 }
 
 impl Evaluator {
@@ -20,6 +25,14 @@ impl Evaluator {
         self
     }
 
+    /// When evaluating function calls, this value tells the evaluator if the global functions
+    /// from the standard library (like `select`) can be assumed to not be redefined or shadowed.
+    /// This makes it possible to evaluate calls to these functions.
+    pub fn assume_standard_library(mut self) -> Self {
+        self.standard_library = true;
+        self
+    }
+
     pub fn evaluate(&self, expression: &Expression) -> LuaValue {
         match expression {
             Expression::False(_) => LuaValue::False,
@@ -27,42 +40,74 @@ impl Evaluator {
             Expression::Nil(_) => LuaValue::Nil,
             Expression::Number(number) => LuaValue::from(number.compute_value()),
             Expression::String(string) => LuaValue::from(string.get_value()),
-            Expression::Table(_) => LuaValue::Table,
+            Expression::Table(table) => self.evaluate_table(table),
             Expression::True(_) => LuaValue::True,
             Expression::Binary(binary) => self.evaluate_binary(binary),
             Expression::Unary(unary) => self.evaluate_unary(unary),
-            Expression::Parenthese(parenthese) => {
-                // when the evaluator will be able to manage tuples, keep only the first element
-                // of the tuple here (or coerce the tuple to `nil` if it is empty)
-                self.evaluate(parenthese.inner_expression())
-            }
+            Expression::Parenthese(parenthese) => self
+                .evaluate_tuple(parenthese.inner_expression())
+                .coerce_to_single_value(),
             Expression::If(if_expression) => self.evaluate_if(if_expression),
-            Expression::Call(_)
-            | Expression::Field(_)
-            | Expression::Identifier(_)
-            | Expression::Index(_)
-            | Expression::VariableArguments(_) => LuaValue::Unknown,
+            Expression::Field(field) => self.evaluate_field(field),
+            Expression::Index(index) => self.evaluate_index(index),
+            Expression::Call(call) => self.evaluate_call(call).coerce_to_single_value(),
+            Expression::Identifier(_) | Expression::VariableArguments(_) => LuaValue::Unknown,
         }
     }
 
+    /// Evaluates an expression into all the values it returns. Function calls and the variadic
+    /// expression (`...`) can return any number of values, while other expressions always
+    /// return a single value.
+    pub fn evaluate_tuple(&self, expression: &Expression) -> TupleValue {
+        match expression {
+            Expression::Call(call) => self.evaluate_call(call),
+            Expression::VariableArguments(_) => TupleValue::unknown(),
+            _ => TupleValue::singleton(self.evaluate(expression)),
+        }
+    }
+
+    /// Evaluates a list of expressions (like the values of an assignment, the arguments of a
+    /// function call or the values of a return statement). Every expression is truncated to a
+    /// single value, except the last one that is expanded into all its values.
+    pub fn evaluate_expressions<'a, I>(&self, expressions: I) -> TupleValue
+    where
+        I: IntoIterator<Item = &'a Expression>,
+    {
+        let mut tuple = TupleValue::default();
+        let mut iter = expressions.into_iter().peekable();
+
+        while let Some(expression) = iter.next() {
+            if iter.peek().is_some() {
+                tuple.push(self.evaluate(expression));
+            } else {
+                tuple.extend(self.evaluate_tuple(expression));
+            }
+        }
+
+        tuple
+    }
+
+    /// Returns true if the expression may not return exactly one value. Wrapping these
+    /// expressions in parentheses truncates them to a single value.
     pub fn can_return_multiple_values(&self, expression: &Expression) -> bool {
         match expression {
+            Expression::Call(_) | Expression::VariableArguments(_) => {
+                self.evaluate_tuple(expression).len() != Some(1)
+            }
             Expression::Binary(_)
-            | Expression::Call(_)
+            | Expression::False(_)
             | Expression::Field(_)
-            | Expression::Index(_)
-            | Expression::Unary(_)
-            | Expression::VariableArguments(_) => true,
-            Expression::False(_)
             | Expression::Function(_)
             | Expression::Identifier(_)
             | Expression::If(_)
+            | Expression::Index(_)
             | Expression::Nil(_)
             | Expression::Number(_)
             | Expression::Parenthese(_)
             | Expression::String(_)
             | Expression::Table(_)
-            | Expression::True(_) => false,
+            | Expression::True(_)
+            | Expression::Unary(_) => false,
         }
     }
 
@@ -198,14 +243,23 @@ impl Evaluator {
 
     #[inline]
     fn field_has_side_effects(&self, field: &FieldExpression) -> bool {
-        !self.pure_metamethods || self.prefix_has_side_effects(field.get_prefix())
+        let prefix = field.get_prefix();
+        self.prefix_index_has_side_effects(prefix) || self.prefix_has_side_effects(prefix)
     }
 
     #[inline]
     fn index_has_side_effects(&self, index: &IndexExpression) -> bool {
-        !self.pure_metamethods
+        let prefix = index.get_prefix();
+        self.prefix_index_has_side_effects(prefix)
             || self.has_side_effects(index.get_index())
-            || self.prefix_has_side_effects(index.get_prefix())
+            || self.prefix_has_side_effects(prefix)
+    }
+
+    /// Indexing a table created from a table constructor can't have side effects because
+    /// it does not have a metatable.
+    #[inline]
+    fn prefix_index_has_side_effects(&self, prefix: &Prefix) -> bool {
+        !self.pure_metamethods && !matches!(self.evaluate_prefix(prefix), LuaValue::Table(_))
     }
 
     fn prefix_has_side_effects(&self, prefix: &Prefix) -> bool {
@@ -228,12 +282,120 @@ impl Evaluator {
             | LuaValue::Nil
             | LuaValue::Number(_)
             | LuaValue::String(_)
-            | LuaValue::Table
+            | LuaValue::Table(_)
             | LuaValue::True => false,
             LuaValue::Unknown => true,
         }
     }
 
+    fn evaluate_table(&self, table: &TableExpression) -> LuaValue {
+        let mut value = TableValue::default();
+        let last_index = table.len().saturating_sub(1);
+
+        for (index, entry) in table.iter_entries().enumerate() {
+            match entry {
+                TableEntry::Field(entry) => value.insert(
+                    LuaValue::from(entry.get_field().get_name().as_str()),
+                    self.evaluate(entry.get_value()),
+                ),
+                TableEntry::Index(entry) => value.insert(
+                    self.evaluate(entry.get_key()),
+                    self.evaluate(entry.get_value()),
+                ),
+                TableEntry::Value(entry) => {
+                    if index == last_index {
+                        let tuple = self.evaluate_tuple(entry);
+
+                        if tuple.len().is_none() {
+                            value.push_unknown_elements();
+                        }
+
+                        for element in tuple.iter() {
+                            value.push_element(element.clone());
+                        }
+                    } else {
+                        value.push_element(self.evaluate(entry));
+                    }
+                }
+            }
+        }
+
+        LuaValue::Table(value)
+    }
+
+    fn evaluate_call(&self, call: &FunctionCall) -> TupleValue {
+        if self.standard_library && call.get_method().is_none() {
+            if let Prefix::Identifier(identifier) = call.get_prefix() {
+                if identifier.get_name() == "select" {
+                    return self.evaluate_select(call.get_arguments());
+                }
+            }
+        }
+
+        TupleValue::unknown()
+    }
+
+    fn evaluate_arguments(&self, arguments: &Arguments) -> TupleValue {
+        match arguments {
+            Arguments::Tuple(tuple) => self.evaluate_expressions(tuple.iter_values()),
+            Arguments::String(string) => TupleValue::singleton(string.get_value()),
+            Arguments::Table(table) => TupleValue::singleton(self.evaluate_table(table)),
+        }
+    }
+
+    fn evaluate_select(&self, arguments: &Arguments) -> TupleValue {
+        let arguments = self.evaluate_arguments(arguments);
+        let selector = arguments.get(0);
+        let values = arguments.skip(1);
+
+        match selector {
+            LuaValue::String(string) if string == "#" => TupleValue::singleton(
+                values
+                    .len()
+                    .map(|length| LuaValue::from(length as f64))
+                    .unwrap_or(LuaValue::Unknown),
+            ),
+            selector => match selector.number_coercion() {
+                LuaValue::Number(index) if index.fract() == 0.0 && index >= 1.0 => {
+                    values.skip(index as usize - 1)
+                }
+                LuaValue::Number(index) if index.fract() == 0.0 && index < 0.0 => {
+                    let from_end = -index as usize;
+                    match values.len() {
+                        Some(length) if from_end <= length => values.skip(length - from_end),
+                        _ => TupleValue::unknown(),
+                    }
+                }
+                _ => TupleValue::unknown(),
+            },
+        }
+    }
+
+    fn evaluate_field(&self, field: &FieldExpression) -> LuaValue {
+        match self.evaluate_prefix(field.get_prefix()) {
+            LuaValue::Table(table) => {
+                table.get(&LuaValue::from(field.get_field().get_name().as_str()))
+            }
+            _ => LuaValue::Unknown,
+        }
+    }
+
+    fn evaluate_index(&self, index: &IndexExpression) -> LuaValue {
+        match self.evaluate_prefix(index.get_prefix()) {
+            LuaValue::Table(table) => table.get(&self.evaluate(index.get_index())),
+            _ => LuaValue::Unknown,
+        }
+    }
+
+    fn evaluate_prefix(&self, prefix: &Prefix) -> LuaValue {
+        match prefix {
+            Prefix::Field(field) => self.evaluate_field(field),
+            Prefix::Index(index) => self.evaluate_index(index),
+            Prefix::Parenthese(parenthese) => self.evaluate(parenthese.inner_expression()),
+            Prefix::Call(_) | Prefix::Identifier(_) => LuaValue::Unknown,
+        }
+    }
+
     fn evaluate_binary(&self, expression: &BinaryExpression) -> LuaValue {
         match expression.operator() {
             BinaryOperator::And => self
@@ -378,7 +540,13 @@ impl Evaluator {
                     _ => LuaValue::Unknown,
                 }
             }
-            _ => LuaValue::Unknown,
+            UnaryOperator::Length => match self.evaluate(expression.get_expression()) {
+                LuaValue::Table(table) => table
+                    .len()
+                    .map(|length| LuaValue::from(length as f64))
+                    .unwrap_or(LuaValue::Unknown),
+                _ => LuaValue::Unknown,
+            },
         }
     }
 
@@ -436,7 +604,47 @@ mod test {
             => LuaValue::Number(0.0),
         string_wrapped_in_parens(ParentheseExpression::new(StringExpression::from_value("foo")))
             => LuaValue::from("foo"),
-        table_expression(TableExpression::default()) => LuaValue::Table,
+        table_expression(TableExpression::default()) => LuaValue::from(TableValue::default()),
+        table_expression_with_values(
+            TableExpression::default()
+                .append_array_value(true)
+                .append_field("key", 1.0)
+                .append_index(StringExpression::from_value("other"), false)
+        ) => LuaValue::from(
+            TableValue::default()
+                .with_array_element(true)
+                .with_entry("key", 1.0)
+                .with_entry("other", false)
+        ),
+        table_field(FieldExpression::new(
+            ParentheseExpression::new(TableExpression::default().append_field("key", 1.0)),
+            "key",
+        )) => LuaValue::from(1.0),
+        table_missing_field(FieldExpression::new(
+            ParentheseExpression::new(TableExpression::default().append_field("key", 1.0)),
+            "other",
+        )) => LuaValue::Nil,
+        table_index(IndexExpression::new(
+            ParentheseExpression::new(TableExpression::default().append_array_value(true)),
+            1.0,
+        )) => LuaValue::True,
+        table_index_after_call(IndexExpression::new(
+            ParentheseExpression::new(
+                TableExpression::default()
+                    .append_array_value(true)
+                    .append_array_value(FunctionCall::from_name("call"))
+            ),
+            2.0,
+        )) => LuaValue::Unknown,
+        table_index_before_call(IndexExpression::new(
+            ParentheseExpression::new(
+                TableExpression::default()
+                    .append_array_value(true)
+                    .append_array_value(FunctionCall::from_name("call"))
+            ),
+            1.0,
+        )) => LuaValue::True,
+        field_of_identifier(FieldExpression::new(Identifier::new("var"), "key")) => LuaValue::Unknown,
         if_expression_always_true(IfExpression::new(true, 1.0, 0.0)) => LuaValue::from(1.0),
         if_expression_always_false(IfExpression::new(false, 1.0, 0.0)) => LuaValue::from(0.0),
         if_expression_unknown_condition(IfExpression::new(Expression::identifier("test"), 1.0, 0.0))
@@ -447,6 +655,183 @@ mod test {
             => LuaValue::from(0.0),
     );
 
+    mod tuples {
+        use super::*;
+
+        fn select<I: IntoIterator<Item = Expression>>(arguments: I) -> FunctionCall {
+            arguments
+                .into_iter()
+                .fold(FunctionCall::from_name("select"), |call, argument| {
+                    call.with_argument(argument)
+                })
+        }
+
+        macro_rules! evaluate_tuples {
+            ($($name:ident ($expression:expr) => $value:expr),* $(,)?) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let evaluator = Evaluator::default().assume_standard_library();
+                        assert_eq!($value, evaluator.evaluate_tuple(&$expression.into()));
+                    }
+                )*
+            };
+        }
+
+        evaluate_tuples!(
+            single_value(Expression::from(true)) => TupleValue::singleton(true),
+            variadic_expression(Expression::variable_arguments()) => TupleValue::unknown(),
+            unknown_call(FunctionCall::from_name("call")) => TupleValue::unknown(),
+            parenthese_call(Expression::from(FunctionCall::from_name("call")).in_parentheses())
+                => TupleValue::singleton(LuaValue::Unknown),
+            parenthese_variadic(Expression::variable_arguments().in_parentheses())
+                => TupleValue::singleton(LuaValue::Unknown),
+            select_length(select([
+                StringExpression::from_value("#").into(),
+                Expression::from(true),
+                Expression::from(false),
+            ])) => TupleValue::singleton(2.0),
+            select_length_of_variadic(select([
+                StringExpression::from_value("#").into(),
+                Expression::variable_arguments(),
+            ])) => TupleValue::singleton(LuaValue::Unknown),
+            select_first(select([
+                Expression::from(1.0),
+                Expression::from(true),
+                Expression::from(false),
+            ])) => TupleValue::singleton(true).with_value(false),
+            select_second(select([
+                Expression::from(2.0),
+                Expression::from(true),
+                Expression::from(false),
+            ])) => TupleValue::singleton(false),
+            select_past_the_end(select([
+                Expression::from(3.0),
+                Expression::from(true),
+            ])) => TupleValue::default(),
+            select_from_end(select([
+                Expression::from(-1.0),
+                Expression::from(true),
+                Expression::from(false),
+            ])) => TupleValue::singleton(false),
+            select_from_end_past_the_start(select([
+                Expression::from(-3.0),
+                Expression::from(true),
+            ])) => TupleValue::unknown(),
+            select_zero(select([Expression::from(0.0), Expression::from(true)])) => TupleValue::unknown(),
+            select_unknown_index(select([
+                Expression::identifier("index"),
+                Expression::from(true),
+            ])) => TupleValue::unknown(),
+            select_with_call_before_last_argument(select([
+                Expression::from(2.0),
+                FunctionCall::from_name("call").into(),
+                Expression::from(true),
+            ])) => TupleValue::singleton(true),
+            select_method(FunctionCall::from_name("select").with_method("foo")) => TupleValue::unknown(),
+        );
+
+        #[test]
+        fn select_is_unknown_without_standard_library() {
+            let call = select([Expression::from(1.0), Expression::from(true)]);
+
+            assert_eq!(
+                Evaluator::default().evaluate_tuple(&call.into()),
+                TupleValue::unknown()
+            );
+        }
+
+        #[test]
+        fn evaluate_expressions_truncates_all_but_last() {
+            let expressions = vec![
+                Expression::from(true),
+                select([
+                    Expression::from(1.0),
+                    Expression::from(1.0),
+                    Expression::from(2.0),
+                ])
+                .into(),
+                select([
+                    Expression::from(1.0),
+                    Expression::from(3.0),
+                    Expression::from(4.0),
+                ])
+                .into(),
+            ];
+
+            assert_eq!(
+                Evaluator::default()
+                    .assume_standard_library()
+                    .evaluate_expressions(&expressions),
+                TupleValue::singleton(true)
+                    .with_value(1.0)
+                    .with_value(3.0)
+                    .with_value(4.0)
+            );
+        }
+
+        #[test]
+        fn evaluate_expressions_ending_with_variadic() {
+            let expressions = vec![Expression::from(true), Expression::variable_arguments()];
+
+            let tuple = Evaluator::default().evaluate_expressions(&expressions);
+
+            assert_eq!(tuple.get(0), LuaValue::True);
+            assert_eq!(tuple.len(), None);
+        }
+
+        #[test]
+        fn table_with_trailing_select() {
+            let table = TableExpression::default().append_array_value(select([
+                Expression::from(1.0),
+                Expression::from(true),
+                Expression::from(false),
+            ]));
+
+            assert_eq!(
+                Evaluator::default()
+                    .assume_standard_library()
+                    .evaluate(&table.into()),
+                LuaValue::from(
+                    TableValue::default()
+                        .with_array_element(true)
+                        .with_array_element(false)
+                )
+            );
+        }
+
+        macro_rules! can_return_multiple_values {
+            ($($name:ident ($expression:expr) => $value:expr),* $(,)?) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let evaluator = Evaluator::default().assume_standard_library();
+                        assert_eq!(
+                            $value,
+                            evaluator.can_return_multiple_values(&$expression.into())
+                        );
+                    }
+                )*
+            };
+        }
+
+        can_return_multiple_values!(
+            call_can_return_multiple_values(FunctionCall::from_name("call")) => true,
+            variadic_can_return_multiple_values(Expression::variable_arguments()) => true,
+            select_length_returns_one_value(select([
+                StringExpression::from_value("#").into(),
+                Expression::variable_arguments(),
+            ])) => false,
+            select_can_return_zero_value(select([Expression::from(2.0), Expression::from(true)])) => true,
+            field_returns_one_value(FieldExpression::new(Identifier::new("var"), "field")) => false,
+            index_returns_one_value(IndexExpression::new(Identifier::new("var"), 1.0)) => false,
+            binary_returns_one_value(
+                BinaryExpression::new(BinaryOperator::Plus, Expression::identifier("var"), 1.0)
+            ) => false,
+            parenthese_returns_one_value(Expression::from(FunctionCall::from_name("call")).in_parentheses()) => false,
+        );
+    }
+
     mod binary_expressions {
         use super::*;
 
@@ -512,7 +897,7 @@ mod test {
                 BinaryOperator::And,
                 true,
                 TableExpression::default()
-            ) => LuaValue::Table,
+            ) => LuaValue::from(TableValue::default()),
             nil_and_true(
                 BinaryOperator::And,
                 Expression::nil(),
@@ -935,7 +1320,17 @@ mod test {
             not_identifier(Not, Expression::identifier("foo")) => LuaValue::Unknown,
             minus_one(Minus, DecimalNumber::new(1.0)) => LuaValue::from(-1.0),
             minus_negative_number(Minus, DecimalNumber::new(-5.0)) => LuaValue::from(5.0),
-            minus_string_converted_to_number(Minus, StringExpression::from_value("1")) => LuaValue::from(-1.0)
+            minus_string_converted_to_number(Minus, StringExpression::from_value("1")) => LuaValue::from(-1.0),
+            length_empty_table(Length, TableExpression::default()) => LuaValue::from(0.0),
+            length_table(
+                Length,
+                TableExpression::default().append_array_value(true).append_array_value(false)
+            ) => LuaValue::from(2.0),
+            length_table_with_nil(
+                Length,
+                TableExpression::default().append_array_value(Expression::nil())
+            ) => LuaValue::Unknown,
+            length_identifier(Length, Identifier::new("var")) => LuaValue::Unknown
         );
     }
 
@@ -986,6 +1381,15 @@ mod test {
         unary_minus_on_variable => UnaryExpression::new(UnaryOperator::Minus, Identifier::new("var")),
         length_on_variable => UnaryExpression::new(UnaryOperator::Length, Identifier::new("var")),
         field_index => FieldExpression::new(Identifier::new("var"), "field"),
+        field_of_table_field => FieldExpression::new(
+            FieldExpression::new(
+                ParentheseExpression::new(
+                    TableExpression::default().append_field("field", Expression::identifier("var"))
+                ),
+                "field",
+            ),
+            "other",
+        ),
         table_value_with_call_in_entry => TableExpression::default()
             .append_array_value(FunctionCall::from_name("call")),
     );
@@ -1010,6 +1414,15 @@ mod test {
             FunctionCall::from_name("foo"),
         ),
         not_variable => UnaryExpression::new(UnaryOperator::Not, Identifier::new("var")),
+        field_of_table => FieldExpression::new(
+            ParentheseExpression::new(TableExpression::default()),
+            "field",
+        ),
+        index_of_table => IndexExpression::new(
+            ParentheseExpression::new(TableExpression::default()),
+            Expression::identifier("key"),
+        ),
+        length_of_table => UnaryExpression::new(UnaryOperator::Length, TableExpression::default()),
     );
 
     mod assume_pure_metamethods {
@@ -0,0 +1,265 @@
+use super::LuaValue;
+
+/// Represents the content of a table created from a table constructor. Since a table
+/// constructor always creates a new table, there is no metatable that can be involved when
+/// reading its values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableValue {
+    array: Vec<LuaValue>,
+    entries: Vec<(LuaValue, LuaValue)>,
+    unknown_array_tail: bool,
+    unknown_entries: bool,
+}
+
+impl TableValue {
+    pub fn with_array_element<T: Into<LuaValue>>(mut self, value: T) -> Self {
+        self.push_element(value.into());
+        self
+    }
+
+    pub fn with_entry<T: Into<LuaValue>, U: Into<LuaValue>>(mut self, key: T, value: U) -> Self {
+        self.insert(key.into(), value.into());
+        self
+    }
+
+    /// Appends a value to the array part of the table (like a positional value in a table
+    /// constructor).
+    pub fn push_element(&mut self, value: LuaValue) {
+        self.array.push(value);
+    }
+
+    /// Marks the array part of the table to be followed by an unknown number of values. This
+    /// happens when the last value of a table constructor is a function call or `...`.
+    pub fn push_unknown_elements(&mut self) {
+        self.unknown_array_tail = true;
+    }
+
+    /// Assigns a value to a key in the hash part of the table. If the key is not a value that
+    /// can be compared at compile time (like an unknown value or another table), the whole
+    /// content of the table becomes unknown.
+    pub fn insert(&mut self, key: LuaValue, value: LuaValue) {
+        if !is_comparable_key(&key) {
+            self.unknown_entries = true;
+            return;
+        }
+
+        if let Some((_, previous_value)) = self
+            .entries
+            .iter_mut()
+            .find(|(entry_key, _)| entry_key == &key)
+        {
+            *previous_value = value;
+        } else {
+            self.entries.push((key, value));
+        }
+    }
+
+    /// Returns the value associated with the given key. If the key is not found, nil is
+    /// returned unless the table has entries that are unknown.
+    pub fn get(&self, key: &LuaValue) -> LuaValue {
+        if self.unknown_entries || !is_comparable_key(key) {
+            return match key {
+                LuaValue::Nil => LuaValue::Nil,
+                _ => LuaValue::Unknown,
+            };
+        }
+
+        let hash_value = self
+            .entries
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value);
+
+        if let Some(index) = self.get_array_index(key) {
+            if index <= self.array.len() {
+                return if hash_value.is_some() {
+                    // when a key is defined both in the array part and the hash part,
+                    // the value that ends up in the table depends on the Lua implementation
+                    LuaValue::Unknown
+                } else {
+                    self.array[index - 1].clone()
+                };
+            } else if self.unknown_array_tail {
+                return LuaValue::Unknown;
+            }
+        }
+
+        hash_value.cloned().unwrap_or(LuaValue::Nil)
+    }
+
+    /// Returns the result of the length operator (`#`) when it can be determined. When the
+    /// table has holes, the length operator can return any border so it is considered unknown.
+    pub fn len(&self) -> Option<usize> {
+        if self.unknown_entries || self.unknown_array_tail {
+            return None;
+        }
+
+        if self
+            .array
+            .iter()
+            .any(|value| matches!(value, LuaValue::Nil | LuaValue::Unknown))
+        {
+            return None;
+        }
+
+        if self
+            .entries
+            .iter()
+            .any(|(key, _)| self.get_array_index(key).is_some())
+        {
+            return None;
+        }
+
+        Some(self.array.len())
+    }
+
+    /// Returns true if the table has no entries. Returns false if the content is unknown.
+    pub fn is_empty(&self) -> bool {
+        !self.unknown_entries
+            && !self.unknown_array_tail
+            && self.array.is_empty()
+            && self.entries.is_empty()
+    }
+
+    fn get_array_index(&self, key: &LuaValue) -> Option<usize> {
+        match key {
+            LuaValue::Number(value) if value.fract() == 0.0 && *value >= 1.0 => {
+                Some(*value as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[inline]
+fn is_comparable_key(key: &LuaValue) -> bool {
+    match key {
+        LuaValue::False | LuaValue::True | LuaValue::String(_) => true,
+        LuaValue::Number(value) => !value.is_nan(),
+        LuaValue::Function | LuaValue::Nil | LuaValue::Table(_) | LuaValue::Unknown => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn get_array_element() {
+        let table = TableValue::default()
+            .with_array_element("a")
+            .with_array_element("b");
+
+        assert_eq!(table.get(&LuaValue::from(1.0)), LuaValue::from("a"));
+        assert_eq!(table.get(&LuaValue::from(2.0)), LuaValue::from("b"));
+    }
+
+    #[test]
+    fn get_array_element_out_of_bounds_is_nil() {
+        let table = TableValue::default().with_array_element("a");
+
+        assert_eq!(table.get(&LuaValue::from(2.0)), LuaValue::Nil);
+        assert_eq!(table.get(&LuaValue::from(0.0)), LuaValue::Nil);
+    }
+
+    #[test]
+    fn get_array_element_out_of_bounds_with_unknown_tail() {
+        let mut table = TableValue::default().with_array_element("a");
+        table.push_unknown_elements();
+
+        assert_eq!(table.get(&LuaValue::from(1.0)), LuaValue::from("a"));
+        assert_eq!(table.get(&LuaValue::from(2.0)), LuaValue::Unknown);
+    }
+
+    #[test]
+    fn get_field_entry() {
+        let table = TableValue::default().with_entry("key", true);
+
+        assert_eq!(table.get(&LuaValue::from("key")), LuaValue::True);
+        assert_eq!(table.get(&LuaValue::from("other")), LuaValue::Nil);
+    }
+
+    #[test]
+    fn get_field_entry_assigned_twice_returns_last_value() {
+        let table = TableValue::default()
+            .with_entry("key", true)
+            .with_entry("key", false);
+
+        assert_eq!(table.get(&LuaValue::from("key")), LuaValue::False);
+    }
+
+    #[test]
+    fn get_number_entry_defined_in_array_and_hash_part_is_unknown() {
+        let table = TableValue::default()
+            .with_entry(1.0, true)
+            .with_array_element("a");
+
+        assert_eq!(table.get(&LuaValue::from(1.0)), LuaValue::Unknown);
+    }
+
+    #[test]
+    fn get_with_unknown_key_is_unknown() {
+        let table = TableValue::default().with_entry("key", true);
+
+        assert_eq!(table.get(&LuaValue::Unknown), LuaValue::Unknown);
+    }
+
+    #[test]
+    fn get_with_nil_key_is_nil() {
+        let table = TableValue::default().with_entry("key", true);
+
+        assert_eq!(table.get(&LuaValue::Nil), LuaValue::Nil);
+    }
+
+    #[test]
+    fn insert_unknown_key_makes_content_unknown() {
+        let table = TableValue::default()
+            .with_entry("key", true)
+            .with_entry(LuaValue::Unknown, true);
+
+        assert_eq!(table.get(&LuaValue::from("key")), LuaValue::Unknown);
+        assert_eq!(table.len(), None);
+    }
+
+    #[test]
+    fn len_of_empty_table_is_zero() {
+        assert_eq!(TableValue::default().len(), Some(0));
+    }
+
+    #[test]
+    fn len_of_array() {
+        let table = TableValue::default()
+            .with_array_element(1.0)
+            .with_array_element(2.0)
+            .with_entry("key", true);
+
+        assert_eq!(table.len(), Some(2));
+    }
+
+    #[test]
+    fn len_of_array_with_nil_is_unknown() {
+        let table = TableValue::default()
+            .with_array_element(1.0)
+            .with_array_element(LuaValue::Nil)
+            .with_array_element(2.0);
+
+        assert_eq!(table.len(), None);
+    }
+
+    #[test]
+    fn len_of_array_with_number_key_is_unknown() {
+        let table = TableValue::default()
+            .with_array_element(1.0)
+            .with_entry(2.0, true);
+
+        assert_eq!(table.len(), None);
+    }
+
+    #[test]
+    fn len_of_array_with_unknown_tail_is_unknown() {
+        let mut table = TableValue::default().with_array_element(1.0);
+        table.push_unknown_elements();
+
+        assert_eq!(table.len(), None);
+    }
+}
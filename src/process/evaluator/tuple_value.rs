@@ -0,0 +1,164 @@
+use super::LuaValue;
+
+/// Represents the evaluated result of an expression that can return multiple values (like a
+/// function call or the variadic expression `...`), or of a list of expressions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TupleValue {
+    values: Vec<LuaValue>,
+    unknown_tail: bool,
+}
+
+impl TupleValue {
+    /// Returns a tuple where both the number of values and the values are unknown.
+    pub fn unknown() -> Self {
+        Self {
+            values: Vec::new(),
+            unknown_tail: true,
+        }
+    }
+
+    /// Returns a tuple that contains exactly one value.
+    pub fn singleton<T: Into<LuaValue>>(value: T) -> Self {
+        Self {
+            values: vec![value.into()],
+            unknown_tail: false,
+        }
+    }
+
+    pub fn with_value<T: Into<LuaValue>>(mut self, value: T) -> Self {
+        self.push(value.into());
+        self
+    }
+
+    /// Appends a value at the end of the tuple. If the tuple already ends with an unknown
+    /// number of values, the value can't be located so it is ignored.
+    pub fn push(&mut self, value: LuaValue) {
+        if !self.unknown_tail {
+            self.values.push(value);
+        }
+    }
+
+    /// Appends all the values of another tuple at the end of this tuple.
+    pub fn extend(&mut self, other: TupleValue) {
+        if !self.unknown_tail {
+            self.values.extend(other.values);
+            self.unknown_tail = other.unknown_tail;
+        }
+    }
+
+    /// Returns the number of values in the tuple if it is known.
+    pub fn len(&self) -> Option<usize> {
+        if self.unknown_tail {
+            None
+        } else {
+            Some(self.values.len())
+        }
+    }
+
+    /// Returns true if the tuple is known to be empty.
+    pub fn is_empty(&self) -> bool {
+        !self.unknown_tail && self.values.is_empty()
+    }
+
+    /// Returns the value at the given index (starting from zero). Like in Lua, accessing a value
+    /// past the end of the tuple gives `nil`.
+    pub fn get(&self, index: usize) -> LuaValue {
+        match self.values.get(index) {
+            Some(value) => value.clone(),
+            None if self.unknown_tail => LuaValue::Unknown,
+            None => LuaValue::Nil,
+        }
+    }
+
+    /// Returns the tuple without its first values.
+    pub fn skip(mut self, count: usize) -> Self {
+        if count >= self.values.len() {
+            self.values.clear();
+        } else {
+            self.values.drain(..count);
+        }
+        self
+    }
+
+    /// Converts the tuple into a single value, as Lua does when a function call or `...` is
+    /// wrapped in parentheses. An empty tuple gives `nil`.
+    pub fn coerce_to_single_value(self) -> LuaValue {
+        self.get(0)
+    }
+
+    /// Returns an iterator over the values that are known to be in the tuple.
+    pub fn iter(&self) -> impl Iterator<Item = &LuaValue> {
+        self.values.iter()
+    }
+}
+
+impl From<LuaValue> for TupleValue {
+    fn from(value: LuaValue) -> Self {
+        Self::singleton(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_tuple_coerces_to_nil() {
+        assert_eq!(
+            TupleValue::default().coerce_to_single_value(),
+            LuaValue::Nil
+        );
+    }
+
+    #[test]
+    fn unknown_tuple_coerces_to_unknown() {
+        assert_eq!(
+            TupleValue::unknown().coerce_to_single_value(),
+            LuaValue::Unknown
+        );
+    }
+
+    #[test]
+    fn tuple_coerces_to_first_value() {
+        let tuple = TupleValue::singleton(true).with_value(false);
+
+        assert_eq!(tuple.coerce_to_single_value(), LuaValue::True);
+    }
+
+    #[test]
+    fn get_past_the_end_is_nil() {
+        assert_eq!(TupleValue::singleton(true).get(1), LuaValue::Nil);
+    }
+
+    #[test]
+    fn get_past_the_end_of_unknown_tail_is_unknown() {
+        let mut tuple = TupleValue::singleton(true);
+        tuple.extend(TupleValue::unknown());
+
+        assert_eq!(tuple.get(0), LuaValue::True);
+        assert_eq!(tuple.get(1), LuaValue::Unknown);
+        assert_eq!(tuple.len(), None);
+    }
+
+    #[test]
+    fn push_after_unknown_tail_is_ignored() {
+        let mut tuple = TupleValue::unknown();
+        tuple.push(LuaValue::True);
+
+        assert_eq!(tuple.get(0), LuaValue::Unknown);
+    }
+
+    #[test]
+    fn skip_values() {
+        let tuple = TupleValue::singleton(1.0).with_value(2.0).with_value(3.0);
+
+        assert_eq!(tuple.skip(1), TupleValue::singleton(2.0).with_value(3.0));
+    }
+
+    #[test]
+    fn skip_more_values_than_length() {
+        let tuple = TupleValue::singleton(1.0);
+
+        assert!(tuple.skip(3).is_empty());
+    }
+}
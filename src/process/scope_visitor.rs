@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
 
 use crate::nodes::*;
@@ -115,11 +115,13 @@ impl<T: NodeProcessor + Scope> NodeVisitor<T> for ScopeVisitor {
             .iter_mut_expressions()
             .for_each(|expression| Self::visit_expression(expression, scope));
 
+        scope.push();
         statement
             .iter_mut_identifiers()
             .for_each(|identifier| scope.insert(identifier.mutate_name()));
 
         Self::visit_block(statement.mutate_block(), scope);
+        scope.pop();
     }
 
     fn visit_numeric_for(statement: &mut NumericForStatement, scope: &mut T) {
@@ -229,6 +231,63 @@ impl Scope for IdentifierTracker {
     }
 }
 
+/// Keeps track of the variable each identifier refers to. Each new local variable gets a
+/// different id, so two identifiers that resolve to the same id refer to the same variable.
+#[derive(Debug, Default)]
+pub(crate) struct BindingTracker {
+    scopes: Vec<HashMap<String, usize>>,
+    binding_count: usize,
+}
+
+impl BindingTracker {
+    pub fn declare(&mut self, name: &str) -> usize {
+        let id = self.binding_count;
+        self.binding_count += 1;
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), id);
+        } else {
+            let mut scope = HashMap::new();
+            scope.insert(name.to_owned(), id);
+            self.scopes.push(scope);
+        }
+
+        id
+    }
+
+    /// Returns the id of the local variable associated with the given name, or `None` if the
+    /// name refers to a global variable.
+    pub fn resolve(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+    }
+}
+
+impl Scope for BindingTracker {
+    fn push(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn insert(&mut self, identifier: &mut String) {
+        self.declare(identifier);
+    }
+
+    fn insert_local(&mut self, identifier: &mut String, _value: Option<&mut Expression>) {
+        self.declare(identifier);
+    }
+
+    fn insert_local_function(&mut self, function: &mut LocalFunctionStatement) {
+        self.declare(function.get_name());
+    }
+}
+
 // implement Scope on anything that can deref into a Scope
 impl<T, U> Scope for T
 where
@@ -1,11 +1,19 @@
 mod permutator;
+mod random;
 
 pub(crate) use permutator::Permutator;
+pub(crate) use random::SeededRandom;
+
+use crate::generator::{DenseLuaGenerator, LuaGenerator};
+use crate::nodes::Expression;
 
 pub(crate) type CharPermutator = Permutator<std::str::Chars<'static>>;
 
+pub(crate) const IDENTIFIER_CHARACTERS: &str =
+    "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789";
+
 pub(crate) fn identifier_permutator() -> CharPermutator {
-    Permutator::new("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789".chars())
+    Permutator::new(IDENTIFIER_CHARACTERS.chars())
 }
 
 pub(crate) const KEYWORDS: [&str; 21] = [
@@ -48,6 +56,20 @@ pub(crate) fn is_valid_identifier(identifier: &str) -> bool {
         && !matches!(identifier, matches_any_keyword!())
 }
 
+/// Generates compact code for an expression without the tokens (whitespaces and comments) it may
+/// hold, so that it can be used to compare expressions.
+pub(crate) fn expression_code(expression: &Expression) -> String {
+    let mut generator = DenseLuaGenerator::default();
+    generator.write_expression(expression);
+    generator.into_string()
+}
+
+/// Returns true if both expressions produce the same code, ignoring the tokens (whitespaces
+/// and comments) they may hold.
+pub(crate) fn is_same_expression(first: &Expression, second: &Expression) -> bool {
+    first == second || expression_code(first) == expression_code(second)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
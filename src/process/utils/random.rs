@@ -0,0 +1,79 @@
+/// A pseudo-random number generator (SplitMix64) that always produces the same sequence of
+/// numbers from a given seed, so that processing the same code twice gives the same result.
+/// It must not be used for anything related to security.
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// Returns a number between `min` and `max` (both included).
+    pub fn range(&mut self, min: u64, max: u64) -> u64 {
+        debug_assert!(min <= max, "invalid random range");
+        match (max - min).checked_add(1) {
+            Some(length) => min + self.next_u64() % length,
+            None => self.next_u64(),
+        }
+    }
+
+    /// Returns a random index for a collection of the given length.
+    pub fn index(&mut self, length: usize) -> usize {
+        debug_assert!(length > 0, "unable to pick an index in an empty collection");
+        (self.next_u64() % length as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_seed_produces_same_numbers() {
+        let mut first = SeededRandom::new(7);
+        let mut second = SeededRandom::new(7);
+
+        for _ in 0..10 {
+            assert_eq!(first.next_u64(), second.next_u64());
+        }
+    }
+
+    #[test]
+    fn different_seeds_produce_different_numbers() {
+        assert_ne!(
+            SeededRandom::new(1).next_u64(),
+            SeededRandom::new(2).next_u64()
+        );
+    }
+
+    #[test]
+    fn range_stays_within_bounds() {
+        let mut random = SeededRandom::new(0);
+
+        for _ in 0..100 {
+            let value = random.range(3, 5);
+            assert!((3..=5).contains(&value));
+        }
+    }
+
+    #[test]
+    fn range_with_single_value() {
+        assert_eq!(SeededRandom::new(0).range(4, 4), 4);
+    }
+
+    #[test]
+    fn full_range_does_not_overflow() {
+        SeededRandom::new(0).range(0, u64::MAX);
+    }
+}
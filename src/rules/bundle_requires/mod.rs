@@ -0,0 +1,357 @@
+use crate::nodes::{
+    AssignStatement, Block, Expression, FieldExpression, FunctionCall, FunctionExpression,
+    Identifier, IfStatement, IndexExpression, LocalAssignStatement, Prefix, ReturnStatement,
+    Statement, StringExpression, TableEntry, TableExpression, TableFieldEntry, TableIndexEntry,
+    UnaryExpression, UnaryOperator, Variable,
+};
+use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, Rule, RuleConfiguration, RuleConfigurationError, RuleProcessResult, RuleProperties,
+    RulePropertyValue,
+};
+use crate::utils::normalize_path;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::require_path::{
+    collect_require_arguments, find_module_candidates, get_require_argument,
+};
+
+const DEFAULT_MODULES_IDENTIFIER: &str = "__DARKLUA_BUNDLE_MODULES";
+
+struct RequireReplacer<'a, 'b> {
+    context: &'a Context<'b>,
+    modules_identifier: &'a str,
+    source: PathBuf,
+    modules: Vec<(String, Expression)>,
+    loaded_modules: HashSet<String>,
+}
+
+impl<'a, 'b> RequireReplacer<'a, 'b> {
+    fn new(context: &'a Context<'b>, modules_identifier: &'a str) -> Self {
+        Self {
+            context,
+            modules_identifier,
+            source: context.current_path().to_path_buf(),
+            modules: Vec::new(),
+            loaded_modules: HashSet::new(),
+        }
+    }
+
+    fn find_module(&self, argument: &Expression) -> Option<(String, &Block)> {
+        find_module_candidates(argument, &self.source, self.context.project())
+            .into_iter()
+            .map(normalize_path)
+            .find_map(|path| {
+                self.context
+                    .block(&path)
+                    .map(|block| (get_module_name(&path), block))
+            })
+    }
+
+    fn insert_module(&mut self, name: String, block: Block) {
+        if !self.loaded_modules.insert(name.clone()) {
+            return;
+        }
+
+        let mut block = block;
+
+        // when the required module was already bundled, its own modules are merged with the
+        // current modules so that each module is only loaded once
+        for (nested_name, loader) in extract_bundled_modules(&mut block, self.modules_identifier) {
+            if self.loaded_modules.insert(nested_name.clone()) {
+                self.modules.push((nested_name, loader));
+            }
+        }
+
+        // the module can read the arguments given to `require` with `...`
+        self.modules.push((
+            name,
+            FunctionExpression::from_block(block).variadic().into(),
+        ));
+    }
+
+    /// Creates the call that loads the module. Like `require` in Lua, the module receives the
+    /// string given to `require` as its arguments. Other arguments (like Roblox instances) are
+    /// not forwarded, since they may not be valid in the bundled code.
+    fn create_load_call(&self, name: String, argument: Expression) -> FunctionCall {
+        let call = FunctionCall::from_prefix(FieldExpression::new(
+            Prefix::from_name(self.modules_identifier),
+            "load",
+        ))
+        .with_argument(StringExpression::from_value(name));
+
+        match argument {
+            Expression::String(_) => call.with_argument(argument),
+            _ => call,
+        }
+    }
+
+    fn create_modules_table(&self, modules: Vec<(String, Expression)>) -> TableExpression {
+        let identifier = self.modules_identifier;
+        let cache = FieldExpression::new(Prefix::from_name(identifier), "cache");
+        let cached_module = IndexExpression::new(cache, Identifier::new("name"));
+
+        let load_function = FunctionExpression::from_block(
+            Block::default()
+                .with_statement(
+                    LocalAssignStatement::from_variable("cached").with_value(cached_module.clone()),
+                )
+                .with_statement(IfStatement::create(
+                    UnaryExpression::new(UnaryOperator::Not, Identifier::new("cached")),
+                    Block::default()
+                        .with_statement(AssignStatement::from_variable(
+                            Identifier::new("cached"),
+                            TableExpression::new(vec![TableFieldEntry::new(
+                                "value",
+                                FunctionCall::from_prefix(IndexExpression::new(
+                                    Prefix::from_name(identifier),
+                                    Identifier::new("name"),
+                                ))
+                                .with_argument(Expression::variable_arguments()),
+                            )
+                            .into()]),
+                        ))
+                        .with_statement(AssignStatement::from_variable(
+                            cached_module,
+                            Identifier::new("cached"),
+                        )),
+                ))
+                .with_last_statement(
+                    ReturnStatement::one(FieldExpression::new(Identifier::new("cached"), "value"))
+                        .into(),
+                ),
+        )
+        .with_parameter("name")
+        .variadic();
+
+        let mut entries: Vec<TableEntry> = vec![
+            TableFieldEntry::new("cache", TableExpression::new(Vec::new())).into(),
+            TableFieldEntry::new("load", load_function).into(),
+        ];
+
+        entries.extend(modules.into_iter().map(|(name, loader)| {
+            TableIndexEntry::new(StringExpression::from_value(name), loader).into()
+        }));
+
+        TableExpression::new(entries)
+    }
+}
+
+impl<'a, 'b> NodeProcessor for RequireReplacer<'a, 'b> {
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        let module = get_require_argument(call).and_then(|argument| {
+            self.find_module(argument)
+                .map(|(name, block)| (name, block.clone(), argument.clone()))
+        });
+
+        if let Some((name, block, argument)) = module {
+            self.insert_module(name.clone(), block);
+            *call = self.create_load_call(name, argument);
+        }
+    }
+}
+
+fn get_module_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Removes the declaration of the modules table from a block processed by this rule and
+/// returns the modules that were bundled in it.
+fn extract_bundled_modules(
+    block: &mut Block,
+    modules_identifier: &str,
+) -> Vec<(String, Expression)> {
+    if let Some(modules) = get_bundled_modules(block, modules_identifier) {
+        let mut index = 0;
+        block.filter_statements(|_| {
+            index += 1;
+            index > 2
+        });
+
+        modules
+    } else {
+        Vec::new()
+    }
+}
+
+fn get_bundled_modules(
+    block: &Block,
+    modules_identifier: &str,
+) -> Option<Vec<(String, Expression)>> {
+    let mut statements = block.iter_statements();
+
+    match statements.next()? {
+        Statement::LocalAssign(local_assign)
+            if !local_assign.has_values()
+                && local_assign.variables_len() == 1
+                && local_assign.get_variables()[0].get_name() == modules_identifier => {}
+        _ => return None,
+    }
+
+    let assign = match statements.next()? {
+        Statement::Assign(assign) if assign.variables_len() == 1 && assign.values_len() == 1 => {
+            assign
+        }
+        _ => return None,
+    };
+
+    match (&assign.get_variables()[0], assign.iter_values().next()?) {
+        (Variable::Identifier(identifier), Expression::Table(table))
+            if identifier.get_name() == modules_identifier =>
+        {
+            Some(
+                table
+                    .iter_entries()
+                    .filter_map(|entry| match entry {
+                        TableEntry::Index(entry) => match entry.get_key() {
+                            Expression::String(name) => {
+                                Some((name.get_value().to_owned(), entry.get_value().clone()))
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+pub const BUNDLE_REQUIRES_RULE_NAME: &str = "bundle_requires";
+
+/// A rule that inlines the modules loaded with `require` calls into the current file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BundleRequires {
+    modules_identifier: String,
+}
+
+impl Default for BundleRequires {
+    fn default() -> Self {
+        Self {
+            modules_identifier: DEFAULT_MODULES_IDENTIFIER.to_owned(),
+        }
+    }
+}
+
+impl BundleRequires {
+    pub fn with_modules_identifier(mut self, identifier: impl Into<String>) -> Self {
+        self.modules_identifier = identifier.into();
+        self
+    }
+}
+
+impl Rule for BundleRequires {
+    fn process(&self, block: &mut Block, context: &mut Context) -> RuleProcessResult {
+        let mut replacer = RequireReplacer::new(context, &self.modules_identifier);
+        DefaultVisitor::visit_block(block, &mut replacer);
+
+        if replacer.modules.is_empty() {
+            return Ok(());
+        }
+
+        let modules = std::mem::take(&mut replacer.modules);
+        let modules_table = replacer.create_modules_table(modules);
+
+        block.insert_statement(
+            0,
+            AssignStatement::from_variable(
+                Identifier::new(&self.modules_identifier),
+                modules_table,
+            ),
+        );
+        block.insert_statement(
+            0,
+            LocalAssignStatement::from_variable(&self.modules_identifier),
+        );
+
+        Ok(())
+    }
+
+    fn require_content(&self, context: &Context, current_block: &Block) -> Vec<PathBuf> {
+        collect_require_arguments(current_block)
+            .iter()
+            .flat_map(|argument| {
+                find_module_candidates(argument, context.current_path(), context.project())
+            })
+            .collect()
+    }
+}
+
+impl RuleConfiguration for BundleRequires {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "modules_identifier" => match value {
+                    RulePropertyValue::String(identifier) => {
+                        self.modules_identifier = identifier;
+                    }
+                    _ => return Err(RuleConfigurationError::StringExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        BUNDLE_REQUIRES_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if self.modules_identifier != DEFAULT_MODULES_IDENTIFIER {
+            properties.insert(
+                "modules_identifier".to_owned(),
+                RulePropertyValue::String(self.modules_identifier.clone()),
+            );
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> BundleRequires {
+        BundleRequires::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_bundle_requires", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_modules_identifier() {
+        let rule: Box<dyn Rule> = Box::new(new_rule().with_modules_identifier("__MODULES"));
+
+        assert_json_snapshot!("bundle_requires_with_modules_identifier", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'bundle_requires',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
@@ -93,7 +93,7 @@ impl Computer {
                     }
                 }
             }
-            Expression::If(_) => {
+            Expression::If(_) | Expression::Field(_) | Expression::Index(_) => {
                 if !self.evaluator.has_side_effects(expression) {
                     self.evaluator.evaluate(expression).to_expression()
                 } else {
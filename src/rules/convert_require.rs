@@ -0,0 +1,361 @@
+use crate::nodes::{
+    Block, Expression, FieldExpression, FunctionCall, Identifier, IndexExpression, Prefix,
+    StringExpression, TupleArguments,
+};
+use crate::process::utils::is_valid_identifier;
+use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+use crate::utils::normalize_path;
+use crate::RojoProject;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::require_path::{
+    get_instance_path, get_module_instance_path, get_path_names, get_relative_path,
+    get_require_argument, get_script_path,
+};
+
+/// The different ways a module can be referenced by a `require` call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequireMode {
+    /// A string path relative to the current file, like `require("./module")`.
+    Path,
+    /// A Roblox instance path starting from `script`, like `require(script.Parent.module)`.
+    Roblox,
+}
+
+impl RequireMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Roblox => "roblox",
+        }
+    }
+}
+
+impl fmt::Display for RequireMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for RequireMode {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "path" => Ok(Self::Path),
+            "roblox" => Ok(Self::Roblox),
+            _ => Err(format!(
+                "invalid require mode `{}` (expected `path` or `roblox`)",
+                string
+            )),
+        }
+    }
+}
+
+/// Returns the number of names shared at the start of both lists.
+fn common_length(a: &[String], b: &[String]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(a, b)| a == b).count()
+}
+
+struct RequireConverter<'a> {
+    source: PathBuf,
+    project: Option<&'a RojoProject>,
+    current: RequireMode,
+    target: RequireMode,
+}
+
+impl<'a> RequireConverter<'a> {
+    fn convert(&self, argument: &Expression) -> Option<Expression> {
+        match (self.current, self.target) {
+            (RequireMode::Path, RequireMode::Roblox) => self.convert_to_roblox(argument),
+            (RequireMode::Roblox, RequireMode::Path) => self.convert_to_path(argument),
+            _ => None,
+        }
+    }
+
+    /// Returns the instance names of the current script and of the required module. When both
+    /// files are part of the Rojo project, the names come from the project instance tree.
+    fn get_instance_names(
+        &self,
+        script_path: &Path,
+        module_path: &Path,
+    ) -> Option<(Vec<String>, Vec<String>)> {
+        let project_names = self.project.and_then(|project| {
+            Some((
+                project.get_instance_path(script_path)?,
+                project.get_instance_path(module_path)?,
+            ))
+        });
+
+        match project_names {
+            Some(names) => Some(names),
+            None => Some((get_path_names(script_path)?, get_path_names(module_path)?)),
+        }
+    }
+
+    fn convert_to_roblox(&self, argument: &Expression) -> Option<Expression> {
+        let value = match argument {
+            Expression::String(string) => string.get_value(),
+            _ => return None,
+        };
+
+        let module_path =
+            get_module_instance_path(&normalize_path(get_relative_path(value, &self.source)?))?;
+        let script_path = get_script_path(&self.source)?;
+
+        let (script_names, module_names) = self.get_instance_names(&script_path, &module_path)?;
+        let common = common_length(&script_names, &module_names);
+        let mut children = module_names[common..].iter();
+
+        let mut prefix = if common == 1 && script_names[0] == "game" {
+            // the script and the module are in different services
+            let service = children.next()?;
+            FunctionCall::from_name("game")
+                .with_method("GetService")
+                .with_argument(StringExpression::from_value(service))
+                .into()
+        } else if common == script_names.len() && common == module_names.len() {
+            return None;
+        } else {
+            let mut prefix = Prefix::from_name("script");
+            for _ in common..script_names.len() {
+                prefix = FieldExpression::new(prefix, "Parent").into();
+            }
+            prefix
+        };
+
+        for name in children {
+            prefix = if is_valid_identifier(name) && name != "Parent" {
+                FieldExpression::new(prefix, Identifier::new(name)).into()
+            } else {
+                IndexExpression::new(prefix, StringExpression::from_value(name)).into()
+            };
+        }
+
+        Some(prefix.into())
+    }
+
+    fn convert_to_path(&self, argument: &Expression) -> Option<Expression> {
+        if matches!(argument, Expression::String(_)) {
+            return None;
+        }
+
+        let mut module_path =
+            normalize_path(get_instance_path(argument, &self.source, self.project)?);
+
+        // a project can map an instance to a single file
+        if matches!(
+            module_path
+                .extension()
+                .and_then(|extension| extension.to_str()),
+            Some("lua") | Some("luau")
+        ) {
+            module_path.set_extension("");
+        }
+        let directory = self.source.parent().unwrap_or_else(|| Path::new(""));
+
+        let directory_names = get_path_names(directory)?;
+        let module_names = get_path_names(&module_path)?;
+        let common = common_length(&directory_names, &module_names);
+
+        if common == module_names.len() {
+            return None;
+        }
+
+        let parent_count = directory_names.len() - common;
+        let mut path = if parent_count == 0 {
+            "./".to_owned()
+        } else {
+            "../".repeat(parent_count)
+        };
+        path.push_str(&module_names[common..].join("/"));
+
+        Some(StringExpression::from_value(path).into())
+    }
+}
+
+impl<'a> NodeProcessor for RequireConverter<'a> {
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        let converted = get_require_argument(call).and_then(|argument| self.convert(argument));
+
+        if let Some(argument) = converted {
+            call.set_arguments(TupleArguments::default().with_argument(argument).into());
+        }
+    }
+}
+
+pub const CONVERT_REQUIRE_RULE_NAME: &str = "convert_require";
+
+/// A rule that converts the arguments of `require` calls between string paths and Roblox
+/// instance paths.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConvertRequire {
+    current: RequireMode,
+    target: RequireMode,
+}
+
+impl Default for ConvertRequire {
+    fn default() -> Self {
+        Self {
+            current: RequireMode::Path,
+            target: RequireMode::Roblox,
+        }
+    }
+}
+
+impl ConvertRequire {
+    pub fn new(current: RequireMode, target: RequireMode) -> Self {
+        Self { current, target }
+    }
+}
+
+impl FlawlessRule for ConvertRequire {
+    fn flawless_process(&self, block: &mut Block, context: &mut Context) {
+        if self.current == self.target {
+            return;
+        }
+
+        let mut converter = RequireConverter {
+            source: normalize_path(context.current_path()),
+            project: context.project(),
+            current: self.current,
+            target: self.target,
+        };
+        DefaultVisitor::visit_block(block, &mut converter);
+    }
+}
+
+impl RuleConfiguration for ConvertRequire {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "current" | "target" => {
+                    let mode = match value {
+                        RulePropertyValue::String(mode) => mode.parse().map_err(|message| {
+                            RuleConfigurationError::UnexpectedValue {
+                                property: key.clone(),
+                                message,
+                            }
+                        })?,
+                        _ => return Err(RuleConfigurationError::StringExpected(key)),
+                    };
+
+                    if key == "current" {
+                        self.current = mode;
+                    } else {
+                        self.target = mode;
+                    }
+                }
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        CONVERT_REQUIRE_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+        let default = Self::default();
+
+        if self.current != default.current {
+            properties.insert("current".to_owned(), self.current.as_str().into());
+        }
+        if self.target != default.target {
+            properties.insert("target".to_owned(), self.target.as_str().into());
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> ConvertRequire {
+        ConvertRequire::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_convert_require", rule);
+    }
+
+    #[test]
+    fn serialize_roblox_to_path_rule() {
+        let rule: Box<dyn Rule> =
+            Box::new(ConvertRequire::new(RequireMode::Roblox, RequireMode::Path));
+
+        assert_json_snapshot!("convert_require_roblox_to_path", rule);
+    }
+
+    #[test]
+    fn configure_without_properties_uses_defaults() {
+        let rule = json5::from_str::<Box<dyn Rule>>("'convert_require'").unwrap();
+
+        pretty_assertions::assert_eq!(
+            rule.serialize_to_properties(),
+            new_rule().serialize_to_properties()
+        );
+    }
+
+    #[test]
+    fn configure_with_only_target() {
+        let mut rule = new_rule();
+        let mut properties = RuleProperties::new();
+        properties.insert("target".to_owned(), "path".into());
+
+        rule.configure(properties).unwrap();
+
+        pretty_assertions::assert_eq!(
+            rule,
+            ConvertRequire::new(RequireMode::Path, RequireMode::Path)
+        );
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'convert_require',
+            current: 'path',
+            target: 'roblox',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+
+    #[test]
+    fn configure_with_invalid_mode_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'convert_require',
+            current: 'path',
+            target: 'node',
+        }"#,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use crate::nodes::{
+    Arguments, Block, Expression, FunctionCall, FunctionExpression, FunctionStatement, Identifier,
+    LocalAssignStatement, LocalFunctionStatement, NumberExpression, StringExpression,
+    TupleArguments,
+};
+use crate::process::utils::{expression_code, identifier_permutator, is_valid_identifier};
+use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor, Scope, ScopeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+};
+
+use std::collections::{HashMap, HashSet};
+
+use super::rename_variables::globals;
+use super::variable_usage::{get_available_locals, VariableUsage, MAX_UPVALUES};
+use super::verify_no_rule_properties;
+
+// the length of `local` followed by a space, the `=` symbol and the space or new line that
+// separates the declaration from the next statement
+const DECLARATION_LENGTH: usize = 8;
+
+struct Constant {
+    value: Expression,
+    code: String,
+    count: usize,
+}
+
+impl Constant {
+    /// Returns the number of bytes saved by replacing each occurrence of the constant with an
+    /// identifier of the given length, including the cost of declaring the local variable.
+    fn savings(&self, name_length: usize) -> isize {
+        let replaced = self.count * self.code.len();
+        let cost = self.count * name_length + DECLARATION_LENGTH + name_length + self.code.len();
+
+        replaced as isize - cost as isize
+    }
+}
+
+#[derive(Default)]
+struct ConstantCounter {
+    constants: Vec<Constant>,
+    indexes: HashMap<String, usize>,
+}
+
+impl ConstantCounter {
+    fn count(&mut self, value: Expression) {
+        let code = expression_code(&value);
+
+        if let Some(index) = self.indexes.get(&code) {
+            self.constants[*index].count += 1;
+        } else {
+            self.indexes.insert(code.clone(), self.constants.len());
+            self.constants.push(Constant {
+                value,
+                code,
+                count: 1,
+            });
+        }
+    }
+}
+
+impl NodeProcessor for ConstantCounter {
+    fn process_string_expression(&mut self, string: &mut StringExpression) {
+        self.count(Expression::String(string.clone()));
+    }
+
+    fn process_number_expression(&mut self, number: &mut NumberExpression) {
+        self.count(Expression::Number(number.clone()));
+    }
+}
+
+/// The variables captured by a function that is being visited.
+struct FunctionFrame {
+    // the number of scopes opened before the function
+    scope_start: usize,
+    upvalues: HashSet<usize>,
+    constants: HashSet<String>,
+}
+
+/// The number of upvalues of a function and the constants used in it.
+struct FunctionCaptures {
+    upvalues: usize,
+    constants: HashSet<String>,
+}
+
+/// Finds the upvalues captured by each function and the constants used in each function,
+/// since each constant moved to a local variable becomes a new upvalue of the functions (and
+/// their parent functions) that use it.
+#[derive(Default)]
+struct CaptureCollector {
+    // the binding id and the number of enclosing functions of each visible local variable
+    scopes: Vec<HashMap<String, (usize, usize)>>,
+    binding_count: usize,
+    frames: Vec<FunctionFrame>,
+    entering_function: bool,
+    functions: Vec<FunctionCaptures>,
+}
+
+impl CaptureCollector {
+    fn declare(&mut self, name: &str) {
+        let binding = (self.binding_count, self.frames.len());
+        self.binding_count += 1;
+
+        if self.scopes.is_empty() {
+            self.scopes.push(HashMap::new());
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), binding);
+        }
+    }
+
+    fn use_constant(&mut self, expression: Expression) {
+        let code = expression_code(&expression);
+
+        for frame in self.frames.iter_mut() {
+            frame.constants.insert(code.clone());
+        }
+    }
+}
+
+impl Scope for CaptureCollector {
+    fn push(&mut self) {
+        if self.entering_function {
+            self.entering_function = false;
+            self.frames.push(FunctionFrame {
+                scope_start: self.scopes.len(),
+                upvalues: HashSet::new(),
+                constants: HashSet::new(),
+            });
+        }
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop(&mut self) {
+        self.scopes.pop();
+
+        if matches!(self.frames.last(), Some(frame) if frame.scope_start == self.scopes.len()) {
+            if let Some(frame) = self.frames.pop() {
+                self.functions.push(FunctionCaptures {
+                    upvalues: frame.upvalues.len(),
+                    constants: frame.constants,
+                });
+            }
+        }
+    }
+
+    fn insert(&mut self, identifier: &mut String) {
+        self.declare(identifier);
+    }
+
+    fn insert_local(&mut self, identifier: &mut String, _value: Option<&mut Expression>) {
+        self.declare(identifier);
+    }
+
+    fn insert_local_function(&mut self, function: &mut LocalFunctionStatement) {
+        self.declare(function.get_name());
+    }
+}
+
+impl NodeProcessor for CaptureCollector {
+    fn process_function_expression(&mut self, _: &mut FunctionExpression) {
+        self.entering_function = true;
+    }
+
+    fn process_function_statement(&mut self, _: &mut FunctionStatement) {
+        self.entering_function = true;
+    }
+
+    fn process_local_function_statement(&mut self, _: &mut LocalFunctionStatement) {
+        self.entering_function = true;
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        let binding = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(identifier.get_name()))
+            .copied();
+
+        // the variable is an upvalue of every function opened after its declaration
+        if let Some((id, depth)) = binding {
+            for frame in self.frames.iter_mut().skip(depth) {
+                frame.upvalues.insert(id);
+            }
+        }
+    }
+
+    fn process_string_expression(&mut self, string: &mut StringExpression) {
+        self.use_constant(Expression::String(string.clone()));
+    }
+
+    fn process_number_expression(&mut self, number: &mut NumberExpression) {
+        self.use_constant(Expression::Number(number.clone()));
+    }
+}
+
+struct ConstantReplacer {
+    identifiers: HashMap<String, Identifier>,
+}
+
+impl ConstantReplacer {
+    fn get_identifier(&self, expression: &Expression) -> Option<Identifier> {
+        self.identifiers.get(&expression_code(expression)).cloned()
+    }
+}
+
+impl NodeProcessor for ConstantReplacer {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if matches!(expression, Expression::String(_) | Expression::Number(_)) {
+            if let Some(identifier) = self.get_identifier(expression) {
+                *expression = Expression::Identifier(identifier);
+            }
+        }
+    }
+
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        if let Arguments::String(string) = call.get_arguments() {
+            if let Some(identifier) = self.get_identifier(&Expression::String(string.clone())) {
+                call.set_arguments(TupleArguments::default().with_argument(identifier).into());
+            }
+        }
+    }
+}
+
+pub const DEDUPLICATE_CONSTANTS_RULE_NAME: &str = "deduplicate_constants";
+
+/// A rule that stores string and number constants repeated in a file into local variables.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DeduplicateConstants {}
+
+impl FlawlessRule for DeduplicateConstants {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut counter = ConstantCounter::default();
+        DefaultVisitor::visit_block(block, &mut counter);
+
+        let mut constants: Vec<Constant> = counter
+            .constants
+            .into_iter()
+            .filter(|constant| constant.count > 1 && constant.savings(1) > 0)
+            .collect();
+
+        if constants.is_empty() {
+            return;
+        }
+
+        constants.sort_by(|a, b| {
+            b.savings(1)
+                .cmp(&a.savings(1))
+                .then_with(|| a.code.cmp(&b.code))
+        });
+
+        let mut usage = VariableUsage::default();
+        DefaultVisitor::visit_block(block, &mut usage);
+
+        // avoid the same globals as `rename_variables` so that the new variables never shadow
+        // a global used by the code
+        let mut used_names: HashSet<String> = usage.names;
+        used_names.extend(
+            globals::DEFAULT
+                .iter()
+                .chain(globals::ROBLOX.iter())
+                .map(ToString::to_string),
+        );
+
+        let mut names = identifier_permutator()
+            .filter(|name| is_valid_identifier(name) && !used_names.contains(name));
+        let mut next_name = names.next();
+
+        let available_locals = get_available_locals(block);
+        let mut declarations = Vec::new();
+        let mut identifiers = HashMap::new();
+
+        let mut captures = CaptureCollector::default();
+        ScopeVisitor::visit_block(block, &mut captures);
+        let mut functions = captures.functions;
+
+        for constant in constants {
+            if declarations.len() >= available_locals {
+                break;
+            }
+
+            let exceeds_upvalues = functions.iter().any(|function| {
+                function.constants.contains(&constant.code) && function.upvalues >= MAX_UPVALUES
+            });
+            if exceeds_upvalues {
+                continue;
+            }
+
+            let name = match next_name.take() {
+                Some(name) => name,
+                None => break,
+            };
+
+            if constant.savings(name.len()) <= 0 {
+                next_name = Some(name);
+                continue;
+            }
+
+            for function in functions.iter_mut() {
+                if function.constants.contains(&constant.code) {
+                    function.upvalues += 1;
+                }
+            }
+
+            let identifier = Identifier::new(name);
+            identifiers.insert(constant.code, identifier.clone());
+            declarations
+                .push(LocalAssignStatement::from_variable(identifier).with_value(constant.value));
+
+            next_name = names.next();
+        }
+
+        let mut replacer = ConstantReplacer { identifiers };
+        DefaultVisitor::visit_block(block, &mut replacer);
+
+        for (index, declaration) in declarations.into_iter().enumerate() {
+            block.insert_statement(index, declaration);
+        }
+    }
+}
+
+impl RuleConfiguration for DeduplicateConstants {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        verify_no_rule_properties(&properties)?;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEDUPLICATE_CONSTANTS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        RuleProperties::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> DeduplicateConstants {
+        DeduplicateConstants::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_deduplicate_constants", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'deduplicate_constants',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
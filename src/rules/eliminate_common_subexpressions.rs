@@ -0,0 +1,635 @@
+use crate::nodes::{
+    Arguments, BinaryOperator, Block, Expression, FunctionCall, Identifier, LastStatement,
+    LocalAssignStatement, Prefix, Statement, TableEntry, TableExpression, Variable,
+};
+use crate::process::utils::{expression_code, is_same_expression};
+use crate::process::{DefaultVisitor, Evaluator, LuaValue, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use super::variable_usage::{generate_variable_name, ExpressionInspector, VariableUsage};
+
+/// Counts the function calls in a part of the code.
+#[derive(Debug, Default)]
+struct CallCounter {
+    calls: usize,
+}
+
+impl CallCounter {
+    fn count_expression(expression: &Expression) -> usize {
+        let mut counter = Self::default();
+        DefaultVisitor::visit_expression(&mut expression.clone(), &mut counter);
+        counter.calls
+    }
+}
+
+impl NodeProcessor for CallCounter {
+    fn process_function_call(&mut self, _: &mut FunctionCall) {
+        self.calls += 1;
+    }
+}
+
+/// Visits the expressions of a statement that are always evaluated once when the statement
+/// runs. The given function is called on each expression with the number of function calls
+/// that may run before it in the statement, and can return an identifier to replace the
+/// expression with.
+struct ExpressionWalker<F: FnMut(&Expression, usize) -> Option<Identifier>> {
+    callback: F,
+    calls: usize,
+}
+
+impl<F: FnMut(&Expression, usize) -> Option<Identifier>> ExpressionWalker<F> {
+    fn new(callback: F) -> Self {
+        Self { callback, calls: 0 }
+    }
+
+    fn walk_statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Assign(assign) => {
+                assign
+                    .mutate_variables()
+                    .iter_mut()
+                    .for_each(|variable| self.walk_variable(variable));
+                assign
+                    .iter_mut_values()
+                    .for_each(|value| self.walk_expression(value));
+            }
+            Statement::Call(call) => self.walk_call(call),
+            Statement::CompoundAssign(assign) => {
+                self.walk_variable(assign.mutate_variable());
+                self.walk_expression(assign.mutate_value());
+            }
+            Statement::GenericFor(generic_for) => generic_for
+                .iter_mut_expressions()
+                .for_each(|expression| self.walk_expression(expression)),
+            Statement::If(if_statement) => {
+                if let Some(branch) = if_statement.mutate_branches().first_mut() {
+                    self.walk_expression(branch.mutate_condition());
+                }
+            }
+            Statement::LocalAssign(assign) => assign
+                .iter_mut_values()
+                .for_each(|value| self.walk_expression(value)),
+            Statement::NumericFor(numeric_for) => {
+                self.walk_expression(numeric_for.mutate_start());
+                self.walk_expression(numeric_for.mutate_end());
+                if let Some(step) = numeric_for.mutate_step() {
+                    self.walk_expression(step);
+                }
+            }
+            // the condition of a while loop is evaluated on each iteration, so it can't be
+            // replaced with a value computed before the loop
+            Statement::Do(_)
+            | Statement::Function(_)
+            | Statement::LocalFunction(_)
+            | Statement::Repeat(_)
+            | Statement::While(_) => {}
+        }
+    }
+
+    fn walk_last_statement(&mut self, statement: &mut LastStatement) {
+        if let LastStatement::Return(statement) = statement {
+            statement
+                .iter_mut_expressions()
+                .for_each(|expression| self.walk_expression(expression));
+        }
+    }
+
+    fn walk_variable(&mut self, variable: &mut Variable) {
+        match variable {
+            Variable::Identifier(_) => {}
+            Variable::Field(field) => self.walk_prefix(field.mutate_prefix()),
+            Variable::Index(index) => {
+                self.walk_prefix(index.mutate_prefix());
+                self.walk_expression(index.mutate_index());
+            }
+        }
+    }
+
+    fn walk_call(&mut self, call: &mut FunctionCall) {
+        self.walk_prefix(call.mutate_prefix());
+
+        match call.mutate_arguments() {
+            Arguments::Tuple(tuple) => tuple
+                .iter_mut_values()
+                .for_each(|value| self.walk_expression(value)),
+            Arguments::Table(table) => self.walk_table(table),
+            Arguments::String(_) => {}
+        }
+
+        self.calls += 1;
+    }
+
+    fn walk_table(&mut self, table: &mut TableExpression) {
+        for entry in table.iter_mut_entries() {
+            match entry {
+                TableEntry::Field(field) => self.walk_expression(field.mutate_value()),
+                TableEntry::Index(index) => {
+                    self.walk_expression(index.mutate_key());
+                    self.walk_expression(index.mutate_value());
+                }
+                TableEntry::Value(value) => self.walk_expression(value),
+            }
+        }
+    }
+
+    fn walk_expression(&mut self, expression: &mut Expression) {
+        if let Some(identifier) = (self.callback)(expression, self.calls) {
+            *expression = Expression::Identifier(identifier);
+            return;
+        }
+
+        match expression {
+            Expression::Binary(binary) => {
+                self.walk_expression(binary.mutate_left());
+
+                // the right operand of `and` and `or` is not always evaluated, but the calls
+                // it contains may run before the next expressions
+                if matches!(binary.operator(), BinaryOperator::And | BinaryOperator::Or) {
+                    self.calls += CallCounter::count_expression(binary.right());
+                } else {
+                    self.walk_expression(binary.mutate_right());
+                }
+            }
+            Expression::Call(call) => self.walk_call(call),
+            Expression::Field(field) => self.walk_prefix(field.mutate_prefix()),
+            Expression::If(if_expression) => {
+                self.walk_expression(if_expression.mutate_condition());
+
+                self.calls += CallCounter::count_expression(if_expression.get_result())
+                    + if_expression
+                        .iter_branches()
+                        .map(|branch| {
+                            CallCounter::count_expression(branch.get_condition())
+                                + CallCounter::count_expression(branch.get_result())
+                        })
+                        .sum::<usize>()
+                    + CallCounter::count_expression(if_expression.get_else_result());
+            }
+            Expression::Index(index) => {
+                self.walk_prefix(index.mutate_prefix());
+                self.walk_expression(index.mutate_index());
+            }
+            Expression::Parenthese(parenthese) => {
+                self.walk_expression(parenthese.mutate_inner_expression())
+            }
+            Expression::Table(table) => self.walk_table(table),
+            Expression::Unary(unary) => self.walk_expression(unary.mutate_expression()),
+            Expression::False(_)
+            | Expression::Function(_)
+            | Expression::Identifier(_)
+            | Expression::Nil(_)
+            | Expression::Number(_)
+            | Expression::String(_)
+            | Expression::True(_)
+            | Expression::VariableArguments(_) => {}
+        }
+    }
+
+    fn walk_prefix(&mut self, prefix: &mut Prefix) {
+        if matches!(prefix, Prefix::Field(_) | Prefix::Index(_)) {
+            if let Some(identifier) = (self.callback)(&Expression::from(prefix.clone()), self.calls)
+            {
+                *prefix = Prefix::Identifier(identifier);
+                return;
+            }
+        }
+
+        match prefix {
+            Prefix::Call(call) => self.walk_call(call),
+            Prefix::Field(field) => self.walk_prefix(field.mutate_prefix()),
+            Prefix::Identifier(_) => {}
+            Prefix::Index(index) => {
+                self.walk_prefix(index.mutate_prefix());
+                self.walk_expression(index.mutate_index());
+            }
+            Prefix::Parenthese(parenthese) => {
+                self.walk_expression(parenthese.mutate_inner_expression())
+            }
+        }
+    }
+}
+
+/// The effects of a statement that can change the value of an expression evaluated after it.
+struct StatementEffects {
+    usage: VariableUsage,
+    declared: Vec<String>,
+    calls: usize,
+}
+
+impl StatementEffects {
+    fn new(statement: &Statement) -> Self {
+        let mut usage = VariableUsage::default();
+        DefaultVisitor::visit_statement(&mut statement.clone(), &mut usage);
+
+        let mut counter = CallCounter::default();
+        DefaultVisitor::visit_statement(&mut statement.clone(), &mut counter);
+
+        let declared = match statement {
+            Statement::LocalAssign(assign) => assign
+                .iter_variables()
+                .map(|variable| variable.get_name().to_owned())
+                .collect(),
+            Statement::LocalFunction(function) => vec![function.get_name().to_owned()],
+            _ => Vec::new(),
+        };
+
+        Self {
+            usage,
+            declared,
+            calls: counter.calls,
+        }
+    }
+
+    fn last_statement(statement: &LastStatement) -> Self {
+        let mut usage = VariableUsage::default();
+        let mut calls = 0;
+        if let LastStatement::Return(statement) = statement {
+            statement.iter_expressions().for_each(|expression| {
+                DefaultVisitor::visit_expression(&mut expression.clone(), &mut usage);
+                calls += CallCounter::count_expression(expression);
+            });
+        }
+
+        Self {
+            usage,
+            declared: Vec::new(),
+            calls,
+        }
+    }
+}
+
+/// The location of an expression: the index of its statement and the number of function calls
+/// that may run before it in that statement.
+type Occurrence = (usize, usize);
+
+/// An expression that appears more than once in a block.
+struct Candidate {
+    expression: Expression,
+    occurrences: Vec<Occurrence>,
+    identifiers: Vec<String>,
+    table_roots: Vec<Option<String>>,
+}
+
+impl Candidate {
+    fn is_modified_by(&self, effects: &StatementEffects) -> bool {
+        self.identifiers
+            .iter()
+            .any(|name| effects.usage.assigned.contains(name) || effects.declared.contains(name))
+            || (!self.table_roots.is_empty() && effects.usage.has_field_assignments)
+    }
+}
+
+struct Processor {
+    file_usage: VariableUsage,
+    names: HashSet<String>,
+    evaluator: Evaluator,
+}
+
+impl Processor {
+    fn new(mut file_usage: VariableUsage, evaluator: Evaluator) -> Self {
+        Self {
+            names: mem::take(&mut file_usage.names),
+            file_usage,
+            evaluator,
+        }
+    }
+
+    fn is_candidate(&self, expression: &Expression) -> bool {
+        matches!(
+            expression,
+            Expression::Binary(_)
+                | Expression::Field(_)
+                | Expression::If(_)
+                | Expression::Index(_)
+                | Expression::Unary(_)
+        ) && !self.evaluator.has_side_effects(expression)
+            && self.evaluator.evaluate(expression) == LuaValue::Unknown
+    }
+
+    fn collect_candidates(&self, block: &mut Block) -> Vec<Candidate> {
+        let mut candidates: Vec<Candidate> = Vec::new();
+        let mut candidate_indexes: HashMap<String, usize> = HashMap::new();
+
+        let mut collect = |expression: &Expression, occurrence: Occurrence| {
+            if !self.is_candidate(expression) {
+                return;
+            }
+
+            let code = expression_code(expression);
+
+            if let Some(index) = candidate_indexes.get(&code) {
+                candidates[*index].occurrences.push(occurrence);
+                return;
+            }
+
+            let mut inspector = ExpressionInspector::default();
+            DefaultVisitor::visit_expression(&mut expression.clone(), &mut inspector);
+
+            if inspector.has_unmovable_expression {
+                return;
+            }
+
+            candidate_indexes.insert(code, candidates.len());
+            candidates.push(Candidate {
+                expression: expression.clone(),
+                occurrences: vec![occurrence],
+                identifiers: inspector.identifiers,
+                table_roots: inspector.table_roots,
+            });
+        };
+
+        let statement_count = block.iter_statements().count();
+
+        for (statement_index, statement) in block.iter_mut_statements().enumerate() {
+            ExpressionWalker::new(|expression: &Expression, calls| {
+                collect(expression, (statement_index, calls));
+                None
+            })
+            .walk_statement(statement);
+        }
+
+        if let Some(last_statement) = block.mutate_last_statement() {
+            ExpressionWalker::new(|expression: &Expression, calls| {
+                collect(expression, (statement_count, calls));
+                None
+            })
+            .walk_last_statement(last_statement);
+        }
+
+        candidates
+    }
+
+    /// Returns true if a function call could change the value of the expression: a function can
+    /// assign any global variable, any local variable assigned somewhere in the file and any
+    /// field of a table.
+    fn is_affected_by_calls(&self, candidate: &Candidate) -> bool {
+        !candidate.table_roots.is_empty()
+            || candidate.identifiers.iter().any(|name| {
+                !self.file_usage.declared.contains(name) || self.file_usage.assigned.contains(name)
+            })
+    }
+
+    /// Finds the range where the expression keeps the same value and appears at least twice.
+    /// The range starts at the statement of its first occurrence and ends at its last
+    /// occurrence.
+    fn find_range(
+        &self,
+        candidate: &Candidate,
+        effects: &[StatementEffects],
+    ) -> Option<(usize, Occurrence)> {
+        let affected_by_calls = self.is_affected_by_calls(candidate);
+        let occurrences = &candidate.occurrences;
+        let mut first = 0;
+
+        while first < occurrences.len() {
+            let (start, start_calls) = occurrences[first];
+
+            // the new variable is computed before the statement of the first occurrence
+            if affected_by_calls && start_calls > 0 {
+                first += 1;
+                continue;
+            }
+
+            let mut end = occurrences[first];
+            let mut next = first + 1;
+
+            while let Some(&occurrence) = occurrences.get(next) {
+                let modified = (end.0..occurrence.0)
+                    .any(|index| candidate.is_modified_by(&effects[index]))
+                    || (affected_by_calls && has_calls_between(end, occurrence, effects));
+
+                if modified {
+                    break;
+                }
+
+                end = occurrence;
+                next += 1;
+            }
+
+            if next - first > 1 {
+                return Some((start, end));
+            }
+
+            first = next;
+        }
+
+        None
+    }
+
+    fn eliminate_next_expression(&mut self, block: &mut Block) -> bool {
+        let candidates = self.collect_candidates(block);
+
+        if candidates
+            .iter()
+            .all(|candidate| candidate.occurrences.len() < 2)
+        {
+            return false;
+        }
+
+        let mut effects: Vec<_> = block.iter_statements().map(StatementEffects::new).collect();
+        if let Some(last_statement) = block.get_last_statement() {
+            effects.push(StatementEffects::last_statement(last_statement));
+        }
+
+        let mut best: Option<(&Candidate, usize, usize, Occurrence)> = None;
+
+        for candidate in candidates.iter() {
+            if candidate.occurrences.len() < 2 {
+                continue;
+            }
+
+            let size = expression_code(&candidate.expression).len();
+
+            if best.is_some_and(|(_, best_size, _, _)| best_size >= size) {
+                continue;
+            }
+
+            if let Some((start, end)) = self.find_range(candidate, &effects) {
+                best = Some((candidate, size, start, end));
+            }
+        }
+
+        let (candidate, _, start, (end, end_calls)) = match best {
+            Some(best) => best,
+            None => return false,
+        };
+
+        let identifier = Identifier::new(generate_variable_name(
+            &candidate.expression,
+            &mut self.names,
+        ));
+
+        for (index, statement) in block
+            .iter_mut_statements()
+            .enumerate()
+            .skip(start)
+            .take(end - start + 1)
+        {
+            ExpressionWalker::new(|expression: &Expression, calls| {
+                // in the last statement, the occurrences after the range are kept
+                (is_same_expression(expression, &candidate.expression)
+                    && (index < end || calls <= end_calls))
+                    .then(|| identifier.clone())
+            })
+            .walk_statement(statement);
+        }
+
+        if end == block.iter_statements().count() {
+            if let Some(last_statement) = block.mutate_last_statement() {
+                ExpressionWalker::new(|expression: &Expression, calls| {
+                    (is_same_expression(expression, &candidate.expression) && calls <= end_calls)
+                        .then(|| identifier.clone())
+                })
+                .walk_last_statement(last_statement);
+            }
+        }
+
+        block.insert_statement(
+            start,
+            LocalAssignStatement::from_variable(identifier.clone())
+                .with_value(candidate.expression.clone()),
+        );
+
+        true
+    }
+}
+
+/// Returns true if a function may be called between two occurrences of an expression.
+fn has_calls_between(from: Occurrence, to: Occurrence, effects: &[StatementEffects]) -> bool {
+    let (from_statement, from_calls) = from;
+    let (to_statement, to_calls) = to;
+
+    if from_statement == to_statement {
+        to_calls > from_calls
+    } else {
+        effects[from_statement].calls > from_calls
+            || (from_statement + 1..to_statement).any(|index| effects[index].calls > 0)
+            || to_calls > 0
+    }
+}
+
+impl NodeProcessor for Processor {
+    fn process_block(&mut self, block: &mut Block) {
+        while self.eliminate_next_expression(block) {}
+    }
+}
+
+pub const ELIMINATE_COMMON_SUBEXPRESSIONS_RULE_NAME: &str = "eliminate_common_subexpressions";
+
+/// A rule that stores expressions that are computed multiple times in a block into a local
+/// variable.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct EliminateCommonSubexpressions {
+    assume_pure_metamethods: bool,
+}
+
+impl EliminateCommonSubexpressions {
+    pub fn with_pure_metamethods(mut self, assume_pure_metamethods: bool) -> Self {
+        self.assume_pure_metamethods = assume_pure_metamethods;
+        self
+    }
+
+    fn build_evaluator(&self) -> Evaluator {
+        if self.assume_pure_metamethods {
+            Evaluator::default().assume_pure_metamethods()
+        } else {
+            Evaluator::default()
+        }
+    }
+}
+
+impl FlawlessRule for EliminateCommonSubexpressions {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut file_usage = VariableUsage::default();
+        DefaultVisitor::visit_block(&mut block.clone(), &mut file_usage);
+
+        let mut processor = Processor::new(file_usage, self.build_evaluator());
+        DefaultVisitor::visit_block(block, &mut processor);
+    }
+}
+
+impl RuleConfiguration for EliminateCommonSubexpressions {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "assume_pure_metamethods" => match value {
+                    RulePropertyValue::Boolean(value) => {
+                        self.assume_pure_metamethods = value;
+                    }
+                    _ => return Err(RuleConfigurationError::BooleanExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        ELIMINATE_COMMON_SUBEXPRESSIONS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if self.assume_pure_metamethods {
+            properties.insert(
+                "assume_pure_metamethods".to_owned(),
+                RulePropertyValue::Boolean(self.assume_pure_metamethods),
+            );
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> EliminateCommonSubexpressions {
+        EliminateCommonSubexpressions::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_eliminate_common_subexpressions", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_pure_metamethods() {
+        let rule: Box<dyn Rule> = Box::new(new_rule().with_pure_metamethods(true));
+
+        assert_json_snapshot!(
+            "eliminate_common_subexpressions_with_pure_metamethods",
+            rule
+        );
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'eliminate_common_subexpressions',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
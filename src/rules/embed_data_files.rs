@@ -0,0 +1,247 @@
+use crate::nodes::{
+    Block, Expression, FunctionCall, LastStatement, Prefix, ReturnStatement, Statement,
+    StringExpression,
+};
+use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    verify_no_rule_properties, Context, Rule, RuleConfiguration, RuleConfigurationError,
+    RuleProcessResult, RuleProperties, RulePropertyValue,
+};
+use crate::utils::normalize_path;
+
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+use super::inject_value::property_to_expression;
+use super::require_path::{
+    collect_require_arguments, find_data_file_candidates, get_require_argument,
+};
+
+/// Converts the content of a data file into a block that returns the equivalent Lua value.
+/// JSON and TOML files are converted to tables and other files are converted to strings.
+pub(crate) fn parse_data_file(path: &Path, content: &str) -> Result<Block, String> {
+    let value = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => json5::from_str::<RulePropertyValue>(content)
+            .map(property_to_expression)
+            .map_err(|err| format!("invalid json: {}", err))?,
+        Some("toml") => toml::from_str::<toml::Value>(content)
+            .map(toml_to_property)
+            .map(property_to_expression)
+            .map_err(|err| format!("invalid toml: {}", err))?,
+        _ => StringExpression::from_value(content).into(),
+    };
+
+    Ok(Block::default().with_last_statement(ReturnStatement::one(value).into()))
+}
+
+/// Converts a TOML value into a property value. Datetimes are converted to their string form.
+fn toml_to_property(value: toml::Value) -> RulePropertyValue {
+    match value {
+        toml::Value::String(value) => RulePropertyValue::String(value),
+        toml::Value::Integer(value) => match usize::try_from(value) {
+            Ok(value) => RulePropertyValue::Usize(value),
+            Err(_) => RulePropertyValue::Float(value as f64),
+        },
+        toml::Value::Float(value) => RulePropertyValue::Float(value),
+        toml::Value::Boolean(value) => RulePropertyValue::Boolean(value),
+        toml::Value::Datetime(datetime) => RulePropertyValue::String(datetime.to_string()),
+        toml::Value::Array(array) => {
+            RulePropertyValue::List(array.into_iter().map(toml_to_property).collect())
+        }
+        toml::Value::Table(table) => RulePropertyValue::Map(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_property(value)))
+                .collect(),
+        ),
+    }
+}
+
+pub(crate) fn get_returned_value(block: &Block) -> Option<&Expression> {
+    match block.get_last_statement() {
+        Some(LastStatement::Return(statement)) if statement.len() == 1 => {
+            statement.iter_expressions().next()
+        }
+        _ => None,
+    }
+}
+
+struct DataFileReplacer<'a, 'b> {
+    context: &'a Context<'b>,
+}
+
+impl<'a, 'b> DataFileReplacer<'a, 'b> {
+    fn find_data_value(&self, call: &FunctionCall) -> Option<Expression> {
+        let argument = get_require_argument(call)?;
+
+        find_data_file_candidates(
+            argument,
+            self.context.current_path(),
+            self.context.project(),
+        )
+        .into_iter()
+        .map(normalize_path)
+        .find_map(|path| self.context.block(path))
+        .and_then(get_returned_value)
+        .cloned()
+    }
+}
+
+impl<'a, 'b> NodeProcessor for DataFileReplacer<'a, 'b> {
+    fn process_block(&mut self, block: &mut Block) {
+        // requiring a data file does not have any side effect
+        block.filter_statements(|statement| match statement {
+            Statement::Call(call) => self.find_data_value(call).is_none(),
+            _ => true,
+        });
+    }
+
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if let Expression::Call(call) = expression {
+            if let Some(value) = self.find_data_value(call) {
+                *expression = value;
+            }
+        }
+    }
+
+    fn process_prefix_expression(&mut self, prefix: &mut Prefix) {
+        if let Prefix::Call(call) = prefix {
+            if let Some(value) = self.find_data_value(call) {
+                *prefix = value.into();
+            }
+        }
+    }
+}
+
+pub const EMBED_DATA_FILES_RULE_NAME: &str = "embed_data_files";
+
+/// A rule that replaces `require` calls to JSON, TOML or text files with their content.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct EmbedDataFiles {}
+
+impl Rule for EmbedDataFiles {
+    fn process(&self, block: &mut Block, context: &mut Context) -> RuleProcessResult {
+        let mut processor = DataFileReplacer { context };
+        DefaultVisitor::visit_block(block, &mut processor);
+        Ok(())
+    }
+
+    fn require_content(&self, context: &Context, current_block: &Block) -> Vec<PathBuf> {
+        collect_require_arguments(current_block)
+            .iter()
+            .flat_map(|argument| {
+                find_data_file_candidates(argument, context.current_path(), context.project())
+            })
+            .collect()
+    }
+}
+
+impl RuleConfiguration for EmbedDataFiles {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        verify_no_rule_properties(&properties)?;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        EMBED_DATA_FILES_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        RuleProperties::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> EmbedDataFiles {
+        EmbedDataFiles::default()
+    }
+
+    fn parse_data(path: &str, content: &str) -> Block {
+        parse_data_file(Path::new(path), content).expect("unable to parse data file")
+    }
+
+    fn parse_lua(code: &str) -> Block {
+        crate::Parser::default()
+            .parse(code)
+            .expect("unable to parse code")
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_embed_data_files", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'embed_data_files',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+
+    #[test]
+    fn parse_json_file() {
+        pretty_assertions::assert_eq!(
+            parse_data("strings.json", r#"{ "title": "Shop", "items": [1, 2] }"#),
+            parse_lua("return { items = { 1, 2 }, title = 'Shop' }")
+        );
+    }
+
+    #[test]
+    fn parse_toml_file() {
+        pretty_assertions::assert_eq!(
+            parse_data("config.toml", "name = 'game'\n[limits]\nplayers = 12\n"),
+            parse_lua("return { limits = { players = 12 }, name = 'game' }")
+        );
+    }
+
+    #[test]
+    fn parse_toml_file_with_dates() {
+        pretty_assertions::assert_eq!(
+            parse_data(
+                "release.toml",
+                "date = 2024-05-27\ntime = 07:32:00\npublished = 2024-05-27T07:32:00Z\n"
+            ),
+            parse_lua(
+                "return { date = '2024-05-27', published = '2024-05-27T07:32:00Z', time = '07:32:00' }"
+            )
+        );
+    }
+
+    #[test]
+    fn parse_toml_file_with_negative_integer() {
+        pretty_assertions::assert_eq!(
+            parse_data("config.toml", "offset = -4\n"),
+            parse_lua("return { offset = -4 }")
+        );
+    }
+
+    #[test]
+    fn parse_text_file() {
+        pretty_assertions::assert_eq!(
+            parse_data("license.txt", "MIT\n"),
+            parse_lua("return 'MIT\\n'")
+        );
+    }
+
+    #[test]
+    fn parse_invalid_json_file_errors() {
+        assert!(parse_data_file(Path::new("data.json"), "{ a: ").is_err());
+    }
+}
@@ -0,0 +1,528 @@
+use crate::nodes::{
+    Arguments, BinaryExpression, BinaryOperator, Block, Expression, FieldExpression, FunctionCall,
+    Identifier, IndexExpression, LocalAssignStatement, Prefix, StringExpression, TableEntry,
+    TableExpression, TupleArguments,
+};
+use crate::process::utils::is_valid_identifier;
+use crate::process::{NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+use crate::Parser;
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::require_path::get_require_argument;
+
+const CHAR_FUNCTION_NAME: &str = "__DARKLUA_CHAR";
+const CONCAT_FUNCTION_NAME: &str = "__DARKLUA_CONCAT";
+const DECODE_FUNCTION_NAME: &str = "__DARKLUA_DECODE_STRING";
+// keeps the number of arguments of each `string.char` call below the register limit of Lua 5.1
+const MAX_CHAR_ARGUMENTS: usize = 100;
+
+// the XOR operation is written with arithmetic operators because Lua 5.1 has no bitwise operators
+const DECODE_FUNCTION_CODE: &str = r#"
+local function __DARKLUA_DECODE_STRING(bytes, key)
+    local characters = {}
+    for i = 1, #bytes do
+        local a, b, value, bit = bytes[i], (key + i) % 256, 0, 1
+        while a > 0 or b > 0 do
+            if a % 2 ~= b % 2 then
+                value = value + bit
+            end
+            a, b, bit = (a - a % 2) / 2, (b - b % 2) / 2, bit * 2
+        end
+        characters[i] = __DARKLUA_CHAR(value)
+    end
+    return __DARKLUA_CONCAT(characters)
+end
+"#;
+
+/// The different ways strings can be encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// Rebuilds the string from its bytes with `string.char`.
+    #[default]
+    Char,
+    /// Stores the bytes of the string XOR-ed with a key, decoded at runtime by a function
+    /// added at the top of the file.
+    Xor,
+}
+
+impl StringEncoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Char => "char",
+            Self::Xor => "xor",
+        }
+    }
+}
+
+impl fmt::Display for StringEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for StringEncoding {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "char" => Ok(Self::Char),
+            "xor" => Ok(Self::Xor),
+            _ => Err(format!(
+                "invalid string encoding method `{}` (expected `char` or `xor`)",
+                string
+            )),
+        }
+    }
+}
+
+/// Returns `true` if the value matches the pattern, where `*` matches any sequence of
+/// characters.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+
+    let mut rest = match value.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let last = match parts.pop() {
+        Some(last) => last,
+        None => return rest.is_empty(),
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Derives the XOR key of a string from its content, so that the same string is always
+/// encoded the same way.
+fn get_key(value: &str) -> u8 {
+    let hash = value.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    (hash % 255 + 1) as u8
+}
+
+fn xor_bytes(value: &str, key: u8) -> Vec<u8> {
+    value
+        .bytes()
+        .enumerate()
+        .map(|(index, byte)| byte ^ ((usize::from(key) + index + 1) % 256) as u8)
+        .collect()
+}
+
+fn is_field_key(expression: &Expression) -> bool {
+    matches!(expression, Expression::String(string) if is_valid_identifier(string.get_value()))
+}
+
+/// Returns `true` if the call is a `require` call with a string path, which must stay a
+/// string so that it can be resolved by the other rules and tools.
+fn is_require_call_with_string(call: &FunctionCall) -> bool {
+    match call.get_arguments() {
+        Arguments::String(_) => {
+            call.get_method().is_none()
+                && matches!(call.get_prefix(), Prefix::Identifier(identifier) if identifier.get_name() == "require")
+        }
+        _ => matches!(get_require_argument(call), Some(Expression::String(_))),
+    }
+}
+
+struct StringEncoder<'a> {
+    rule: &'a EncodeStrings,
+    uses_char: bool,
+    uses_decoder: bool,
+}
+
+impl<'a> StringEncoder<'a> {
+    fn new(rule: &'a EncodeStrings) -> Self {
+        Self {
+            rule,
+            uses_char: false,
+            uses_decoder: false,
+        }
+    }
+
+    fn should_encode(&self, value: &str) -> bool {
+        !value.is_empty()
+            && (self.rule.include.is_empty()
+                || self
+                    .rule
+                    .include
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, value)))
+            && !self
+                .rule
+                .exclude
+                .iter()
+                .any(|pattern| matches_pattern(pattern, value))
+    }
+
+    fn encode(&mut self, value: &str) -> Expression {
+        self.uses_char = true;
+
+        match self.rule.method {
+            StringEncoding::Char => {
+                let bytes: Vec<u8> = value.bytes().collect();
+
+                bytes
+                    .chunks(MAX_CHAR_ARGUMENTS)
+                    .map(|chunk| {
+                        let arguments = chunk
+                            .iter()
+                            .fold(TupleArguments::default(), |arguments, byte| {
+                                arguments.with_argument(*byte)
+                            });
+                        Expression::from(
+                            FunctionCall::from_name(CHAR_FUNCTION_NAME).with_arguments(arguments),
+                        )
+                    })
+                    .rev()
+                    .reduce(|right, left| {
+                        BinaryExpression::new(BinaryOperator::Concat, left, right).into()
+                    })
+                    .expect("encoded string should not be empty")
+            }
+            StringEncoding::Xor => {
+                self.uses_decoder = true;
+
+                let key = get_key(value);
+                let bytes = xor_bytes(value, key)
+                    .into_iter()
+                    .fold(TableExpression::default(), |table, byte| {
+                        table.append_array_value(byte)
+                    });
+
+                FunctionCall::from_name(DECODE_FUNCTION_NAME)
+                    .with_argument(bytes)
+                    .with_argument(key)
+                    .into()
+            }
+        }
+    }
+
+    fn insert_functions(&self, block: &mut Block) {
+        if self.uses_decoder {
+            let mut decoder = Parser::default()
+                .parse(DECODE_FUNCTION_CODE)
+                .expect("string decoder code should be valid");
+
+            for statement in decoder.take_statements().into_iter().rev() {
+                block.insert_statement(0, statement);
+            }
+
+            block.insert_statement(
+                0,
+                LocalAssignStatement::from_variable(CONCAT_FUNCTION_NAME)
+                    .with_value(FieldExpression::new(Identifier::new("table"), "concat")),
+            );
+        }
+
+        if self.uses_char {
+            block.insert_statement(
+                0,
+                LocalAssignStatement::from_variable(CHAR_FUNCTION_NAME)
+                    .with_value(FieldExpression::new(Identifier::new("string"), "char")),
+            );
+        }
+    }
+}
+
+impl<'a> NodeProcessor for StringEncoder<'a> {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if let Expression::String(string) = expression {
+            if self.should_encode(string.get_value()) {
+                *expression = self.encode(string.get_value());
+            }
+        }
+    }
+}
+
+/// A visitor that does not visit the string keys that can be converted to fields by the
+/// `convert_index_to_field` rule or the paths given to `require` calls, and that converts
+/// string arguments to a tuple so they can be encoded.
+struct StringEncoderVisitor;
+
+impl<'a> NodeVisitor<StringEncoder<'a>> for StringEncoderVisitor {
+    fn visit_function_call(call: &mut FunctionCall, encoder: &mut StringEncoder<'a>) {
+        encoder.process_function_call(call);
+
+        Self::visit_prefix_expression(call.mutate_prefix(), encoder);
+
+        if !is_require_call_with_string(call) {
+            Self::visit_arguments(call.mutate_arguments(), encoder);
+        }
+    }
+
+    fn visit_index_expression(index: &mut IndexExpression, encoder: &mut StringEncoder<'a>) {
+        encoder.process_index_expression(index);
+
+        Self::visit_prefix_expression(index.mutate_prefix(), encoder);
+
+        if !is_field_key(index.get_index()) {
+            Self::visit_expression(index.mutate_index(), encoder);
+        }
+    }
+
+    fn visit_arguments(arguments: &mut Arguments, encoder: &mut StringEncoder<'a>) {
+        if let Arguments::String(string) = arguments {
+            encoder.process_string_expression(string);
+
+            if !encoder.should_encode(string.get_value()) {
+                return;
+            }
+
+            let string = StringExpression::from_value(string.get_value());
+            *arguments = TupleArguments::default().with_argument(string).into();
+        }
+
+        match arguments {
+            Arguments::Table(table) => Self::visit_table(table, encoder),
+            Arguments::Tuple(expressions) => expressions
+                .iter_mut_values()
+                .for_each(|expression| Self::visit_expression(expression, encoder)),
+            Arguments::String(_) => {}
+        }
+    }
+
+    fn visit_table(table: &mut TableExpression, encoder: &mut StringEncoder<'a>) {
+        encoder.process_table_expression(table);
+
+        table.iter_mut_entries().for_each(|entry| match entry {
+            TableEntry::Field(entry) => Self::visit_expression(entry.mutate_value(), encoder),
+            TableEntry::Index(entry) => {
+                if !is_field_key(entry.get_key()) {
+                    Self::visit_expression(entry.mutate_key(), encoder);
+                }
+                Self::visit_expression(entry.mutate_value(), encoder);
+            }
+            TableEntry::Value(value) => Self::visit_expression(value, encoder),
+        });
+    }
+}
+
+pub const ENCODE_STRINGS_RULE_NAME: &str = "encode_strings";
+
+/// A rule that replaces string literals with code that builds them at runtime.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct EncodeStrings {
+    method: StringEncoding,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl EncodeStrings {
+    pub fn new(method: StringEncoding) -> Self {
+        Self {
+            method,
+            ..Default::default()
+        }
+    }
+
+    /// Only encode strings matching the given pattern, where `*` matches any sequence of
+    /// characters.
+    pub fn with_include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Do not encode strings matching the given pattern, where `*` matches any sequence of
+    /// characters.
+    pub fn with_exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+}
+
+impl FlawlessRule for EncodeStrings {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut encoder = StringEncoder::new(self);
+        StringEncoderVisitor::visit_block(block, &mut encoder);
+        encoder.insert_functions(block);
+    }
+}
+
+impl RuleConfiguration for EncodeStrings {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "method" => match value {
+                    RulePropertyValue::String(method) => {
+                        self.method = method.parse().map_err(|message| {
+                            RuleConfigurationError::UnexpectedValue {
+                                property: key.clone(),
+                                message,
+                            }
+                        })?;
+                    }
+                    _ => return Err(RuleConfigurationError::StringExpected(key)),
+                },
+                "include" => match value {
+                    RulePropertyValue::StringList(patterns) => {
+                        self.include = patterns;
+                    }
+                    _ => return Err(RuleConfigurationError::StringListExpected(key)),
+                },
+                "exclude" => match value {
+                    RulePropertyValue::StringList(patterns) => {
+                        self.exclude = patterns;
+                    }
+                    _ => return Err(RuleConfigurationError::StringListExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        ENCODE_STRINGS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if self.method != StringEncoding::default() {
+            properties.insert("method".to_owned(), self.method.as_str().into());
+        }
+
+        if !self.include.is_empty() {
+            properties.insert(
+                "include".to_owned(),
+                RulePropertyValue::StringList(self.include.clone()),
+            );
+        }
+
+        if !self.exclude.is_empty() {
+            properties.insert(
+                "exclude".to_owned(),
+                RulePropertyValue::StringList(self.exclude.clone()),
+            );
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> EncodeStrings {
+        EncodeStrings::default()
+    }
+
+    /// Decodes the bytes the same way as the injected Lua function.
+    fn decode(bytes: &[u8], key: u8) -> String {
+        let decoded = bytes
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| {
+                let (mut a, mut b) = (u32::from(*byte), (u32::from(key) + index as u32 + 1) % 256);
+                let (mut value, mut bit) = (0, 1);
+                while a > 0 || b > 0 {
+                    if a % 2 != b % 2 {
+                        value += bit;
+                    }
+                    a /= 2;
+                    b /= 2;
+                    bit *= 2;
+                }
+                value as u8
+            })
+            .collect();
+        String::from_utf8(decoded).unwrap()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_encode_strings", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_properties() {
+        let rule: Box<dyn Rule> = Box::new(
+            EncodeStrings::new(StringEncoding::Xor)
+                .with_include("Remote*")
+                .with_exclude("*Debug"),
+        );
+
+        assert_json_snapshot!("encode_strings_with_properties", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'encode_strings',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+
+    #[test]
+    fn configure_with_invalid_method_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'encode_strings',
+            method: 'base64',
+        }"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn xor_bytes_can_be_decoded() {
+        for value in ["a", "GetPlayerData", "\u{e9}t\u{e9}", "\0\u{ff}\n"] {
+            let key = get_key(value);
+            pretty_assertions::assert_eq!(decode(&xor_bytes(value, key), key), value);
+        }
+    }
+
+    #[test]
+    fn key_is_never_zero() {
+        assert!((0..1000).all(|index| get_key(&index.to_string()) != 0));
+    }
+
+    #[test]
+    fn pattern_without_wildcard_matches_exact_value() {
+        assert!(matches_pattern("Remote", "Remote"));
+        assert!(!matches_pattern("Remote", "RemoteEvent"));
+    }
+
+    #[test]
+    fn pattern_with_wildcards() {
+        assert!(matches_pattern("Remote*", "RemoteEvent"));
+        assert!(matches_pattern("*Event", "RemoteEvent"));
+        assert!(matches_pattern("*mote*", "RemoteEvent"));
+        assert!(matches_pattern("R*t*t", "RemoteEvent"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("*Function", "RemoteEvent"));
+        assert!(!matches_pattern("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,539 @@
+use crate::nodes::{
+    AssignStatement, BinaryExpression, BinaryOperator, Block, Expression, FunctionExpression,
+    FunctionName, FunctionStatement, GenericForStatement, Identifier, IfStatement, LastStatement,
+    LocalAssignStatement, LocalFunctionStatement, NumericForStatement, Statement, Variable,
+    WhileStatement,
+};
+use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+use std::collections::HashSet;
+use std::mem;
+
+const STATE_VARIABLE_NAME: &str = "__DARKLUA_STATE";
+
+/// Collects the name of every variable referenced in the visited nodes.
+#[derive(Default)]
+struct ReferenceCollector {
+    references: HashSet<String>,
+}
+
+impl NodeProcessor for ReferenceCollector {
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        self.references.insert(identifier.get_name().to_owned());
+    }
+}
+
+/// Collects every identifier used or declared in the visited nodes, to find a name for the
+/// state variable that does not conflict with existing variables.
+#[derive(Default)]
+struct NameCollector {
+    names: HashSet<String>,
+}
+
+impl NameCollector {
+    fn insert_all<'a>(&mut self, identifiers: impl Iterator<Item = &'a Identifier>) {
+        self.names
+            .extend(identifiers.map(|identifier| identifier.get_name().to_owned()));
+    }
+}
+
+impl NodeProcessor for NameCollector {
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        self.names.insert(identifier.get_name().to_owned());
+    }
+
+    fn process_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        self.insert_all(statement.iter_variables());
+    }
+
+    fn process_local_function_statement(&mut self, statement: &mut LocalFunctionStatement) {
+        self.names.insert(statement.get_name().to_owned());
+        self.insert_all(statement.iter_parameters());
+    }
+
+    fn process_function_statement(&mut self, statement: &mut FunctionStatement) {
+        self.insert_all(statement.get_parameters().iter());
+    }
+
+    fn process_function_expression(&mut self, function: &mut FunctionExpression) {
+        self.insert_all(function.iter_parameters());
+    }
+
+    fn process_generic_for_statement(&mut self, statement: &mut GenericForStatement) {
+        self.insert_all(statement.iter_identifiers());
+    }
+
+    fn process_numeric_for_statement(&mut self, statement: &mut NumericForStatement) {
+        self.names
+            .insert(statement.get_identifier().get_name().to_owned());
+    }
+}
+
+fn find_state_variable_name(block: &mut Block) -> String {
+    let mut collector = NameCollector::default();
+    DefaultVisitor::visit_block(block, &mut collector);
+
+    let mut name = STATE_VARIABLE_NAME.to_owned();
+    let mut index = 1;
+    while collector.names.contains(&name) {
+        index += 1;
+        name = format!("{}_{}", STATE_VARIABLE_NAME, index);
+    }
+    name
+}
+
+/// Returns `true` if the block contains a `break` or `continue` statement that exits a loop
+/// outside of the block.
+fn has_loop_exit(block: &Block) -> bool {
+    matches!(
+        block.get_last_statement(),
+        Some(LastStatement::Break(_)) | Some(LastStatement::Continue(_))
+    ) || block.iter_statements().any(|statement| match statement {
+        Statement::Do(do_statement) => has_loop_exit(do_statement.get_block()),
+        Statement::If(if_statement) => {
+            if_statement
+                .iter_branches()
+                .any(|branch| has_loop_exit(branch.get_block()))
+                || if_statement.get_else_block().is_some_and(has_loop_exit)
+        }
+        _ => false,
+    })
+}
+
+fn collect_references(statement: &mut Statement) -> HashSet<String> {
+    let mut collector = ReferenceCollector::default();
+
+    match statement {
+        Statement::LocalAssign(local_assign) => {
+            for value in local_assign.iter_mut_values() {
+                DefaultVisitor::visit_expression(value, &mut collector);
+            }
+        }
+        Statement::LocalFunction(local_function) => {
+            DefaultVisitor::visit_block(local_function.mutate_block(), &mut collector);
+        }
+        _ => DefaultVisitor::visit_statement(statement, &mut collector),
+    }
+
+    collector.references
+}
+
+/// Returns the names of the local variables declared directly in the block, or `None` if
+/// one of these names is referenced before its declaration. In that case, declaring the
+/// variable at the beginning of the block would change which variable is referenced.
+fn find_hoisted_locals(block: &mut Block) -> Option<Vec<String>> {
+    let mut references = HashSet::new();
+    let mut locals: Vec<String> = Vec::new();
+
+    for statement in block.iter_mut_statements() {
+        let statement_references = collect_references(statement);
+
+        let declared: Vec<String> = match statement {
+            Statement::LocalAssign(local_assign) => local_assign
+                .iter_variables()
+                .map(|variable| variable.get_name().to_owned())
+                .collect(),
+            Statement::LocalFunction(local_function) => {
+                vec![local_function.get_name().to_owned()]
+            }
+            _ => Vec::new(),
+        };
+
+        let is_local_function = matches!(statement, Statement::LocalFunction(_));
+
+        for name in declared {
+            // a local function can reference itself
+            if references.contains(&name)
+                || (!is_local_function && statement_references.contains(&name))
+            {
+                return None;
+            }
+            if !locals.contains(&name) {
+                locals.push(name);
+            }
+        }
+
+        references.extend(statement_references);
+    }
+
+    Some(locals)
+}
+
+/// Converts a local declaration into an assignment to the hoisted variable.
+fn convert_local_declaration(statement: Statement) -> Statement {
+    match statement {
+        Statement::LocalAssign(local_assign) => {
+            let (variables, mut values) = local_assign.into_assignments();
+
+            // a declaration without values resets the variables to `nil`
+            if values.is_empty() {
+                values.push(Expression::nil());
+            }
+
+            AssignStatement::new(variables.into_iter().map(Variable::from).collect(), values).into()
+        }
+        Statement::LocalFunction(mut local_function) => {
+            let mut function = FunctionExpression::default();
+            function.set_variadic(local_function.is_variadic());
+            mem::swap(function.mutate_block(), local_function.mutate_block());
+            mem::swap(
+                function.mutate_parameters(),
+                local_function.mutate_parameters(),
+            );
+
+            AssignStatement::from_variable(Identifier::new(local_function.get_name()), function)
+                .into()
+        }
+        statement => statement,
+    }
+}
+
+fn state_value(state: usize) -> Expression {
+    Expression::from(state)
+}
+
+/// Rewrites the statements of a block into a loop that runs each statement according to the
+/// value of a state variable.
+fn flatten_block(block: &mut Block) {
+    let parts = block.iter_statements().count() + usize::from(block.get_last_statement().is_some());
+
+    if parts < 2 || has_loop_exit(block) {
+        return;
+    }
+
+    let hoisted = match find_hoisted_locals(block) {
+        Some(hoisted) => hoisted,
+        None => return,
+    };
+
+    let state_name = find_state_variable_name(block);
+
+    let mut states: Vec<Block> = block
+        .take_statements()
+        .into_iter()
+        .map(convert_local_declaration)
+        .map(|statement| Block::default().with_statement(statement))
+        .collect();
+
+    if let Some(last_statement) = block.take_last_statement() {
+        states.push(Block::default().with_last_statement(last_statement));
+    }
+
+    let total_states = states.len();
+    let mut dispatcher: Option<IfStatement> = None;
+
+    for (index, mut state) in states.into_iter().enumerate() {
+        let current = index + 1;
+
+        if state.get_last_statement().is_none() {
+            let next_state = if current == total_states {
+                Expression::nil()
+            } else {
+                state_value(current + 1)
+            };
+            state.push_statement(AssignStatement::from_variable(
+                Identifier::new(&state_name),
+                next_state,
+            ));
+        }
+
+        let condition = BinaryExpression::new(
+            BinaryOperator::Equal,
+            Identifier::new(&state_name),
+            state_value(current),
+        );
+
+        dispatcher = Some(match dispatcher {
+            Some(dispatcher) => dispatcher.with_new_branch(condition, state),
+            None => IfStatement::create(condition, state),
+        });
+    }
+
+    let mut statements: Vec<Statement> = Vec::new();
+
+    if !hoisted.is_empty() {
+        statements.push(
+            LocalAssignStatement::new(
+                hoisted.into_iter().map(Identifier::new).collect(),
+                Vec::new(),
+            )
+            .into(),
+        );
+    }
+
+    statements.push(
+        LocalAssignStatement::from_variable(Identifier::new(&state_name))
+            .with_value(state_value(1))
+            .into(),
+    );
+
+    if let Some(dispatcher) = dispatcher {
+        statements.push(
+            WhileStatement::new(
+                Block::default().with_statement(dispatcher),
+                Identifier::new(&state_name),
+            )
+            .into(),
+        );
+    }
+
+    block.set_statements(statements);
+}
+
+fn get_function_name(name: &FunctionName) -> String {
+    let mut path = name.get_name().get_name().to_owned();
+
+    for field in name.get_field_names() {
+        path.push('.');
+        path.push_str(field.get_name());
+    }
+
+    if let Some(method) = name.get_method() {
+        path.push(':');
+        path.push_str(method.get_name());
+    }
+
+    path
+}
+
+struct Flattener<'a> {
+    functions: &'a [String],
+    max_depth: usize,
+}
+
+impl<'a> Flattener<'a> {
+    fn flattens_all_functions(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    fn is_selected(&self, name: &str) -> bool {
+        self.flattens_all_functions() || self.functions.iter().any(|function| function == name)
+    }
+
+    fn flatten_function_body(&self, block: &mut Block) {
+        self.flatten(block, self.max_depth);
+    }
+
+    fn flatten(&self, block: &mut Block, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+
+        if depth > 1 {
+            for statement in block.iter_mut_statements() {
+                for nested_block in get_nested_blocks(statement) {
+                    self.flatten(nested_block, depth - 1);
+                }
+            }
+        }
+
+        flatten_block(block);
+    }
+}
+
+fn get_nested_blocks(statement: &mut Statement) -> Vec<&mut Block> {
+    match statement {
+        Statement::Do(statement) => vec![statement.mutate_block()],
+        Statement::If(statement) => statement.mutate_all_blocks(),
+        Statement::While(statement) => vec![statement.mutate_block()],
+        Statement::Repeat(statement) => vec![statement.mutate_block()],
+        Statement::NumericFor(statement) => vec![statement.mutate_block()],
+        Statement::GenericFor(statement) => vec![statement.mutate_block()],
+        _ => Vec::new(),
+    }
+}
+
+impl<'a> NodeProcessor for Flattener<'a> {
+    fn process_function_statement(&mut self, statement: &mut FunctionStatement) {
+        if self.is_selected(&get_function_name(statement.get_name())) {
+            self.flatten_function_body(statement.mutate_block());
+        }
+    }
+
+    fn process_local_function_statement(&mut self, statement: &mut LocalFunctionStatement) {
+        if self.is_selected(statement.get_name()) {
+            self.flatten_function_body(statement.mutate_block());
+        }
+    }
+
+    fn process_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        // when all functions are flattened, function expressions are processed directly
+        if self.flattens_all_functions() {
+            return;
+        }
+
+        statement.for_each_assignment(|variable, value| {
+            if let Some(Expression::Function(function)) = value {
+                if self.is_selected(variable.get_name()) {
+                    self.flatten_function_body(function.mutate_block());
+                }
+            }
+        });
+    }
+
+    fn process_assign_statement(&mut self, statement: &mut AssignStatement) {
+        if self.flattens_all_functions() || statement.variables_len() != statement.values_len() {
+            return;
+        }
+
+        let selected: Vec<bool> = statement
+            .iter_variables()
+            .map(|variable| {
+                matches!(variable, Variable::Identifier(identifier) if self.is_selected(identifier.get_name()))
+            })
+            .collect();
+
+        for (value, is_selected) in statement.iter_mut_values().zip(selected) {
+            if let (Expression::Function(function), true) = (value, is_selected) {
+                self.flatten_function_body(function.mutate_block());
+            }
+        }
+    }
+
+    fn process_function_expression(&mut self, function: &mut FunctionExpression) {
+        if self.flattens_all_functions() {
+            self.flatten_function_body(function.mutate_block());
+        }
+    }
+}
+
+pub const FLATTEN_CONTROL_FLOW_RULE_NAME: &str = "flatten_control_flow";
+const DEFAULT_MAX_DEPTH: usize = 1;
+
+/// A rule that rewrites function bodies into a loop that dispatches each statement using a
+/// state variable.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FlattenControlFlow {
+    functions: Vec<String>,
+    max_depth: usize,
+}
+
+impl Default for FlattenControlFlow {
+    fn default() -> Self {
+        Self {
+            functions: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+impl FlattenControlFlow {
+    /// Only flatten the functions with the given name (like `process` or `Class:method`).
+    pub fn with_function(mut self, name: impl Into<String>) -> Self {
+        self.functions.push(name.into());
+        self
+    }
+
+    /// Set how many levels of nested blocks are flattened in each function. With a depth of
+    /// 1, only the function body is flattened.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+impl FlawlessRule for FlattenControlFlow {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut processor = Flattener {
+            functions: &self.functions,
+            max_depth: self.max_depth,
+        };
+        DefaultVisitor::visit_block(block, &mut processor);
+    }
+}
+
+impl RuleConfiguration for FlattenControlFlow {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "functions" => match value {
+                    RulePropertyValue::StringList(functions) => {
+                        self.functions = functions;
+                    }
+                    _ => return Err(RuleConfigurationError::StringListExpected(key)),
+                },
+                "max_depth" => match value {
+                    RulePropertyValue::Usize(max_depth) => {
+                        self.max_depth = max_depth;
+                    }
+                    _ => return Err(RuleConfigurationError::UsizeExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        FLATTEN_CONTROL_FLOW_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if !self.functions.is_empty() {
+            properties.insert(
+                "functions".to_owned(),
+                RulePropertyValue::StringList(self.functions.clone()),
+            );
+        }
+
+        if self.max_depth != DEFAULT_MAX_DEPTH {
+            properties.insert("max_depth".to_owned(), self.max_depth.into());
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> FlattenControlFlow {
+        FlattenControlFlow::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_flatten_control_flow", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_functions_and_max_depth() {
+        let rule: Box<dyn Rule> = Box::new(
+            new_rule()
+                .with_function("process")
+                .with_function("Class:update")
+                .with_max_depth(2),
+        );
+
+        assert_json_snapshot!("flatten_control_flow_with_functions_and_max_depth", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'flatten_control_flow',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
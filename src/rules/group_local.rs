@@ -1,6 +1,6 @@
 use crate::nodes::{Block, Expression, LocalAssignStatement, Statement};
 use crate::process::processors::FindVariables;
-use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor};
+use crate::process::{DefaultVisitor, Evaluator, NodeProcessor, NodeVisitor};
 use crate::rules::{
     Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
 };
@@ -10,7 +10,9 @@ use std::iter;
 use super::verify_no_rule_properties;
 
 #[derive(Debug, Clone, Default)]
-struct GroupLocalProcessor {}
+struct GroupLocalProcessor {
+    evaluator: Evaluator,
+}
 
 impl GroupLocalProcessor {
     fn filter_statements(&self, block: &mut Block) -> Vec<Statement> {
@@ -57,7 +59,16 @@ impl GroupLocalProcessor {
     fn should_merge(&self, first: &LocalAssignStatement, next: &mut LocalAssignStatement) -> bool {
         let first_value_count = first.values_len();
 
-        if first.variables_len() > first_value_count && first_value_count != 0 {
+        if first_value_count > first.variables_len() {
+            return false;
+        }
+
+        if first.variables_len() > first_value_count
+            && first
+                .last_value()
+                .filter(|last_value| self.evaluator.can_return_multiple_values(last_value))
+                .is_some()
+        {
             return false;
         }
 
@@ -70,9 +81,9 @@ impl GroupLocalProcessor {
     }
 
     fn merge(&self, first: &mut LocalAssignStatement, mut other: LocalAssignStatement) {
-        if first.values_len() == 0 && other.values_len() != 0 {
-            let variable_count = first.variables_len();
-            first.extend_values(iter::repeat(Expression::nil()).take(variable_count));
+        if first.variables_len() > first.values_len() && other.values_len() != 0 {
+            let missing_value_count = first.variables_len() - first.values_len();
+            first.extend_values(iter::repeat(Expression::nil()).take(missing_value_count));
         }
 
         if other.values_len() == 0 && first.values_len() != 0 {
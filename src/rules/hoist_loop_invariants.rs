@@ -0,0 +1,580 @@
+use crate::nodes::{
+    Arguments, BinaryOperator, Block, Expression, FunctionCall, Identifier, LastStatement,
+    LocalAssignStatement, NumericForStatement, Prefix, Statement, TableEntry, TableExpression,
+    UnaryOperator, Variable,
+};
+use crate::process::utils::is_same_expression;
+use crate::process::{DefaultVisitor, Evaluator, LuaValue, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+use std::collections::HashSet;
+use std::mem;
+
+use super::variable_usage::{generate_variable_name, ExpressionInspector, VariableUsage};
+
+fn contains_exit(block: &Block) -> bool {
+    block.get_last_statement().is_some() || block.iter_statements().any(statement_contains_exit)
+}
+
+fn statement_contains_exit(statement: &Statement) -> bool {
+    match statement {
+        Statement::Do(do_statement) => contains_exit(do_statement.get_block()),
+        Statement::GenericFor(generic_for) => contains_exit(generic_for.get_block()),
+        Statement::If(if_statement) => {
+            if_statement
+                .iter_branches()
+                .any(|branch| contains_exit(branch.get_block()))
+                || if_statement.get_else_block().is_some_and(contains_exit)
+        }
+        Statement::NumericFor(numeric_for) => contains_exit(numeric_for.get_block()),
+        Statement::Repeat(repeat) => contains_exit(repeat.get_block()),
+        Statement::While(while_statement) => contains_exit(while_statement.get_block()),
+        Statement::Assign(_)
+        | Statement::Call(_)
+        | Statement::CompoundAssign(_)
+        | Statement::Function(_)
+        | Statement::LocalAssign(_)
+        | Statement::LocalFunction(_) => false,
+    }
+}
+
+/// Returns true if evaluating the expression can throw an error or call a metamethod. Operators
+/// on unknown values (like `a + b` or `a.b`) can fail if the values do not have the expected type.
+fn may_fail(expression: &Expression) -> bool {
+    match expression {
+        Expression::Binary(binary) => {
+            !matches!(binary.operator(), BinaryOperator::And | BinaryOperator::Or)
+                || may_fail(binary.left())
+                || may_fail(binary.right())
+        }
+        Expression::If(if_expression) => {
+            may_fail(if_expression.get_condition())
+                || may_fail(if_expression.get_result())
+                || if_expression
+                    .iter_branches()
+                    .any(|branch| may_fail(branch.get_condition()) || may_fail(branch.get_result()))
+                || may_fail(if_expression.get_else_result())
+        }
+        Expression::Parenthese(parenthese) => may_fail(parenthese.inner_expression()),
+        Expression::Unary(unary) => {
+            !matches!(unary.operator(), UnaryOperator::Not) || may_fail(unary.get_expression())
+        }
+        Expression::False(_)
+        | Expression::Identifier(_)
+        | Expression::Nil(_)
+        | Expression::Number(_)
+        | Expression::String(_)
+        | Expression::True(_) => false,
+        Expression::Call(_)
+        | Expression::Field(_)
+        | Expression::Function(_)
+        | Expression::Index(_)
+        | Expression::Table(_)
+        | Expression::VariableArguments(_) => true,
+    }
+}
+
+/// Returns true if the numeric for loop is known to run at least once.
+fn numeric_for_runs(evaluator: &Evaluator, numeric_for: &NumericForStatement) -> bool {
+    let step = match numeric_for.get_step() {
+        Some(step) => evaluator.evaluate(step),
+        None => LuaValue::Number(1.0),
+    };
+
+    match (
+        evaluator.evaluate(numeric_for.get_start()),
+        evaluator.evaluate(numeric_for.get_end()),
+        step,
+    ) {
+        (LuaValue::Number(start), LuaValue::Number(end), LuaValue::Number(step)) => {
+            (step > 0.0 && start <= end) || (step < 0.0 && start >= end)
+        }
+        _ => false,
+    }
+}
+
+/// Finds the expressions of a loop that are evaluated on each iteration and that produce the
+/// same value each time, and replaces them with a new local variable.
+struct LoopHoister<'a> {
+    loop_usage: &'a VariableUsage,
+    file_usage: &'a VariableUsage,
+    evaluator: &'a Evaluator,
+    names: &'a mut HashSet<String>,
+    hoisted: Vec<(Identifier, Expression)>,
+    // when the code may not run, the hoisted expressions would be evaluated even if the loop
+    // never evaluates them
+    may_not_run: bool,
+    // once the loop may have side effects, a hoisted expression that fails would throw its
+    // error before these side effects happen
+    has_side_effects: bool,
+}
+
+impl<'a> LoopHoister<'a> {
+    /// Returns false if the block may exit the loop early, so that the code after can't be
+    /// assumed to run on each iteration.
+    fn hoist_block(&mut self, block: &mut Block) -> bool {
+        for statement in block.iter_mut_statements() {
+            self.hoist_statement(statement);
+
+            if statement_contains_exit(statement) {
+                return false;
+            }
+        }
+
+        match block.mutate_last_statement() {
+            Some(LastStatement::Return(statement)) => {
+                statement
+                    .iter_mut_expressions()
+                    .for_each(|expression| self.hoist_expression(expression));
+                false
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    fn hoist_statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Assign(assign) => {
+                assign
+                    .mutate_variables()
+                    .iter_mut()
+                    .for_each(|variable| self.hoist_variable(variable));
+                assign
+                    .iter_mut_values()
+                    .for_each(|value| self.hoist_expression(value));
+            }
+            Statement::Call(call) => self.hoist_call(call),
+            Statement::CompoundAssign(assign) => {
+                self.hoist_variable(assign.mutate_variable());
+                self.hoist_expression(assign.mutate_value());
+            }
+            Statement::Do(do_statement) => {
+                self.hoist_block(do_statement.mutate_block());
+            }
+            Statement::GenericFor(generic_for) => generic_for
+                .iter_mut_expressions()
+                .for_each(|expression| self.hoist_expression(expression)),
+            Statement::If(if_statement) => {
+                if let Some(branch) = if_statement.mutate_branches().first_mut() {
+                    self.hoist_expression(branch.mutate_condition());
+                }
+            }
+            Statement::LocalAssign(assign) => assign
+                .iter_mut_values()
+                .for_each(|value| self.hoist_expression(value)),
+            Statement::NumericFor(numeric_for) => {
+                self.hoist_expression(numeric_for.mutate_start());
+                self.hoist_expression(numeric_for.mutate_end());
+                if let Some(step) = numeric_for.mutate_step() {
+                    self.hoist_expression(step);
+                }
+            }
+            Statement::While(while_statement) => {
+                self.hoist_expression(while_statement.mutate_condition());
+            }
+            Statement::Function(_) | Statement::LocalFunction(_) | Statement::Repeat(_) => {}
+        }
+
+        // assignments and the blocks of nested statements (that are not visited) may have
+        // side effects
+        match statement {
+            Statement::Assign(_)
+            | Statement::CompoundAssign(_)
+            | Statement::Function(_)
+            | Statement::GenericFor(_)
+            | Statement::If(_)
+            | Statement::NumericFor(_)
+            | Statement::Repeat(_)
+            | Statement::While(_) => {
+                self.has_side_effects = true;
+            }
+            Statement::Call(_)
+            | Statement::Do(_)
+            | Statement::LocalAssign(_)
+            | Statement::LocalFunction(_) => {}
+        }
+    }
+
+    fn hoist_variable(&mut self, variable: &mut Variable) {
+        match variable {
+            Variable::Identifier(_) => {}
+            Variable::Field(field) => self.hoist_prefix(field.mutate_prefix()),
+            Variable::Index(index) => {
+                self.hoist_prefix(index.mutate_prefix());
+                self.hoist_expression(index.mutate_index());
+            }
+        }
+    }
+
+    fn hoist_call(&mut self, call: &mut FunctionCall) {
+        self.hoist_prefix(call.mutate_prefix());
+
+        match call.mutate_arguments() {
+            Arguments::Tuple(tuple) => tuple
+                .iter_mut_values()
+                .for_each(|value| self.hoist_expression(value)),
+            Arguments::Table(table) => self.hoist_table(table),
+            Arguments::String(_) => {}
+        }
+
+        self.has_side_effects = true;
+    }
+
+    fn hoist_table(&mut self, table: &mut TableExpression) {
+        for entry in table.iter_mut_entries() {
+            match entry {
+                TableEntry::Field(field) => self.hoist_expression(field.mutate_value()),
+                TableEntry::Index(index) => {
+                    self.hoist_expression(index.mutate_key());
+                    self.hoist_expression(index.mutate_value());
+                }
+                TableEntry::Value(value) => self.hoist_expression(value),
+            }
+        }
+    }
+
+    fn hoist_expression(&mut self, expression: &mut Expression) {
+        if let Expression::Parenthese(parenthese) = expression {
+            if self.is_invariant(parenthese.inner_expression()) {
+                let identifier = self.hoist(parenthese.inner_expression().clone());
+                *expression = Expression::Identifier(identifier);
+                return;
+            }
+        }
+
+        if self.is_invariant(expression) {
+            let identifier = self.hoist(expression.clone());
+            *expression = Expression::Identifier(identifier);
+            return;
+        }
+
+        match expression {
+            Expression::Binary(binary) => {
+                self.hoist_expression(binary.mutate_left());
+
+                // the right operand of `and` and `or` is not always evaluated
+                if !matches!(binary.operator(), BinaryOperator::And | BinaryOperator::Or) {
+                    self.hoist_expression(binary.mutate_right());
+                }
+            }
+            Expression::Call(call) => self.hoist_call(call),
+            Expression::Field(field) => self.hoist_prefix(field.mutate_prefix()),
+            Expression::If(if_expression) => {
+                self.hoist_expression(if_expression.mutate_condition())
+            }
+            Expression::Index(index) => {
+                self.hoist_prefix(index.mutate_prefix());
+                self.hoist_expression(index.mutate_index());
+            }
+            Expression::Parenthese(parenthese) => {
+                self.hoist_expression(parenthese.mutate_inner_expression())
+            }
+            Expression::Table(table) => self.hoist_table(table),
+            Expression::Unary(unary) => self.hoist_expression(unary.mutate_expression()),
+            Expression::False(_)
+            | Expression::Function(_)
+            | Expression::Identifier(_)
+            | Expression::Nil(_)
+            | Expression::Number(_)
+            | Expression::String(_)
+            | Expression::True(_)
+            | Expression::VariableArguments(_) => {}
+        }
+
+        if self.evaluator.has_side_effects(expression) {
+            self.has_side_effects = true;
+        }
+    }
+
+    fn hoist_prefix(&mut self, prefix: &mut Prefix) {
+        match prefix {
+            Prefix::Field(_) | Prefix::Index(_) => {
+                let expression = Expression::from(prefix.clone());
+
+                if self.is_invariant(&expression) {
+                    *prefix = Prefix::Identifier(self.hoist(expression));
+                    return;
+                }
+            }
+            Prefix::Call(_) | Prefix::Identifier(_) | Prefix::Parenthese(_) => {}
+        }
+
+        match prefix {
+            Prefix::Call(call) => self.hoist_call(call),
+            Prefix::Field(field) => self.hoist_prefix(field.mutate_prefix()),
+            Prefix::Identifier(_) => {}
+            Prefix::Index(index) => {
+                self.hoist_prefix(index.mutate_prefix());
+                self.hoist_expression(index.mutate_index());
+            }
+            Prefix::Parenthese(parenthese) => {
+                self.hoist_expression(parenthese.mutate_inner_expression())
+            }
+        }
+    }
+
+    fn is_invariant(&self, expression: &Expression) -> bool {
+        if !matches!(
+            expression,
+            Expression::Binary(_)
+                | Expression::Field(_)
+                | Expression::If(_)
+                | Expression::Index(_)
+                | Expression::Unary(_)
+        ) {
+            return false;
+        }
+
+        if self.has_side_effects
+            || self.evaluator.has_side_effects(expression)
+            || self.evaluator.evaluate(expression) != LuaValue::Unknown
+            || (self.may_not_run && may_fail(expression))
+        {
+            return false;
+        }
+
+        let mut inspector = ExpressionInspector::default();
+        DefaultVisitor::visit_expression(&mut expression.clone(), &mut inspector);
+
+        if inspector.has_unmovable_expression {
+            return false;
+        }
+
+        // a function called in the loop could assign any global variable, or any local
+        // variable assigned somewhere in the file
+        let identifiers_are_invariant = inspector.identifiers.iter().all(|name| {
+            !self.loop_usage.declared.contains(name)
+                && !self.loop_usage.assigned.contains(name)
+                && (!self.loop_usage.has_calls
+                    || (self.file_usage.declared.contains(name)
+                        && !self.file_usage.assigned.contains(name)))
+        });
+
+        if !identifiers_are_invariant {
+            return false;
+        }
+
+        // a function called in the loop could also modify the content of any table
+        inspector.table_roots.is_empty()
+            || !(self.loop_usage.has_field_assignments || self.loop_usage.has_calls)
+    }
+
+    fn hoist(&mut self, expression: Expression) -> Identifier {
+        if let Some((identifier, _)) = self
+            .hoisted
+            .iter()
+            .find(|(_, hoisted_expression)| is_same_expression(hoisted_expression, &expression))
+        {
+            return identifier.clone();
+        }
+
+        let identifier = Identifier::new(generate_variable_name(&expression, self.names));
+        self.hoisted.push((identifier.clone(), expression));
+        identifier
+    }
+}
+
+struct Processor {
+    file_usage: VariableUsage,
+    names: HashSet<String>,
+    evaluator: Evaluator,
+}
+
+impl Processor {
+    fn new(mut file_usage: VariableUsage, evaluator: Evaluator) -> Self {
+        Self {
+            names: mem::take(&mut file_usage.names),
+            file_usage,
+            evaluator,
+        }
+    }
+
+    fn hoist_loop(&mut self, statement: &mut Statement) -> Vec<(Identifier, Expression)> {
+        if !matches!(
+            statement,
+            Statement::GenericFor(_)
+                | Statement::NumericFor(_)
+                | Statement::Repeat(_)
+                | Statement::While(_)
+        ) {
+            return Vec::new();
+        }
+
+        let mut loop_usage = VariableUsage::default();
+        DefaultVisitor::visit_statement(&mut statement.clone(), &mut loop_usage);
+
+        let mut hoister = LoopHoister {
+            loop_usage: &loop_usage,
+            file_usage: &self.file_usage,
+            evaluator: &self.evaluator,
+            names: &mut self.names,
+            hoisted: Vec::new(),
+            may_not_run: false,
+            has_side_effects: false,
+        };
+
+        match statement {
+            Statement::GenericFor(generic_for) => {
+                hoister.may_not_run = true;
+                hoister.hoist_block(generic_for.mutate_block());
+            }
+            Statement::NumericFor(numeric_for) => {
+                hoister.may_not_run = !numeric_for_runs(hoister.evaluator, numeric_for);
+                hoister.hoist_block(numeric_for.mutate_block());
+            }
+            Statement::Repeat(repeat) => {
+                let reaches_condition = hoister.hoist_block(repeat.mutate_block());
+
+                if reaches_condition {
+                    hoister.hoist_expression(repeat.mutate_condition());
+                }
+            }
+            Statement::While(while_statement) => {
+                hoister.hoist_expression(while_statement.mutate_condition());
+                hoister.may_not_run = true;
+                hoister.hoist_block(while_statement.mutate_block());
+            }
+            _ => {}
+        }
+
+        hoister.hoisted
+    }
+}
+
+impl NodeProcessor for Processor {
+    fn process_block(&mut self, block: &mut Block) {
+        let mut index = 0;
+
+        loop {
+            let hoisted = match block.iter_mut_statements().nth(index) {
+                Some(statement) => self.hoist_loop(statement),
+                None => break,
+            };
+            let hoisted_count = hoisted.len();
+
+            for (offset, (identifier, value)) in hoisted.into_iter().enumerate() {
+                block.insert_statement(
+                    index + offset,
+                    LocalAssignStatement::from_variable(identifier).with_value(value),
+                );
+            }
+
+            index += hoisted_count + 1;
+        }
+    }
+}
+
+pub const HOIST_LOOP_INVARIANTS_RULE_NAME: &str = "hoist_loop_invariants";
+
+/// A rule that moves expressions that produce the same value on each iteration of a loop into
+/// local variables declared before the loop.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct HoistLoopInvariants {
+    assume_pure_metamethods: bool,
+}
+
+impl HoistLoopInvariants {
+    pub fn with_pure_metamethods(mut self, assume_pure_metamethods: bool) -> Self {
+        self.assume_pure_metamethods = assume_pure_metamethods;
+        self
+    }
+
+    fn build_evaluator(&self) -> Evaluator {
+        if self.assume_pure_metamethods {
+            Evaluator::default().assume_pure_metamethods()
+        } else {
+            Evaluator::default()
+        }
+    }
+}
+
+impl FlawlessRule for HoistLoopInvariants {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut file_usage = VariableUsage::default();
+        DefaultVisitor::visit_block(&mut block.clone(), &mut file_usage);
+
+        let mut processor = Processor::new(file_usage, self.build_evaluator());
+        DefaultVisitor::visit_block(block, &mut processor);
+    }
+}
+
+impl RuleConfiguration for HoistLoopInvariants {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "assume_pure_metamethods" => match value {
+                    RulePropertyValue::Boolean(value) => {
+                        self.assume_pure_metamethods = value;
+                    }
+                    _ => return Err(RuleConfigurationError::BooleanExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        HOIST_LOOP_INVARIANTS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if self.assume_pure_metamethods {
+            properties.insert(
+                "assume_pure_metamethods".to_owned(),
+                RulePropertyValue::Boolean(self.assume_pure_metamethods),
+            );
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> HoistLoopInvariants {
+        HoistLoopInvariants::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_hoist_loop_invariants", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_pure_metamethods() {
+        let rule: Box<dyn Rule> = Box::new(new_rule().with_pure_metamethods(true));
+
+        assert_json_snapshot!("hoist_loop_invariants_with_pure_metamethods", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'hoist_loop_invariants',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
@@ -1,16 +1,22 @@
 use crate::nodes::{
     Block, DecimalNumber, Expression, LocalFunctionStatement, ParentheseExpression, Prefix,
-    StringExpression, UnaryOperator,
+    StringExpression, TableEntry, TableExpression, TableFieldEntry, TableIndexEntry, UnaryOperator,
 };
+use crate::process::utils::is_valid_identifier;
 use crate::process::{NodeProcessor, NodeVisitor, Scope, ScopeVisitor};
 use crate::rules::{
-    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    Context, Rule, RuleConfiguration, RuleConfigurationError, RuleProcessResult, RuleProperties,
     RulePropertyValue,
 };
+use crate::utils::normalize_path;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
 
+use super::embed_data_files::get_returned_value;
+use super::require_path::{is_data_file, DATA_EXTENSIONS};
 use super::{verify_property_collisions, verify_required_properties};
 
 #[derive(Debug, Clone)]
@@ -105,6 +111,76 @@ impl NodeProcessor for ValueInjection {
     }
 }
 
+/// The type used to convert the content of an environment variable into a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnvType {
+    Boolean,
+    Number,
+    String,
+    Json,
+}
+
+impl EnvType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Boolean => "boolean",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Json => "json",
+        }
+    }
+
+    fn accepts(&self, value: &RulePropertyValue) -> bool {
+        match self {
+            Self::Boolean => matches!(value, RulePropertyValue::Boolean(_)),
+            Self::Number => matches!(
+                value,
+                RulePropertyValue::Usize(_) | RulePropertyValue::Float(_)
+            ),
+            Self::String => matches!(value, RulePropertyValue::String(_)),
+            Self::Json => true,
+        }
+    }
+
+    fn parse(&self, value: &str) -> Result<Expression, String> {
+        match self {
+            Self::Boolean => match value.trim() {
+                "true" | "1" => Ok(Expression::from(true)),
+                "false" | "0" => Ok(Expression::from(false)),
+                _ => Err(format!("expected `true` or `false` but got `{}`", value)),
+            },
+            Self::Number => value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .map(Expression::from)
+                .ok_or_else(|| format!("expected a number but got `{}`", value)),
+            Self::String => Ok(StringExpression::from_value(value).into()),
+            Self::Json => json5::from_str::<RulePropertyValue>(value)
+                .map(property_to_expression)
+                .map_err(|err| format!("invalid json: {}", err)),
+        }
+    }
+}
+
+impl FromStr for EnvType {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "boolean" => Ok(Self::Boolean),
+            "number" => Ok(Self::Number),
+            "string" => Ok(Self::String),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "invalid type `{}` (expected `boolean`, `number`, `string` or `json`)",
+                string
+            )),
+        }
+    }
+}
+
 pub const INJECT_GLOBAL_VALUE_RULE_NAME: &str = "inject_global_value";
 
 /// A rule to replace global variables with values.
@@ -112,6 +188,7 @@ pub const INJECT_GLOBAL_VALUE_RULE_NAME: &str = "inject_global_value";
 pub struct InjectGlobalValue {
     identifier: String,
     value: Expression,
+    file: Option<PathBuf>,
 }
 
 impl InjectGlobalValue {
@@ -119,6 +196,7 @@ impl InjectGlobalValue {
         Self {
             identifier: identifier.into(),
             value: Expression::nil(),
+            file: None,
         }
     }
 
@@ -126,6 +204,7 @@ impl InjectGlobalValue {
         Self {
             identifier: identifier.into(),
             value: Expression::from(value),
+            file: None,
         }
     }
 
@@ -133,6 +212,7 @@ impl InjectGlobalValue {
         Self {
             identifier: identifier.into(),
             value: StringExpression::from_value(value).into(),
+            file: None,
         }
     }
 
@@ -140,7 +220,157 @@ impl InjectGlobalValue {
         Self {
             identifier: identifier.into(),
             value: Expression::from(value),
+            file: None,
+        }
+    }
+
+    /// Injects a table created from the given property value. Lists become arrays and maps
+    /// become tables with string keys.
+    pub fn table<S: Into<String>>(identifier: S, value: RulePropertyValue) -> Self {
+        Self {
+            identifier: identifier.into(),
+            value: property_to_expression(value),
+            file: None,
+        }
+    }
+
+    /// Injects the value read from a data file (JSON, TOML or text file). The path is relative
+    /// to the configuration file location.
+    pub fn file<S: Into<String>, P: Into<PathBuf>>(identifier: S, path: P) -> Self {
+        Self {
+            identifier: identifier.into(),
+            value: Expression::nil(),
+            file: Some(path.into()),
+        }
+    }
+
+    fn get_file_path(&self, context: &Context) -> Option<PathBuf> {
+        self.file.as_ref().map(|file| {
+            normalize_path(match context.configuration_location() {
+                Some(location) => location.join(file),
+                None => file.clone(),
+            })
+        })
+    }
+}
+
+pub(crate) fn property_to_expression(value: RulePropertyValue) -> Expression {
+    match value {
+        RulePropertyValue::None => Expression::nil(),
+        RulePropertyValue::String(value) => StringExpression::from_value(value).into(),
+        RulePropertyValue::Boolean(value) => Expression::from(value),
+        RulePropertyValue::Usize(value) => DecimalNumber::new(value as f64).into(),
+        RulePropertyValue::Float(value) => Expression::from(value),
+        RulePropertyValue::StringList(list) => TableExpression::new(
+            list.into_iter()
+                .map(|value| TableEntry::Value(StringExpression::from_value(value).into()))
+                .collect(),
+        )
+        .into(),
+        RulePropertyValue::List(list) => TableExpression::new(
+            list.into_iter()
+                .map(|value| TableEntry::Value(property_to_expression(value)))
+                .collect(),
+        )
+        .into(),
+        RulePropertyValue::Map(map) => TableExpression::new(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = property_to_expression(value);
+                    if is_valid_identifier(&key) {
+                        TableFieldEntry::new(key, value).into()
+                    } else {
+                        TableIndexEntry::new(StringExpression::from_value(key), value).into()
+                    }
+                })
+                .collect(),
+        )
+        .into(),
+    }
+}
+
+fn expression_to_property(expression: &Expression) -> RulePropertyValue {
+    match expression {
+        Expression::True(_) => RulePropertyValue::Boolean(true),
+        Expression::False(_) => RulePropertyValue::Boolean(false),
+        Expression::Nil(_) => RulePropertyValue::None,
+        Expression::Number(number) => {
+            let value = number.compute_value();
+            if value.trunc() == value && value >= 0.0 && value < usize::MAX as f64 {
+                RulePropertyValue::Usize(value as usize)
+            } else {
+                RulePropertyValue::Float(value)
+            }
+        }
+        Expression::String(string) => RulePropertyValue::from(string.get_value()),
+        Expression::Unary(unary) => {
+            if matches!(unary.operator(), UnaryOperator::Minus) {
+                if let Expression::Number(number) = unary.get_expression() {
+                    RulePropertyValue::Float(-number.compute_value())
+                } else {
+                    unreachable!(
+                        "unexpected expression for unary minus {:?}",
+                        unary.get_expression()
+                    );
+                }
+            } else {
+                unreachable!("unexpected unary operator {:?}", unary.operator());
+            }
+        }
+        Expression::Table(table) => {
+            if table
+                .iter_entries()
+                .all(|entry| matches!(entry, TableEntry::Value(_)))
+            {
+                let list: Vec<_> = table
+                    .iter_entries()
+                    .map(|entry| match entry {
+                        TableEntry::Value(value) => expression_to_property(value),
+                        _ => unreachable!(),
+                    })
+                    .collect();
+
+                if !list.is_empty()
+                    && list
+                        .iter()
+                        .all(|value| matches!(value, RulePropertyValue::String(_)))
+                {
+                    RulePropertyValue::StringList(
+                        list.into_iter()
+                            .map(|value| match value {
+                                RulePropertyValue::String(value) => value,
+                                _ => unreachable!(),
+                            })
+                            .collect(),
+                    )
+                } else {
+                    RulePropertyValue::List(list)
+                }
+            } else {
+                RulePropertyValue::Map(
+                    table
+                        .iter_entries()
+                        .map(|entry| match entry {
+                            TableEntry::Field(entry) => (
+                                entry.get_field().get_name().to_owned(),
+                                expression_to_property(entry.get_value()),
+                            ),
+                            TableEntry::Index(entry) => match entry.get_key() {
+                                Expression::String(key) => (
+                                    key.get_value().to_owned(),
+                                    expression_to_property(entry.get_value()),
+                                ),
+                                key => unreachable!("unexpected table key {:?}", key),
+                            },
+                            TableEntry::Value(value) => {
+                                unreachable!("unexpected table value {:?}", value)
+                            }
+                        })
+                        .collect::<BTreeMap<_, _>>(),
+                )
+            }
         }
+        _ => unreachable!("unexpected expression {:?}", expression),
     }
 }
 
@@ -149,21 +379,73 @@ impl Default for InjectGlobalValue {
         Self {
             identifier: "".to_owned(),
             value: Expression::nil(),
+            file: None,
         }
     }
 }
 
-impl FlawlessRule for InjectGlobalValue {
-    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
-        let mut processor = ValueInjection::new(&self.identifier, self.value.clone());
+impl Rule for InjectGlobalValue {
+    fn process(&self, block: &mut Block, context: &mut Context) -> RuleProcessResult {
+        let value = match self.get_file_path(context) {
+            Some(path) => context
+                .block(&path)
+                .and_then(get_returned_value)
+                .cloned()
+                .ok_or_else(|| format!("unable to read file `{}`", path.display()))?,
+            None => self.value.clone(),
+        };
+
+        let mut processor = ValueInjection::new(&self.identifier, value);
         ScopeVisitor::visit_block(block, &mut processor);
+        Ok(())
+    }
+
+    fn require_content(&self, context: &Context, _current_block: &Block) -> Vec<PathBuf> {
+        self.get_file_path(context).into_iter().collect()
     }
 }
 
 impl RuleConfiguration for InjectGlobalValue {
-    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+    fn configure(&mut self, mut properties: RuleProperties) -> Result<(), RuleConfigurationError> {
         verify_required_properties(&properties, &["identifier"])?;
-        verify_property_collisions(&properties, &["value", "env"])?;
+        verify_property_collisions(&properties, &["value", "env", "file"])?;
+
+        // the `type` and `default` properties are only used to read the `env` property
+        let env_type = match properties.remove("type") {
+            Some(RulePropertyValue::String(env_type)) => {
+                Some(env_type.parse::<EnvType>().map_err(|message| {
+                    RuleConfigurationError::UnexpectedValue {
+                        property: "type".to_owned(),
+                        message,
+                    }
+                })?)
+            }
+            Some(_) => return Err(RuleConfigurationError::StringExpected("type".to_owned())),
+            None => None,
+        };
+        let mut default = properties.remove("default");
+
+        if let (Some(env_type), Some(value)) = (env_type, &default) {
+            if !env_type.accepts(value) && *value != RulePropertyValue::None {
+                return Err(RuleConfigurationError::UnexpectedValue {
+                    property: "default".to_owned(),
+                    message: format!("expected a value of type `{}`", env_type.as_str()),
+                });
+            }
+        }
+
+        if !properties.contains_key("env") {
+            for (property, is_defined) in
+                [("type", env_type.is_some()), ("default", default.is_some())]
+            {
+                if is_defined {
+                    return Err(RuleConfigurationError::UnexpectedValue {
+                        property: property.to_owned(),
+                        message: "can only be used with the `env` property".to_owned(),
+                    });
+                }
+            }
+        }
 
         for (key, value) in properties {
             match key.as_str() {
@@ -173,27 +455,39 @@ impl RuleConfiguration for InjectGlobalValue {
                     }
                     _ => return Err(RuleConfigurationError::StringExpected(key)),
                 },
-                "value" => match value {
-                    RulePropertyValue::None => {}
-                    RulePropertyValue::String(value) => {
-                        self.value = StringExpression::from_value(value).into();
-                    }
-                    RulePropertyValue::Boolean(value) => {
-                        self.value = Expression::from(value);
-                    }
-                    RulePropertyValue::Usize(value) => {
-                        self.value = DecimalNumber::new(value as f64).into();
-                    }
-                    RulePropertyValue::Float(value) => {
-                        self.value = Expression::from(value);
+                "value" => {
+                    self.value = property_to_expression(value);
+                }
+                "file" => match value {
+                    RulePropertyValue::String(path) => {
+                        let path = PathBuf::from(path);
+                        if !is_data_file(&path) {
+                            return Err(RuleConfigurationError::UnexpectedValue {
+                                property: key,
+                                message: format!(
+                                    "expected a path to a data file (with one of these extensions: {})",
+                                    DATA_EXTENSIONS.join(", ")
+                                ),
+                            });
+                        }
+                        self.file = Some(path);
                     }
-                    _ => return Err(RuleConfigurationError::UnexpectedValueType(key)),
+                    _ => return Err(RuleConfigurationError::StringExpected(key)),
                 },
                 "env" => match value {
                     RulePropertyValue::String(variable_name) => {
                         if let Some(os_value) = env::var_os(&variable_name) {
                             if let Some(value) = os_value.to_str() {
-                                self.value = StringExpression::from_value(value).into();
+                                self.value = env_type
+                                    .unwrap_or(EnvType::String)
+                                    .parse(value)
+                                    .map_err(|message| RuleConfigurationError::UnexpectedValue {
+                                        property: key.clone(),
+                                        message: format!(
+                                            "unable to parse the `{}` environment variable: {}",
+                                            variable_name, message
+                                        ),
+                                    })?;
                             } else {
                                 return Err(RuleConfigurationError::UnexpectedValue {
                                     property: key,
@@ -203,6 +497,8 @@ impl RuleConfiguration for InjectGlobalValue {
                                     ),
                                 });
                             }
+                        } else if let Some(default) = default.take() {
+                            self.value = property_to_expression(default);
                         } else {
                             log::warn!(
                                 "environment variable `{}` is not defined. The rule `{}` will use `nil`",
@@ -231,36 +527,15 @@ impl RuleConfiguration for InjectGlobalValue {
             RulePropertyValue::String(self.identifier.clone()),
         );
 
-        let property_value = match &self.value {
-            Expression::True(_) => RulePropertyValue::Boolean(true),
-            Expression::False(_) => RulePropertyValue::Boolean(false),
-            Expression::Nil(_) => RulePropertyValue::None,
-            Expression::Number(number) => {
-                let value = number.compute_value();
-                if value.trunc() == value && value >= 0.0 && value < usize::MAX as f64 {
-                    RulePropertyValue::Usize(value as usize)
-                } else {
-                    RulePropertyValue::Float(value)
-                }
-            }
-            Expression::String(string) => RulePropertyValue::from(string.get_value()),
-            Expression::Unary(unary) => {
-                if matches!(unary.operator(), UnaryOperator::Minus) {
-                    if let Expression::Number(number) = unary.get_expression() {
-                        RulePropertyValue::Float(-number.compute_value())
-                    } else {
-                        unreachable!(
-                            "unexpected expression for unary minus {:?}",
-                            unary.get_expression()
-                        );
-                    }
-                } else {
-                    unreachable!("unexpected unary operator {:?}", unary.operator());
-                }
-            }
-            _ => unreachable!("unexpected expression {:?}", self.value),
-        };
-        rules.insert("value".to_owned(), property_value);
+        if let Some(file) = &self.file {
+            rules.insert(
+                "file".to_owned(),
+                RulePropertyValue::String(file.display().to_string()),
+            );
+        } else {
+            let property_value = expression_to_property(&self.value);
+            rules.insert("value".to_owned(), property_value);
+        }
 
         rules
     }
@@ -352,4 +627,29 @@ mod test {
 
         assert_json_snapshot!("inject_float_value_as_var", rule);
     }
+
+    #[test]
+    fn serialize_inject_file_as_var() {
+        let rule: Box<dyn Rule> = Box::new(InjectGlobalValue::file("VAR", "build.json"));
+
+        assert_json_snapshot!("inject_file_value_as_var", rule);
+    }
+
+    #[test]
+    fn serialize_inject_table_as_var() {
+        let mut map = BTreeMap::new();
+        map.insert("enabled".to_owned(), RulePropertyValue::from(true));
+        map.insert(
+            "tags".to_owned(),
+            RulePropertyValue::StringList(vec!["a".to_owned(), "b".to_owned()]),
+        );
+        map.insert(
+            "values".to_owned(),
+            RulePropertyValue::List(vec![RulePropertyValue::from(1), RulePropertyValue::None]),
+        );
+        let rule: Box<dyn Rule> =
+            Box::new(InjectGlobalValue::table("VAR", RulePropertyValue::Map(map)));
+
+        assert_json_snapshot!("inject_table_value_as_var", rule);
+    }
 }
@@ -0,0 +1,583 @@
+use crate::nodes::{
+    AssignStatement, Block, CompoundAssignStatement, Expression, FunctionCall, FunctionExpression,
+    FunctionStatement, Identifier, LastStatement, LocalFunctionStatement, Prefix, Statement,
+    Variable,
+};
+use crate::process::{
+    BindingTracker, DefaultVisitor, Evaluator, NodeProcessor, NodeVisitor, Scope, ScopeVisitor,
+};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+
+/// Collects information about an expression returned by a function.
+#[derive(Debug, Default)]
+struct ExpressionInspector {
+    identifiers: Vec<String>,
+    expression_count: usize,
+    has_function: bool,
+    has_variadic_arguments: bool,
+}
+
+impl ExpressionInspector {
+    fn inspect(expression: &Expression) -> Self {
+        let mut inspector = Self::default();
+        DefaultVisitor::visit_expression(&mut expression.clone(), &mut inspector);
+        inspector
+    }
+}
+
+impl NodeProcessor for ExpressionInspector {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        self.expression_count += 1;
+
+        if let Expression::VariableArguments(_) = expression {
+            self.has_variadic_arguments = true;
+        }
+    }
+
+    fn process_function_expression(&mut self, _: &mut FunctionExpression) {
+        self.has_function = true;
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        self.identifiers.push(identifier.get_name().to_owned());
+    }
+}
+
+fn parameter_placeholder(index: usize) -> String {
+    // this name is not a valid Lua identifier, so it can't collide with any variable
+    format!("#{}", index)
+}
+
+/// Replaces the placeholders of the parameters in a function body with the given values.
+struct ParameterReplacer<'a> {
+    values: &'a [Expression],
+}
+
+impl<'a> ParameterReplacer<'a> {
+    fn get_value(&self, identifier: &Identifier) -> Option<&Expression> {
+        identifier
+            .get_name()
+            .strip_prefix('#')
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| self.values.get(index))
+    }
+}
+
+impl<'a> NodeProcessor for ParameterReplacer<'a> {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if let Expression::Identifier(identifier) = expression {
+            if let Some(value) = self.get_value(identifier) {
+                *expression = value.clone();
+            }
+        }
+    }
+
+    fn process_prefix_expression(&mut self, prefix: &mut Prefix) {
+        if let Prefix::Identifier(identifier) = prefix {
+            if let Some(value) = self.get_value(identifier) {
+                *prefix = Prefix::from(value.clone());
+            }
+        }
+    }
+}
+
+/// A local function that can be inlined: its body must only return a single expression.
+#[derive(Debug)]
+struct InlineCandidate {
+    body: Expression,
+    parameter_usages: Vec<usize>,
+    free_identifiers: Vec<(String, Option<usize>)>,
+    body_has_side_effects: bool,
+}
+
+struct InlineAnalyzer {
+    bindings: BindingTracker,
+    candidates: HashMap<usize, InlineCandidate>,
+    reassigned: HashSet<usize>,
+    evaluator: Evaluator,
+    max_size: usize,
+}
+
+impl InlineAnalyzer {
+    fn new(max_size: usize) -> Self {
+        Self {
+            bindings: BindingTracker::default(),
+            candidates: HashMap::new(),
+            reassigned: HashSet::new(),
+            evaluator: Evaluator::default(),
+            max_size,
+        }
+    }
+
+    fn get_returned_expression(function: &LocalFunctionStatement) -> Option<&Expression> {
+        let block = function.get_block();
+
+        if block.iter_statements().next().is_some() {
+            return None;
+        }
+
+        match block.get_last_statement() {
+            Some(LastStatement::Return(statement)) if statement.len() == 1 => {
+                statement.iter_expressions().next()
+            }
+            _ => None,
+        }
+    }
+
+    fn create_candidate(
+        &self,
+        function: &LocalFunctionStatement,
+        function_id: usize,
+    ) -> Option<InlineCandidate> {
+        let expression = Self::get_returned_expression(function)?;
+        let inspector = ExpressionInspector::inspect(expression);
+
+        if inspector.has_function
+            || inspector.has_variadic_arguments
+            || inspector.expression_count > self.max_size
+        {
+            return None;
+        }
+
+        let parameters: Vec<&String> = function
+            .iter_parameters()
+            .map(Identifier::get_name)
+            .collect();
+
+        let unique_parameters: HashSet<_> = parameters.iter().collect();
+        if unique_parameters.len() != parameters.len() {
+            return None;
+        }
+
+        let mut parameter_usages = vec![0; parameters.len()];
+        let mut free_identifiers = Vec::new();
+
+        for identifier in inspector.identifiers {
+            if let Some(index) = parameters
+                .iter()
+                .position(|parameter| *parameter == &identifier)
+            {
+                parameter_usages[index] += 1;
+            } else {
+                let binding = self.bindings.resolve(&identifier);
+
+                if binding == Some(function_id) {
+                    // recursive functions are not inlined
+                    return None;
+                }
+
+                free_identifiers.push((identifier, binding));
+            }
+        }
+
+        let mut body = expression.clone();
+        let placeholders: Vec<Expression> = (0..parameters.len())
+            .map(|index| Expression::identifier(parameter_placeholder(index)))
+            .collect();
+        let mut rename_parameters = RenameParameters {
+            parameters: &parameters,
+            placeholders: &placeholders,
+        };
+        DefaultVisitor::visit_expression(&mut body, &mut rename_parameters);
+
+        Some(InlineCandidate {
+            body_has_side_effects: self.evaluator.has_side_effects(&body),
+            body,
+            parameter_usages,
+            free_identifiers,
+        })
+    }
+
+    fn mark_reassigned(&mut self, identifier: &Identifier) {
+        if let Some(id) = self.bindings.resolve(identifier.get_name()) {
+            self.reassigned.insert(id);
+        }
+    }
+
+    /// Returns the candidates that are never reassigned, along with the set of all the
+    /// reassigned variables.
+    fn into_candidates(self) -> (HashMap<usize, InlineCandidate>, HashSet<usize>) {
+        let Self {
+            mut candidates,
+            reassigned,
+            ..
+        } = self;
+
+        candidates.retain(|id, _| !reassigned.contains(id));
+
+        (candidates, reassigned)
+    }
+}
+
+struct RenameParameters<'a> {
+    parameters: &'a [&'a String],
+    placeholders: &'a [Expression],
+}
+
+impl<'a> NodeProcessor for RenameParameters<'a> {
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        if let Some(index) = self
+            .parameters
+            .iter()
+            .position(|parameter| *parameter == identifier.get_name())
+        {
+            if let Some(Expression::Identifier(placeholder)) = self.placeholders.get(index) {
+                identifier.set_name(placeholder.get_name().to_owned());
+            }
+        }
+    }
+}
+
+impl NodeProcessor for InlineAnalyzer {
+    fn process_assign_statement(&mut self, assign: &mut AssignStatement) {
+        for variable in assign.iter_variables() {
+            if let Variable::Identifier(identifier) = variable {
+                self.mark_reassigned(identifier);
+            }
+        }
+    }
+
+    fn process_compound_assign_statement(&mut self, assign: &mut CompoundAssignStatement) {
+        if let Variable::Identifier(identifier) = assign.get_variable() {
+            self.mark_reassigned(identifier);
+        }
+    }
+
+    fn process_function_statement(&mut self, function: &mut FunctionStatement) {
+        let name = function.get_name();
+
+        if name.get_field_names().is_empty() && name.get_method().is_none() {
+            self.mark_reassigned(name.get_name());
+        }
+    }
+}
+
+impl Scope for InlineAnalyzer {
+    fn push(&mut self) {
+        self.bindings.push();
+    }
+
+    fn pop(&mut self) {
+        self.bindings.pop();
+    }
+
+    fn insert(&mut self, identifier: &mut String) {
+        self.bindings.insert(identifier);
+    }
+
+    fn insert_local(&mut self, identifier: &mut String, value: Option<&mut Expression>) {
+        self.bindings.insert_local(identifier, value);
+    }
+
+    fn insert_local_function(&mut self, function: &mut LocalFunctionStatement) {
+        let id = self.bindings.declare(function.get_name());
+
+        if let Some(candidate) = self.create_candidate(function, id) {
+            self.candidates.insert(id, candidate);
+        }
+    }
+}
+
+struct Inliner {
+    bindings: BindingTracker,
+    candidates: HashMap<usize, InlineCandidate>,
+    reassigned: HashSet<usize>,
+    evaluator: Evaluator,
+}
+
+impl Inliner {
+    fn new(candidates: HashMap<usize, InlineCandidate>, reassigned: HashSet<usize>) -> Self {
+        Self {
+            bindings: BindingTracker::default(),
+            candidates,
+            reassigned,
+            evaluator: Evaluator::default(),
+        }
+    }
+
+    /// Returns true if the expression always evaluates to the same value, no matter when it
+    /// is evaluated.
+    fn is_stable(&self, expression: &Expression) -> bool {
+        ExpressionInspector::inspect(expression)
+            .identifiers
+            .iter()
+            .all(|identifier| {
+                self.bindings
+                    .resolve(identifier)
+                    .filter(|id| !self.reassigned.contains(id))
+                    .is_some()
+            })
+    }
+
+    fn can_be_duplicated(&self, expression: &Expression) -> bool {
+        matches!(
+            expression,
+            Expression::False(_)
+                | Expression::Identifier(_)
+                | Expression::Nil(_)
+                | Expression::Number(_)
+                | Expression::String(_)
+                | Expression::True(_)
+                | Expression::VariableArguments(_)
+        )
+    }
+
+    fn inline_call(&self, call: &FunctionCall) -> Option<Expression> {
+        if call.get_method().is_some() {
+            return None;
+        }
+
+        let candidate = match call.get_prefix() {
+            Prefix::Identifier(identifier) => self
+                .bindings
+                .resolve(identifier.get_name())
+                .and_then(|id| self.candidates.get(&id))?,
+            _ => return None,
+        };
+
+        let references_same_variables = candidate
+            .free_identifiers
+            .iter()
+            .all(|(name, binding)| &self.bindings.resolve(name) == binding);
+
+        if !references_same_variables {
+            return None;
+        }
+
+        let arguments = call.get_arguments().clone().to_expressions();
+
+        for argument in arguments.iter() {
+            if self.evaluator.has_side_effects(argument)
+                || ExpressionInspector::inspect(argument).has_function
+            {
+                return None;
+            }
+        }
+
+        let mut values = Vec::with_capacity(candidate.parameter_usages.len());
+
+        for (index, usage) in candidate.parameter_usages.iter().enumerate() {
+            let value = match arguments.get(index) {
+                Some(Expression::VariableArguments(_)) if index + 1 == arguments.len() => {
+                    let next_parameters_used = candidate
+                        .parameter_usages
+                        .iter()
+                        .skip(index + 1)
+                        .any(|usage| *usage != 0);
+
+                    if next_parameters_used {
+                        // the variadic expression would need to be expanded into multiple
+                        // parameters
+                        return None;
+                    }
+
+                    Expression::variable_arguments().in_parentheses()
+                }
+                Some(Expression::VariableArguments(_)) => {
+                    Expression::variable_arguments().in_parentheses()
+                }
+                Some(argument) => argument.clone(),
+                None => Expression::nil(),
+            };
+
+            if *usage > 1 && !self.can_be_duplicated(&value) && !is_parenthese_variadic(&value) {
+                return None;
+            }
+
+            // when the function body has side effects, an argument that is not stable could
+            // observe them because it gets evaluated after
+            if *usage > 0 && candidate.body_has_side_effects && !self.is_stable(&value) {
+                return None;
+            }
+
+            values.push(value);
+        }
+
+        let mut body = candidate.body.clone();
+        let mut replacer = ParameterReplacer { values: &values };
+        DefaultVisitor::visit_expression(&mut body, &mut replacer);
+
+        Some(body)
+    }
+}
+
+#[inline]
+fn is_parenthese_variadic(expression: &Expression) -> bool {
+    if let Expression::Parenthese(parenthese) = expression {
+        matches!(
+            parenthese.inner_expression(),
+            Expression::VariableArguments(_)
+        )
+    } else {
+        false
+    }
+}
+
+impl NodeProcessor for Inliner {
+    fn process_statement(&mut self, statement: &mut Statement) {
+        while let Statement::Call(call) = statement {
+            if let Some(Expression::Call(inlined_call)) = self.inline_call(call) {
+                *statement = Statement::Call(*inlined_call);
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn process_expression(&mut self, expression: &mut Expression) {
+        // the inlined expression can also be a call to a function that can be inlined
+        while let Expression::Call(call) = expression {
+            if let Some(inlined) = self.inline_call(call) {
+                *expression = inlined;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn process_prefix_expression(&mut self, prefix: &mut Prefix) {
+        while let Prefix::Call(call) = prefix {
+            if let Some(inlined) = self.inline_call(call) {
+                *prefix = Prefix::from(inlined);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+impl Deref for Inliner {
+    type Target = BindingTracker;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bindings
+    }
+}
+
+impl DerefMut for Inliner {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bindings
+    }
+}
+
+pub const INLINE_FUNCTIONS_RULE_NAME: &str = "inline_functions";
+const DEFAULT_MAX_SIZE: usize = 20;
+
+/// A rule that inlines calls to small local functions that only return a single expression.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InlineFunctions {
+    max_size: usize,
+}
+
+impl InlineFunctions {
+    /// Set the maximum number of expressions that the returned expression of a function can
+    /// contain to be inlined.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl Default for InlineFunctions {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+impl FlawlessRule for InlineFunctions {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut analyzer = InlineAnalyzer::new(self.max_size);
+        ScopeVisitor::visit_block(block, &mut analyzer);
+
+        let (candidates, reassigned) = analyzer.into_candidates();
+
+        if candidates.is_empty() {
+            return;
+        }
+
+        let mut inliner = Inliner::new(candidates, reassigned);
+        ScopeVisitor::visit_block(block, &mut inliner);
+    }
+}
+
+impl RuleConfiguration for InlineFunctions {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "max_size" => match value {
+                    RulePropertyValue::Usize(max_size) => {
+                        self.max_size = max_size;
+                    }
+                    _ => return Err(RuleConfigurationError::UsizeExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        INLINE_FUNCTIONS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if self.max_size != DEFAULT_MAX_SIZE {
+            properties.insert("max_size".to_owned(), self.max_size.into());
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> InlineFunctions {
+        InlineFunctions::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_inline_functions", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_max_size() {
+        let rule: Box<dyn Rule> = Box::new(new_rule().with_max_size(5));
+
+        assert_json_snapshot!("inline_functions_with_max_size", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'inline_functions',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
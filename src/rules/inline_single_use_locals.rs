@@ -0,0 +1,822 @@
+use crate::nodes::{
+    Arguments, AssignStatement, BinaryOperator, Block, CompoundAssignStatement, Expression,
+    FunctionCall, FunctionStatement, Identifier, LastStatement, LocalAssignStatement,
+    LocalFunctionStatement, Prefix, Statement, TableEntry, Variable,
+};
+use crate::process::{
+    BindingTracker, DefaultVisitor, Evaluator, NodeProcessor, NodeVisitor, Scope, ScopeVisitor,
+};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use super::verify_no_rule_properties;
+
+fn local_placeholder(id: usize) -> String {
+    // this name is not a valid Lua identifier, so it can't collide with any variable
+    format!("#{}", id)
+}
+
+fn parse_placeholder(name: &str) -> Option<usize> {
+    name.strip_prefix('#')
+        .and_then(|id| id.parse::<usize>().ok())
+}
+
+/// A variable read while evaluating an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Read {
+    Local(usize),
+    Global(String),
+}
+
+/// Describes a use of a local variable that is evaluated directly by a statement of the block
+/// where the variable is declared (not inside a nested block or function).
+#[derive(Debug, Clone)]
+struct DirectUse {
+    statement_index: usize,
+    conditional: bool,
+    side_effects_before: bool,
+    reads_before: Vec<Read>,
+}
+
+#[derive(Debug)]
+struct LocalCandidate {
+    name: String,
+    statement_index: usize,
+    value_identifiers: Vec<(String, Option<usize>)>,
+    value_has_side_effects: bool,
+    value_creates_objects: bool,
+    value_has_variadic_arguments: bool,
+    references: usize,
+    pinned: bool,
+    direct_use: Option<DirectUse>,
+    same_bindings_at_use: bool,
+}
+
+/// Collects information about the value assigned to a local variable.
+#[derive(Debug, Default)]
+struct ValueInspector {
+    identifiers: Vec<String>,
+    creates_objects: bool,
+    has_variadic_arguments: bool,
+}
+
+impl NodeProcessor for ValueInspector {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        match expression {
+            Expression::Function(_) | Expression::Table(_) => self.creates_objects = true,
+            Expression::VariableArguments(_) => self.has_variadic_arguments = true,
+            _ => {}
+        }
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        self.identifiers.push(identifier.get_name().to_owned());
+    }
+}
+
+#[derive(Debug, Default)]
+struct Frame {
+    next_statement: usize,
+    candidates: Vec<usize>,
+    // the statements that may run code which modifies globals or tables
+    side_effect_statements: Vec<usize>,
+}
+
+impl Frame {
+    fn current_statement(&self) -> usize {
+        self.next_statement.saturating_sub(1)
+    }
+}
+
+/// Walks through the expressions that a statement evaluates directly, in the order Lua
+/// evaluates them, to find uses of local variables.
+struct DirectUseScanner<'a> {
+    bindings: &'a BindingTracker,
+    evaluator: &'a Evaluator,
+    candidates: &'a [usize],
+    statement_index: usize,
+    conditional_depth: usize,
+    side_effects_before: bool,
+    reads_before: Vec<Read>,
+    uses: Vec<(usize, DirectUse)>,
+}
+
+impl<'a> DirectUseScanner<'a> {
+    fn scan_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Assign(assign) => {
+                for variable in assign.iter_variables() {
+                    match variable {
+                        Variable::Identifier(_) => {}
+                        Variable::Field(field) => self.scan_prefix(field.get_prefix()),
+                        Variable::Index(index) => {
+                            self.scan_prefix(index.get_prefix());
+                            self.scan_expression(index.get_index());
+                        }
+                    }
+                }
+                assign
+                    .iter_values()
+                    .for_each(|value| self.scan_expression(value));
+
+                // assigning a field or an index can trigger a metamethod
+                self.side_effects_before |= assign
+                    .iter_variables()
+                    .any(|variable| !matches!(variable, Variable::Identifier(_)));
+            }
+            Statement::Call(call) => self.scan_call(call),
+            Statement::CompoundAssign(assign) => {
+                match assign.get_variable() {
+                    Variable::Identifier(identifier) => self.scan_identifier(identifier),
+                    Variable::Field(field) => {
+                        self.scan_prefix(field.get_prefix());
+                        self.side_effects_before = true;
+                    }
+                    Variable::Index(index) => {
+                        self.scan_prefix(index.get_prefix());
+                        self.scan_expression(index.get_index());
+                        self.side_effects_before = true;
+                    }
+                }
+                self.scan_expression(assign.get_value());
+            }
+            Statement::GenericFor(generic_for) => {
+                generic_for
+                    .iter_expressions()
+                    .for_each(|expression| self.scan_expression(expression));
+                self.side_effects_before = true;
+            }
+            Statement::If(if_statement) => {
+                if let Some(branch) = if_statement.iter_branches().next() {
+                    self.scan_expression(branch.get_condition());
+                }
+                self.side_effects_before = true;
+            }
+            Statement::LocalAssign(assign) => assign
+                .iter_values()
+                .for_each(|value| self.scan_expression(value)),
+            Statement::NumericFor(numeric_for) => {
+                self.scan_expression(numeric_for.get_start());
+                self.scan_expression(numeric_for.get_end());
+                if let Some(step) = numeric_for.get_step() {
+                    self.scan_expression(step);
+                }
+                self.side_effects_before = true;
+            }
+            // the nested blocks are not scanned, so they are assumed to have side effects
+            Statement::Do(_) | Statement::Repeat(_) | Statement::While(_) => {
+                self.side_effects_before = true;
+            }
+            Statement::Function(_) | Statement::LocalFunction(_) => {}
+        }
+    }
+
+    fn scan_last_statement(&mut self, statement: &LastStatement) {
+        if let LastStatement::Return(statement) = statement {
+            statement
+                .iter_expressions()
+                .for_each(|expression| self.scan_expression(expression));
+        }
+    }
+
+    fn scan_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Binary(binary) => {
+                self.scan_expression(binary.left());
+
+                if matches!(binary.operator(), BinaryOperator::And | BinaryOperator::Or) {
+                    self.scan_conditional_expression(binary.right());
+                } else {
+                    self.scan_expression(binary.right());
+                }
+
+                self.add_operation(expression);
+            }
+            Expression::Call(call) => self.scan_call(call),
+            Expression::Field(field) => {
+                self.scan_prefix(field.get_prefix());
+                self.add_operation(expression);
+            }
+            Expression::Identifier(identifier) => self.scan_identifier(identifier),
+            Expression::If(if_expression) => {
+                self.scan_expression(if_expression.get_condition());
+                self.scan_conditional_expression(if_expression.get_result());
+                for branch in if_expression.iter_branches() {
+                    self.scan_conditional_expression(branch.get_condition());
+                    self.scan_conditional_expression(branch.get_result());
+                }
+                self.scan_conditional_expression(if_expression.get_else_result());
+            }
+            Expression::Index(index) => {
+                self.scan_prefix(index.get_prefix());
+                self.scan_expression(index.get_index());
+                self.add_operation(expression);
+            }
+            Expression::Parenthese(parenthese) => {
+                self.scan_expression(parenthese.inner_expression())
+            }
+            Expression::Table(table) => {
+                for entry in table.iter_entries() {
+                    match entry {
+                        TableEntry::Field(field) => self.scan_expression(field.get_value()),
+                        TableEntry::Index(index) => {
+                            self.scan_expression(index.get_key());
+                            self.scan_expression(index.get_value());
+                        }
+                        TableEntry::Value(value) => self.scan_expression(value),
+                    }
+                }
+            }
+            Expression::Unary(unary) => {
+                self.scan_expression(unary.get_expression());
+                self.add_operation(expression);
+            }
+            Expression::False(_)
+            | Expression::Function(_)
+            | Expression::Nil(_)
+            | Expression::Number(_)
+            | Expression::String(_)
+            | Expression::True(_)
+            | Expression::VariableArguments(_) => {}
+        }
+    }
+
+    fn scan_conditional_expression(&mut self, expression: &Expression) {
+        self.conditional_depth += 1;
+        self.scan_expression(expression);
+        self.conditional_depth -= 1;
+    }
+
+    fn scan_call(&mut self, call: &FunctionCall) {
+        self.scan_prefix(call.get_prefix());
+
+        if call.get_method().is_some() {
+            // indexing the method can trigger a metamethod
+            self.side_effects_before = true;
+        }
+
+        match call.get_arguments() {
+            Arguments::Tuple(tuple) => tuple
+                .iter_values()
+                .for_each(|value| self.scan_expression(value)),
+            Arguments::Table(table) => self.scan_expression(&table.clone().into()),
+            Arguments::String(_) => {}
+        }
+
+        self.side_effects_before = true;
+    }
+
+    fn scan_prefix(&mut self, prefix: &Prefix) {
+        match prefix {
+            Prefix::Call(call) => self.scan_call(call),
+            Prefix::Field(field) => {
+                self.scan_prefix(field.get_prefix());
+                self.side_effects_before |= self
+                    .evaluator
+                    .has_side_effects(&Expression::Field(field.clone()));
+            }
+            Prefix::Identifier(identifier) => self.scan_identifier(identifier),
+            Prefix::Index(index) => {
+                self.scan_prefix(index.get_prefix());
+                self.scan_expression(index.get_index());
+                self.side_effects_before |= self
+                    .evaluator
+                    .has_side_effects(&Expression::Index(index.clone()));
+            }
+            Prefix::Parenthese(parenthese) => self.scan_expression(parenthese.inner_expression()),
+        }
+    }
+
+    fn scan_identifier(&mut self, identifier: &Identifier) {
+        let name = identifier.get_name();
+
+        match self.bindings.resolve(name) {
+            Some(id) if self.candidates.contains(&id) => {
+                if self.uses.iter().all(|(used_id, _)| *used_id != id) {
+                    self.uses.push((
+                        id,
+                        DirectUse {
+                            statement_index: self.statement_index,
+                            conditional: self.conditional_depth != 0,
+                            side_effects_before: self.side_effects_before,
+                            reads_before: self.reads_before.clone(),
+                        },
+                    ));
+                }
+            }
+            Some(id) => self.reads_before.push(Read::Local(id)),
+            None => self.reads_before.push(Read::Global(name.to_owned())),
+        }
+    }
+
+    fn add_operation(&mut self, expression: &Expression) {
+        // the operation is evaluated after its operands, so a use found in the operands
+        // is not affected by it
+        self.side_effects_before |= self.evaluator.has_side_effects(expression);
+    }
+}
+
+struct LocalAnalyzer {
+    bindings: BindingTracker,
+    frames: Vec<Frame>,
+    candidates: HashMap<usize, LocalCandidate>,
+    reassigned: HashSet<usize>,
+    assigned_globals: HashSet<String>,
+    next_local_is_candidate: bool,
+    evaluator: Evaluator,
+}
+
+impl LocalAnalyzer {
+    fn new() -> Self {
+        Self {
+            bindings: BindingTracker::default(),
+            frames: Vec::new(),
+            candidates: HashMap::new(),
+            reassigned: HashSet::new(),
+            assigned_globals: HashSet::new(),
+            next_local_is_candidate: false,
+            evaluator: Evaluator::default(),
+        }
+    }
+
+    fn mark_assigned(&mut self, identifier: &Identifier) {
+        let name = identifier.get_name();
+
+        match self.bindings.resolve(name) {
+            Some(id) => {
+                self.reassigned.insert(id);
+            }
+            None => {
+                self.assigned_globals.insert(name.to_owned());
+            }
+        }
+    }
+
+    fn scan_direct_uses<F>(&mut self, scan: F)
+    where
+        F: FnOnce(&mut DirectUseScanner),
+    {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        frame.next_statement += 1;
+
+        if frame.candidates.is_empty() {
+            return;
+        }
+
+        let mut scanner = DirectUseScanner {
+            bindings: &self.bindings,
+            evaluator: &self.evaluator,
+            candidates: &frame.candidates,
+            statement_index: frame.current_statement(),
+            conditional_depth: 0,
+            side_effects_before: false,
+            reads_before: Vec::new(),
+            uses: Vec::new(),
+        };
+
+        scan(&mut scanner);
+
+        let statement_index = scanner.statement_index;
+        let statement_has_side_effects = scanner.side_effects_before;
+
+        for (id, mut direct_use) in scanner.uses {
+            if let Some(candidate) = self.candidates.get_mut(&id) {
+                if candidate.references == 0 && candidate.direct_use.is_none() {
+                    direct_use.side_effects_before |= frame
+                        .side_effect_statements
+                        .iter()
+                        .any(|index| *index > candidate.statement_index);
+                    candidate.direct_use = Some(direct_use);
+                }
+            }
+        }
+
+        if statement_has_side_effects {
+            frame.side_effect_statements.push(statement_index);
+        }
+    }
+
+    /// Returns true if the variable read has the same value at the use of the inlined variable.
+    /// A global can be modified by any code that runs before the use, so it is only stable when
+    /// the path to the use does not have side effects.
+    fn is_stable_read(
+        &self,
+        read: &Read,
+        inlined: &HashSet<usize>,
+        side_effects_before_use: bool,
+    ) -> bool {
+        match read {
+            Read::Local(id) => !self.reassigned.contains(id) && !inlined.contains(id),
+            Read::Global(name) => !side_effects_before_use && !self.assigned_globals.contains(name),
+        }
+    }
+
+    /// Returns the ids of the local variables that can be replaced by their value, along with
+    /// the original name of each candidate.
+    fn into_inlined_locals(self) -> (HashSet<usize>, HashMap<usize, String>) {
+        let mut ids: Vec<usize> = self.candidates.keys().copied().collect();
+        ids.sort_unstable();
+
+        let mut inlined = HashSet::new();
+
+        for id in ids {
+            let candidate = &self.candidates[&id];
+
+            if candidate.references != 1
+                || candidate.pinned
+                || !candidate.same_bindings_at_use
+                || self.reassigned.contains(&id)
+            {
+                continue;
+            }
+
+            let depends_on_inlined = candidate
+                .value_identifiers
+                .iter()
+                .any(|(_, binding)| binding.filter(|id| inlined.contains(id)).is_some());
+            let value_has_side_effects = candidate.value_has_side_effects || depends_on_inlined;
+
+            // a use in a nested block or function can run after any other code
+            let side_effects_before_use = candidate
+                .direct_use
+                .as_ref()
+                .map(|direct_use| direct_use.side_effects_before)
+                .unwrap_or(true);
+
+            let movable = !value_has_side_effects
+                && candidate.value_identifiers.iter().all(|(name, binding)| {
+                    let read = match binding {
+                        Some(id) => Read::Local(*id),
+                        None => Read::Global(name.clone()),
+                    };
+                    self.is_stable_read(&read, &inlined, side_effects_before_use)
+                });
+
+            let can_inline = match &candidate.direct_use {
+                Some(direct_use)
+                    if direct_use.statement_index == candidate.statement_index + 1
+                        && !direct_use.conditional =>
+                {
+                    movable
+                        || (!direct_use.side_effects_before
+                            && (!value_has_side_effects
+                                || direct_use
+                                    .reads_before
+                                    .iter()
+                                    .all(|read| self.is_stable_read(read, &inlined, false))))
+                }
+                Some(_) => movable,
+                None => {
+                    movable
+                        && !candidate.value_creates_objects
+                        && !candidate.value_has_variadic_arguments
+                }
+            };
+
+            if can_inline {
+                inlined.insert(id);
+            }
+        }
+
+        let names = self
+            .candidates
+            .into_iter()
+            .map(|(id, candidate)| (id, candidate.name))
+            .collect();
+
+        (inlined, names)
+    }
+}
+
+impl NodeProcessor for LocalAnalyzer {
+    fn process_statement(&mut self, statement: &mut Statement) {
+        self.scan_direct_uses(|scanner| scanner.scan_statement(statement));
+    }
+
+    fn process_last_statement(&mut self, statement: &mut LastStatement) {
+        self.scan_direct_uses(|scanner| scanner.scan_last_statement(statement));
+    }
+
+    fn process_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        self.next_local_is_candidate =
+            statement.variables_len() == 1 && statement.values_len() == 1;
+    }
+
+    fn process_assign_statement(&mut self, assign: &mut AssignStatement) {
+        for variable in assign.iter_variables() {
+            if let Variable::Identifier(identifier) = variable {
+                self.mark_assigned(identifier);
+            }
+        }
+    }
+
+    fn process_compound_assign_statement(&mut self, assign: &mut CompoundAssignStatement) {
+        if let Variable::Identifier(identifier) = assign.get_variable() {
+            self.mark_assigned(identifier);
+        }
+    }
+
+    fn process_function_statement(&mut self, function: &mut FunctionStatement) {
+        let name = function.get_name();
+
+        if name.get_field_names().is_empty() && name.get_method().is_none() {
+            self.mark_assigned(name.get_name());
+        } else if let Some(id) = self.bindings.resolve(name.get_name().get_name()) {
+            // the function name can't be replaced with another expression
+            if let Some(candidate) = self.candidates.get_mut(&id) {
+                candidate.pinned = true;
+            }
+        }
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        let id = match self.bindings.resolve(identifier.get_name()) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let same_bindings = match self.candidates.get(&id) {
+            Some(candidate) if candidate.references == 0 => candidate
+                .value_identifiers
+                .iter()
+                .all(|(name, binding)| self.bindings.resolve(name) == *binding),
+            _ => false,
+        };
+
+        if let Some(candidate) = self.candidates.get_mut(&id) {
+            candidate.references += 1;
+
+            if candidate.references == 1 {
+                candidate.same_bindings_at_use = same_bindings;
+                identifier.set_name(local_placeholder(id));
+            }
+        }
+    }
+}
+
+impl Scope for LocalAnalyzer {
+    fn push(&mut self) {
+        self.bindings.push();
+        self.frames.push(Frame::default());
+    }
+
+    fn pop(&mut self) {
+        self.bindings.pop();
+        self.frames.pop();
+    }
+
+    fn insert(&mut self, identifier: &mut String) {
+        self.bindings.insert(identifier);
+    }
+
+    fn insert_local(&mut self, identifier: &mut String, value: Option<&mut Expression>) {
+        let is_candidate = mem::take(&mut self.next_local_is_candidate);
+
+        let value = match value {
+            Some(value) if is_candidate => value,
+            _ => {
+                self.bindings.insert_local(identifier, None);
+                return;
+            }
+        };
+
+        let mut inspector = ValueInspector::default();
+        DefaultVisitor::visit_expression(&mut value.clone(), &mut inspector);
+
+        let value_identifiers = inspector
+            .identifiers
+            .into_iter()
+            .map(|name| {
+                // a candidate used in the value was already renamed to its placeholder
+                match parse_placeholder(&name)
+                    .and_then(|id| self.candidates.get(&id).map(|candidate| (id, candidate)))
+                {
+                    Some((id, candidate)) => (candidate.name.clone(), Some(id)),
+                    None => {
+                        let binding = self.bindings.resolve(&name);
+                        (name, binding)
+                    }
+                }
+            })
+            .collect();
+
+        let id = self.bindings.declare(identifier);
+
+        let statement_index = self
+            .frames
+            .last()
+            .map(Frame::current_statement)
+            .unwrap_or_default();
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.candidates.push(id);
+        }
+
+        self.candidates.insert(
+            id,
+            LocalCandidate {
+                name: mem::replace(identifier, local_placeholder(id)),
+                statement_index,
+                value_identifiers,
+                value_has_side_effects: self.evaluator.has_side_effects(value),
+                value_creates_objects: inspector.creates_objects,
+                value_has_variadic_arguments: inspector.has_variadic_arguments,
+                references: 0,
+                pinned: false,
+                direct_use: None,
+                same_bindings_at_use: false,
+            },
+        );
+    }
+
+    fn insert_local_function(&mut self, function: &mut LocalFunctionStatement) {
+        self.bindings.insert_local_function(function);
+    }
+}
+
+/// Takes the values of the local variables that are inlined out of their declaration.
+struct ValueCollector<'a> {
+    inlined: &'a HashSet<usize>,
+    values: HashMap<usize, Expression>,
+    evaluator: Evaluator,
+}
+
+impl<'a> NodeProcessor for ValueCollector<'a> {
+    fn process_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        let id = match statement.get_variables().as_slice() {
+            [variable] => parse_placeholder(variable.get_name()),
+            _ => None,
+        };
+
+        if let Some(id) = id.filter(|id| self.inlined.contains(id)) {
+            if let Some(value) = statement.iter_mut_values().next() {
+                let mut value = mem::replace(value, Expression::nil());
+
+                // the value is not part of the tree anymore, so it must be visited here
+                DefaultVisitor::visit_expression(&mut value, self);
+
+                if self.evaluator.can_return_multiple_values(&value) {
+                    value = value.in_parentheses();
+                }
+
+                self.values.insert(id, value);
+            }
+        }
+    }
+}
+
+/// Removes the declarations of the inlined variables, replaces their use with their value and
+/// restores the name of the variables that are kept.
+struct LocalReplacer<'a> {
+    inlined: &'a HashSet<usize>,
+    names: &'a HashMap<usize, String>,
+    values: HashMap<usize, Expression>,
+}
+
+impl<'a> LocalReplacer<'a> {
+    fn take_value(&mut self, identifier: &Identifier) -> Option<Expression> {
+        parse_placeholder(identifier.get_name()).and_then(|id| self.values.remove(&id))
+    }
+
+    fn restore_name(&self, identifier: &mut Identifier) {
+        if let Some(name) =
+            parse_placeholder(identifier.get_name()).and_then(|id| self.names.get(&id))
+        {
+            identifier.set_name(name);
+        }
+    }
+}
+
+impl<'a> NodeProcessor for LocalReplacer<'a> {
+    fn process_block(&mut self, block: &mut Block) {
+        let inlined = self.inlined;
+
+        block.filter_statements(|statement| match statement {
+            Statement::LocalAssign(assign) => match assign.get_variables().as_slice() {
+                [variable] => parse_placeholder(variable.get_name())
+                    .filter(|id| inlined.contains(id))
+                    .is_none(),
+                _ => true,
+            },
+            _ => true,
+        });
+    }
+
+    fn process_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        statement.for_each_assignment(|variable, _| self.restore_name(variable));
+    }
+
+    fn process_expression(&mut self, expression: &mut Expression) {
+        // the value can also be another inlined variable
+        while let Expression::Identifier(identifier) = expression {
+            match self.take_value(identifier) {
+                Some(value) => *expression = value,
+                None => break,
+            }
+        }
+    }
+
+    fn process_prefix_expression(&mut self, prefix: &mut Prefix) {
+        while let Prefix::Identifier(identifier) = prefix {
+            match self.take_value(identifier) {
+                Some(value) => *prefix = Prefix::from(value),
+                None => break,
+            }
+        }
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        self.restore_name(identifier);
+    }
+}
+
+pub const INLINE_SINGLE_USE_LOCALS_RULE_NAME: &str = "inline_single_use_locals";
+
+/// A rule that replaces local variables used only once with their value.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct InlineSingleUseLocals {}
+
+impl FlawlessRule for InlineSingleUseLocals {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut analyzer = LocalAnalyzer::new();
+        ScopeVisitor::visit_block(block, &mut analyzer);
+
+        if analyzer.candidates.is_empty() {
+            return;
+        }
+
+        let (inlined, names) = analyzer.into_inlined_locals();
+
+        let mut collector = ValueCollector {
+            inlined: &inlined,
+            values: HashMap::new(),
+            evaluator: Evaluator::default(),
+        };
+        DefaultVisitor::visit_block(block, &mut collector);
+
+        let mut replacer = LocalReplacer {
+            inlined: &inlined,
+            names: &names,
+            values: collector.values,
+        };
+        DefaultVisitor::visit_block(block, &mut replacer);
+    }
+}
+
+impl RuleConfiguration for InlineSingleUseLocals {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        verify_no_rule_properties(&properties)?;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        INLINE_SINGLE_USE_LOCALS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        RuleProperties::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> InlineSingleUseLocals {
+        InlineSingleUseLocals::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_inline_single_use_locals", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'inline_single_use_locals',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
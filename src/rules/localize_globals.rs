@@ -0,0 +1,451 @@
+use crate::nodes::{
+    AssignStatement, Block, CompoundAssignStatement, Expression, FieldExpression,
+    FunctionStatement, Identifier, LocalAssignStatement, Prefix, Statement, Variable,
+};
+use crate::process::{BindingTracker, DefaultVisitor, NodeProcessor, NodeVisitor, ScopeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+
+use super::rename_variables::globals;
+use super::variable_usage::{
+    generate_variable_name, get_available_locals, get_prefix_root, VariableUsage,
+};
+
+/// Returns the names of a chain of fields starting from an identifier (like `string.format`).
+fn get_field_chain(field: &FieldExpression) -> Option<Vec<String>> {
+    let mut chain = vec![field.get_field().get_name().to_owned()];
+    let mut prefix = field.get_prefix();
+
+    loop {
+        match prefix {
+            Prefix::Field(field) => {
+                chain.push(field.get_field().get_name().to_owned());
+                prefix = field.get_prefix();
+            }
+            Prefix::Identifier(identifier) => {
+                chain.push(identifier.get_name().to_owned());
+                chain.reverse();
+                return Some(chain);
+            }
+            Prefix::Call(_) | Prefix::Index(_) | Prefix::Parenthese(_) => return None,
+        }
+    }
+}
+
+fn get_variable_root(variable: &Variable) -> Option<&str> {
+    match variable {
+        Variable::Identifier(_) => None,
+        Variable::Field(field) => get_prefix_root(field.get_prefix()),
+        Variable::Index(index) => get_prefix_root(index.get_prefix()),
+    }
+}
+
+#[derive(Default)]
+struct GlobalCollector {
+    tracker: BindingTracker,
+    global_uses: HashMap<String, usize>,
+    chain_uses: HashMap<Vec<String>, usize>,
+    assigned: HashSet<String>,
+    field_assigned: HashSet<String>,
+    // the number of prefixes to skip because they are part of a chain that was already counted
+    chain_prefixes_to_skip: usize,
+}
+
+impl Deref for GlobalCollector {
+    type Target = BindingTracker;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tracker
+    }
+}
+
+impl DerefMut for GlobalCollector {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tracker
+    }
+}
+
+impl GlobalCollector {
+    fn is_global(&self, name: &str) -> bool {
+        self.resolve(name).is_none()
+    }
+
+    fn collect_field(&mut self, field: &FieldExpression) {
+        if self.chain_prefixes_to_skip > 0 {
+            self.chain_prefixes_to_skip -= 1;
+            return;
+        }
+
+        if let Some(chain) = get_field_chain(field) {
+            if self.is_global(&chain[0]) {
+                self.chain_prefixes_to_skip = chain.len() - 2;
+                *self.chain_uses.entry(chain).or_default() += 1;
+            }
+        }
+    }
+
+    fn assign_variable(&mut self, variable: &Variable) {
+        match variable {
+            Variable::Identifier(identifier) => {
+                if self.is_global(identifier.get_name()) {
+                    self.assigned.insert(identifier.get_name().to_owned());
+                }
+            }
+            Variable::Field(_) | Variable::Index(_) => {
+                if let Some(root) = get_variable_root(variable) {
+                    if self.is_global(root) {
+                        self.field_assigned.insert(root.to_owned());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl NodeProcessor for GlobalCollector {
+    fn process_assign_statement(&mut self, assign: &mut AssignStatement) {
+        for variable in assign.iter_variables() {
+            self.assign_variable(variable);
+        }
+    }
+
+    fn process_compound_assign_statement(&mut self, assign: &mut CompoundAssignStatement) {
+        self.assign_variable(assign.get_variable());
+    }
+
+    fn process_function_statement(&mut self, function: &mut FunctionStatement) {
+        let name = function.get_name();
+        let root = name.get_name().get_name();
+
+        if self.is_global(root) {
+            if name.get_field_names().is_empty() && name.get_method().is_none() {
+                self.assigned.insert(root.to_owned());
+            } else {
+                self.field_assigned.insert(root.to_owned());
+            }
+        }
+    }
+
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if let Expression::Field(field) = expression {
+            self.collect_field(field);
+        }
+    }
+
+    fn process_prefix_expression(&mut self, prefix: &mut Prefix) {
+        if let Prefix::Field(field) = prefix {
+            self.collect_field(field);
+        }
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        if self.is_global(identifier.get_name()) {
+            *self
+                .global_uses
+                .entry(identifier.get_name().to_owned())
+                .or_default() += 1;
+        }
+    }
+}
+
+struct ChainReplacer {
+    tracker: BindingTracker,
+    aliases: HashMap<Vec<String>, Identifier>,
+}
+
+impl Deref for ChainReplacer {
+    type Target = BindingTracker;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tracker
+    }
+}
+
+impl DerefMut for ChainReplacer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tracker
+    }
+}
+
+impl ChainReplacer {
+    fn get_alias(&self, field: &FieldExpression) -> Option<Identifier> {
+        let chain = get_field_chain(field)?;
+
+        if self.resolve(&chain[0]).is_some() {
+            return None;
+        }
+
+        self.aliases.get(&chain).cloned()
+    }
+}
+
+impl NodeProcessor for ChainReplacer {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if let Expression::Field(field) = expression {
+            if let Some(alias) = self.get_alias(field) {
+                *expression = Expression::Identifier(alias);
+            }
+        }
+    }
+
+    fn process_prefix_expression(&mut self, prefix: &mut Prefix) {
+        if let Prefix::Field(field) = prefix {
+            if let Some(alias) = self.get_alias(field) {
+                *prefix = Prefix::Identifier(alias);
+            }
+        }
+    }
+}
+
+fn build_chain_expression(chain: &[String]) -> Expression {
+    let mut prefix = Prefix::from_name(&chain[0]);
+
+    for field in chain.iter().skip(1) {
+        prefix = FieldExpression::new(prefix, Identifier::new(field)).into();
+    }
+
+    prefix.into()
+}
+
+pub const LOCALIZE_GLOBALS_RULE_NAME: &str = "localize_globals";
+const DEFAULT_MIN_USES: usize = 3;
+
+/// A rule that declares local variables at the top of the file for globals that are used
+/// frequently, and for fields of library tables (like `string.format`).
+#[derive(Debug, PartialEq, Eq)]
+pub struct LocalizeGlobals {
+    globals: Vec<String>,
+    min_uses: usize,
+}
+
+impl Default for LocalizeGlobals {
+    fn default() -> Self {
+        Self {
+            globals: globals::DEFAULT.iter().map(ToString::to_string).collect(),
+            min_uses: DEFAULT_MIN_USES,
+        }
+    }
+}
+
+impl LocalizeGlobals {
+    pub fn new<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        Self {
+            globals: iter.into_iter().collect(),
+            min_uses: DEFAULT_MIN_USES,
+        }
+    }
+
+    pub fn with_min_uses(mut self, min_uses: usize) -> Self {
+        self.min_uses = min_uses;
+        self
+    }
+
+    fn can_localize(&self, name: &str) -> bool {
+        // keep `require` calls visible to tools that look for them
+        name != "require" && self.globals.iter().any(|global| global == name)
+    }
+}
+
+impl FlawlessRule for LocalizeGlobals {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut collector = GlobalCollector::default();
+        ScopeVisitor::visit_block(block, &mut collector);
+
+        let mut localized_chains: Vec<(Vec<String>, usize)> = collector
+            .chain_uses
+            .iter()
+            .filter(|(chain, uses)| {
+                let root = chain[0].as_str();
+                **uses >= self.min_uses
+                    && self.can_localize(root)
+                    && globals::LIBRARIES.contains(&root)
+                    && !collector.assigned.contains(root)
+                    && !collector.field_assigned.contains(root)
+            })
+            .map(|(chain, uses)| (chain.clone(), *uses))
+            .collect();
+
+        let mut localized_globals: Vec<(String, usize)> = collector
+            .global_uses
+            .iter()
+            .filter_map(|(name, uses)| {
+                let chain_uses: usize = localized_chains
+                    .iter()
+                    .filter(|(chain, _)| &chain[0] == name)
+                    .map(|(_, chain_uses)| chain_uses)
+                    .sum();
+                let remaining_uses = uses.saturating_sub(chain_uses);
+
+                (remaining_uses >= self.min_uses
+                    && self.can_localize(name)
+                    && !collector.assigned.contains(name))
+                .then(|| (name.clone(), remaining_uses))
+            })
+            .collect();
+
+        let available_locals = get_available_locals(block);
+
+        if localized_globals.len() + localized_chains.len() > available_locals {
+            let mut uses: Vec<usize> = localized_globals
+                .iter()
+                .map(|(_, uses)| *uses)
+                .chain(localized_chains.iter().map(|(_, uses)| *uses))
+                .collect();
+            uses.sort_unstable_by(|a, b| b.cmp(a));
+
+            let min_uses = uses.get(available_locals).copied().unwrap_or(0) + 1;
+            localized_globals.retain(|(_, uses)| *uses >= min_uses);
+            localized_chains.retain(|(_, uses)| *uses >= min_uses);
+        }
+
+        if localized_globals.is_empty() && localized_chains.is_empty() {
+            return;
+        }
+
+        localized_globals.sort();
+        localized_chains.sort();
+
+        let mut usage = VariableUsage::default();
+        DefaultVisitor::visit_block(block, &mut usage);
+        let mut names = usage.names;
+
+        let mut declarations: Vec<Statement> = localized_globals
+            .into_iter()
+            .map(|(name, _)| {
+                LocalAssignStatement::from_variable(name.as_str())
+                    .with_value(Identifier::new(name))
+                    .into()
+            })
+            .collect();
+
+        let mut aliases = HashMap::new();
+
+        for (chain, _) in localized_chains {
+            let value = build_chain_expression(&chain);
+            let alias = Identifier::new(generate_variable_name(&value, &mut names));
+
+            declarations.push(
+                LocalAssignStatement::from_variable(alias.clone())
+                    .with_value(value)
+                    .into(),
+            );
+            aliases.insert(chain, alias);
+        }
+
+        let mut replacer = ChainReplacer {
+            tracker: BindingTracker::default(),
+            aliases,
+        };
+        ScopeVisitor::visit_block(block, &mut replacer);
+
+        for (index, declaration) in declarations.into_iter().enumerate() {
+            block.insert_statement(index, declaration);
+        }
+    }
+}
+
+impl RuleConfiguration for LocalizeGlobals {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "globals" => match value {
+                    RulePropertyValue::StringList(list) => {
+                        self.globals.clear();
+                        globals::extend_globals(&mut self.globals, list)?;
+                    }
+                    _ => return Err(RuleConfigurationError::StringListExpected(key)),
+                },
+                "min_uses" => match value {
+                    RulePropertyValue::Usize(min_uses) => {
+                        self.min_uses = min_uses;
+                    }
+                    _ => return Err(RuleConfigurationError::UsizeExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        LOCALIZE_GLOBALS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        let globals = globals::normalize_globals(&self.globals);
+        if !(globals.len() == 1 && globals.contains(&"$default".to_owned())) {
+            properties.insert("globals".to_owned(), RulePropertyValue::StringList(globals));
+        }
+
+        if self.min_uses != DEFAULT_MIN_USES {
+            properties.insert(
+                "min_uses".to_owned(),
+                RulePropertyValue::Usize(self.min_uses),
+            );
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> LocalizeGlobals {
+        LocalizeGlobals::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_localize_globals", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_min_uses() {
+        let rule: Box<dyn Rule> = Box::new(new_rule().with_min_uses(5));
+
+        assert_json_snapshot!("localize_globals_with_min_uses", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_roblox_globals() {
+        let rule: Box<dyn Rule> = Box::new(LocalizeGlobals::new(
+            globals::DEFAULT
+                .iter()
+                .chain(globals::ROBLOX.iter())
+                .map(ToString::to_string),
+        ));
+
+        assert_json_snapshot!("localize_globals_with_roblox_globals", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'localize_globals',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
@@ -1,44 +1,77 @@
 //! A module that contains the different rules that mutates a Lua block.
 
+mod bundle_requires;
 mod call_parens;
 mod compute_expression;
 mod configuration_error;
 mod convert_index_to_field;
+mod convert_require;
+mod deduplicate_constants;
+mod eliminate_common_subexpressions;
+pub(crate) mod embed_data_files;
 mod empty_do;
+mod encode_strings;
 mod filter_early_return;
+mod flatten_control_flow;
 mod group_local;
+mod hoist_loop_invariants;
 mod inject_value;
+mod inline_functions;
+mod inline_single_use_locals;
+mod localize_globals;
 mod method_def;
 mod no_local_function;
+mod obfuscate_numbers;
+mod remove_calls;
 mod remove_comments;
 mod remove_compound_assign;
 mod remove_nil_declarations;
 mod remove_spaces;
+mod remove_unused_declarations;
+mod rename_fields;
 mod rename_variables;
+pub(crate) mod require_path;
 mod rule_property;
 mod unused_if_branch;
 mod unused_while;
+mod variable_usage;
 
+pub use bundle_requires::*;
 pub use call_parens::*;
 pub use compute_expression::*;
 pub use configuration_error::RuleConfigurationError;
 pub use convert_index_to_field::*;
+pub use convert_require::*;
+pub use deduplicate_constants::*;
+pub use eliminate_common_subexpressions::*;
+pub use embed_data_files::*;
 pub use empty_do::*;
+pub use encode_strings::*;
 pub use filter_early_return::*;
+pub use flatten_control_flow::*;
 pub use group_local::*;
+pub use hoist_loop_invariants::*;
 pub use inject_value::*;
+pub use inline_functions::*;
+pub use inline_single_use_locals::*;
+pub use localize_globals::*;
 pub use method_def::*;
 pub use no_local_function::*;
+pub use obfuscate_numbers::*;
+pub use remove_calls::*;
 pub use remove_comments::*;
 pub use remove_compound_assign::*;
 pub use remove_nil_declarations::*;
 pub use remove_spaces::*;
+pub use remove_unused_declarations::*;
+pub use rename_fields::*;
 pub use rename_variables::*;
 pub use rule_property::*;
 pub use unused_if_branch::*;
 pub use unused_while::*;
 
 use crate::nodes::Block;
+use crate::RojoProject;
 
 use serde::de::{self, MapAccess, Visitor};
 use serde::ser::SerializeMap;
@@ -52,6 +85,8 @@ use std::str::FromStr;
 pub struct ContextBuilder<'a> {
     path: PathBuf,
     blocks: HashMap<PathBuf, &'a Block>,
+    project: Option<&'a RojoProject>,
+    configuration_location: Option<&'a Path>,
 }
 
 impl<'a> ContextBuilder<'a> {
@@ -59,13 +94,28 @@ impl<'a> ContextBuilder<'a> {
         Self {
             path: path.into(),
             blocks: Default::default(),
+            project: None,
+            configuration_location: None,
         }
     }
 
+    pub fn with_project(mut self, project: &'a RojoProject) -> Self {
+        self.project = Some(project);
+        self
+    }
+
+    pub fn with_configuration_location(mut self, location: &'a Path) -> Self {
+        self.configuration_location = Some(location);
+        self
+    }
+
     pub fn build(self) -> Context<'a> {
         Context {
             path: self.path,
             blocks: self.blocks,
+            project: self.project,
+            configuration_location: self.configuration_location,
+            renamed_variables: Vec::new(),
         }
     }
 
@@ -79,6 +129,9 @@ impl<'a> ContextBuilder<'a> {
 pub struct Context<'a> {
     path: PathBuf,
     blocks: HashMap<PathBuf, &'a Block>,
+    project: Option<&'a RojoProject>,
+    configuration_location: Option<&'a Path>,
+    renamed_variables: Vec<(PathBuf, Vec<RenamedVariable>)>,
 }
 
 impl<'a> Context<'a> {
@@ -89,6 +142,31 @@ impl<'a> Context<'a> {
     pub fn current_path(&self) -> &Path {
         self.path.as_ref()
     }
+
+    /// Returns the Rojo project given to darklua, which maps Roblox instances to files.
+    pub fn project(&self) -> Option<&RojoProject> {
+        self.project
+    }
+
+    /// Returns the directory of the configuration file, from which the paths given to rules
+    /// are resolved.
+    pub fn configuration_location(&self) -> Option<&Path> {
+        self.configuration_location
+    }
+
+    /// Adds renamed variables of the current file to the name mapping written at the given
+    /// path once all files are processed.
+    pub fn add_renamed_variables(
+        &mut self,
+        mapping: impl Into<PathBuf>,
+        variables: Vec<RenamedVariable>,
+    ) {
+        self.renamed_variables.push((mapping.into(), variables));
+    }
+
+    pub(crate) fn take_renamed_variables(&mut self) -> Vec<(PathBuf, Vec<RenamedVariable>)> {
+        std::mem::take(&mut self.renamed_variables)
+    }
 }
 
 pub type RuleProcessResult = Result<(), String>;
@@ -100,8 +178,9 @@ pub trait Rule: RuleConfiguration {
     fn process(&self, block: &mut Block, context: &mut Context) -> RuleProcessResult;
 
     /// Return the list of paths to Lua files that is necessary to apply this rule. This will load
-    /// each AST block from these files into the context object.
-    fn require_content(&self, _current_source: &Path, _current_block: &Block) -> Vec<PathBuf> {
+    /// each AST block from these files into the context object. The given context does not
+    /// contain any block yet.
+    fn require_content(&self, _context: &Context, _current_block: &Block) -> Vec<PathBuf> {
         Vec::new()
     }
 }
@@ -154,12 +233,25 @@ pub fn get_default_rules() -> Vec<Box<dyn Rule>> {
 
 pub fn get_all_rule_names() -> Vec<&'static str> {
     vec![
+        BUNDLE_REQUIRES_RULE_NAME,
         COMPUTE_EXPRESSIONS_RULE_NAME,
         CONVERT_INDEX_TO_FIELD_RULE_NAME,
         CONVERT_LOCAL_FUNCTION_TO_ASSIGN_RULE_NAME,
+        CONVERT_REQUIRE_RULE_NAME,
+        DEDUPLICATE_CONSTANTS_RULE_NAME,
+        ELIMINATE_COMMON_SUBEXPRESSIONS_RULE_NAME,
+        EMBED_DATA_FILES_RULE_NAME,
+        ENCODE_STRINGS_RULE_NAME,
         FILTER_AFTER_EARLY_RETURN_RULE_NAME,
+        FLATTEN_CONTROL_FLOW_RULE_NAME,
         GROUP_LOCAL_ASSIGNMENT_RULE_NAME,
+        HOIST_LOOP_INVARIANTS_RULE_NAME,
         INJECT_GLOBAL_VALUE_RULE_NAME,
+        INLINE_FUNCTIONS_RULE_NAME,
+        INLINE_SINGLE_USE_LOCALS_RULE_NAME,
+        LOCALIZE_GLOBALS_RULE_NAME,
+        OBFUSCATE_NUMBERS_RULE_NAME,
+        REMOVE_CALLS_RULE_NAME,
         REMOVE_COMMENTS_RULE_NAME,
         REMOVE_COMPOUND_ASSIGNMENT_RULE_NAME,
         REMOVE_EMPTY_DO_RULE_NAME,
@@ -167,8 +259,10 @@ pub fn get_all_rule_names() -> Vec<&'static str> {
         REMOVE_METHOD_DEFINITION_RULE_NAME,
         REMOVE_NIL_DECLARATION_RULE_NAME,
         REMOVE_SPACES_RULE_NAME,
+        REMOVE_UNUSED_DECLARATIONS_RULE_NAME,
         REMOVE_UNUSED_IF_BRANCH_RULE_NAME,
         REMOVE_UNUSED_WHILE_RULE_NAME,
+        RENAME_FIELDS_RULE_NAME,
         RENAME_VARIABLES_RULE_NAME,
     ]
 }
@@ -178,14 +272,29 @@ impl FromStr for Box<dyn Rule> {
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         let rule: Box<dyn Rule> = match string {
+            BUNDLE_REQUIRES_RULE_NAME => Box::<BundleRequires>::default(),
             COMPUTE_EXPRESSIONS_RULE_NAME => Box::<ComputeExpression>::default(),
             CONVERT_INDEX_TO_FIELD_RULE_NAME => Box::<ConvertIndexToField>::default(),
             CONVERT_LOCAL_FUNCTION_TO_ASSIGN_RULE_NAME => {
                 Box::<ConvertLocalFunctionToAssign>::default()
             }
+            CONVERT_REQUIRE_RULE_NAME => Box::<ConvertRequire>::default(),
+            DEDUPLICATE_CONSTANTS_RULE_NAME => Box::<DeduplicateConstants>::default(),
+            ELIMINATE_COMMON_SUBEXPRESSIONS_RULE_NAME => {
+                Box::<EliminateCommonSubexpressions>::default()
+            }
+            EMBED_DATA_FILES_RULE_NAME => Box::<EmbedDataFiles>::default(),
+            ENCODE_STRINGS_RULE_NAME => Box::<EncodeStrings>::default(),
             FILTER_AFTER_EARLY_RETURN_RULE_NAME => Box::<FilterAfterEarlyReturn>::default(),
+            FLATTEN_CONTROL_FLOW_RULE_NAME => Box::<FlattenControlFlow>::default(),
             GROUP_LOCAL_ASSIGNMENT_RULE_NAME => Box::<GroupLocalAssignment>::default(),
+            HOIST_LOOP_INVARIANTS_RULE_NAME => Box::<HoistLoopInvariants>::default(),
             INJECT_GLOBAL_VALUE_RULE_NAME => Box::<InjectGlobalValue>::default(),
+            INLINE_FUNCTIONS_RULE_NAME => Box::<InlineFunctions>::default(),
+            INLINE_SINGLE_USE_LOCALS_RULE_NAME => Box::<InlineSingleUseLocals>::default(),
+            LOCALIZE_GLOBALS_RULE_NAME => Box::<LocalizeGlobals>::default(),
+            OBFUSCATE_NUMBERS_RULE_NAME => Box::<ObfuscateNumbers>::default(),
+            REMOVE_CALLS_RULE_NAME => Box::<RemoveCalls>::default(),
             REMOVE_COMMENTS_RULE_NAME => Box::<RemoveComments>::default(),
             REMOVE_COMPOUND_ASSIGNMENT_RULE_NAME => Box::<RemoveCompoundAssignment>::default(),
             REMOVE_EMPTY_DO_RULE_NAME => Box::<RemoveEmptyDo>::default(),
@@ -193,8 +302,10 @@ impl FromStr for Box<dyn Rule> {
             REMOVE_METHOD_DEFINITION_RULE_NAME => Box::<RemoveMethodDefinition>::default(),
             REMOVE_NIL_DECLARATION_RULE_NAME => Box::<RemoveNilDeclaration>::default(),
             REMOVE_SPACES_RULE_NAME => Box::<RemoveSpaces>::default(),
+            REMOVE_UNUSED_DECLARATIONS_RULE_NAME => Box::<RemoveUnusedDeclarations>::default(),
             REMOVE_UNUSED_IF_BRANCH_RULE_NAME => Box::<RemoveUnusedIfBranch>::default(),
             REMOVE_UNUSED_WHILE_RULE_NAME => Box::<RemoveUnusedWhile>::default(),
+            RENAME_FIELDS_RULE_NAME => Box::<RenameFields>::default(),
             RENAME_VARIABLES_RULE_NAME => Box::<RenameVariables>::default(),
             _ => return Err(format!("invalid rule name: {}", string)),
         };
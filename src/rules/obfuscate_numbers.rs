@@ -0,0 +1,268 @@
+use crate::nodes::{BinaryExpression, BinaryOperator, Block, DecimalNumber, Expression};
+use crate::process::utils::SeededRandom;
+use crate::process::{DefaultVisitor, Evaluator, LuaValue, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+// numbers above 2^53 cannot represent every integer, so they are left unchanged
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+const MAX_OFFSET: u64 = 10_000;
+const MAX_FACTOR: u64 = 64;
+const FORM_COUNT: usize = 3;
+
+fn number(value: f64) -> Option<Expression> {
+    if value.is_finite() && value.is_sign_positive() {
+        Some(DecimalNumber::new(value).into())
+    } else {
+        None
+    }
+}
+
+fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+    BinaryExpression::new(operator, left, right).into()
+}
+
+struct NumberObfuscator {
+    random: SeededRandom,
+    evaluator: Evaluator,
+    generated_numbers: usize,
+}
+
+impl NumberObfuscator {
+    fn new(seed: u64) -> Self {
+        Self {
+            random: SeededRandom::new(seed),
+            evaluator: Evaluator::default(),
+            generated_numbers: 0,
+        }
+    }
+
+    /// Builds one of the arithmetic expressions that computes the given value, and returns it
+    /// with the number of literals it contains.
+    fn build(&mut self, form: usize, value: f64) -> Option<(Expression, usize)> {
+        match form {
+            0 => {
+                let left = self.random.range(0, value.floor() as u64) as f64;
+                let expression = binary(BinaryOperator::Plus, number(left)?, number(value - left)?);
+                Some((expression, 2))
+            }
+            1 => {
+                let right = self.random.range(1, MAX_OFFSET) as f64;
+                let expression = binary(
+                    BinaryOperator::Minus,
+                    number(value + right)?,
+                    number(right)?,
+                );
+                Some((expression, 2))
+            }
+            _ => {
+                let factor = self.random.range(2, MAX_FACTOR) as f64;
+                let quotient = (value / factor).floor();
+                if quotient == 0.0 {
+                    return None;
+                }
+                let product = binary(BinaryOperator::Asterisk, number(factor)?, number(quotient)?);
+                let expression = binary(
+                    BinaryOperator::Plus,
+                    product,
+                    number(value - factor * quotient)?,
+                );
+                Some((expression, 3))
+            }
+        }
+    }
+
+    fn is_exact(&self, expression: &Expression, value: f64) -> bool {
+        matches!(
+            self.evaluator.evaluate(expression),
+            LuaValue::Number(result) if result.to_bits() == value.to_bits()
+        )
+    }
+
+    fn obfuscate(&mut self, value: f64) -> Option<(Expression, usize)> {
+        if !value.is_finite() || value > MAX_SAFE_INTEGER {
+            return None;
+        }
+
+        let first_form = self.random.index(FORM_COUNT);
+
+        for offset in 0..FORM_COUNT {
+            if let Some((expression, count)) = self.build((first_form + offset) % FORM_COUNT, value)
+            {
+                if self.is_exact(&expression, value) {
+                    return Some((expression, count));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl NodeProcessor for NumberObfuscator {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if let Expression::Number(number) = expression {
+            // the visitor goes through the replacement right after it is inserted, so the next
+            // numbers are the ones that were just generated
+            if self.generated_numbers > 0 {
+                self.generated_numbers -= 1;
+                return;
+            }
+
+            if let Some((replacement, count)) = self.obfuscate(number.compute_value()) {
+                *expression = replacement;
+                self.generated_numbers = count;
+            }
+        }
+    }
+}
+
+pub const OBFUSCATE_NUMBERS_RULE_NAME: &str = "obfuscate_numbers";
+
+/// A rule that replaces number literals with arithmetic expressions that compute the same value.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ObfuscateNumbers {
+    seed: u64,
+}
+
+impl ObfuscateNumbers {
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl FlawlessRule for ObfuscateNumbers {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut processor = NumberObfuscator::new(self.seed);
+        DefaultVisitor::visit_block(block, &mut processor);
+    }
+}
+
+impl RuleConfiguration for ObfuscateNumbers {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "seed" => match value {
+                    RulePropertyValue::Usize(seed) => {
+                        self.seed = seed as u64;
+                    }
+                    _ => return Err(RuleConfigurationError::UsizeExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        OBFUSCATE_NUMBERS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if self.seed != 0 {
+            properties.insert("seed".to_owned(), (self.seed as usize).into());
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generator::{DenseLuaGenerator, LuaGenerator};
+    use crate::nodes::LastStatement;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> ObfuscateNumbers {
+        ObfuscateNumbers::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_obfuscate_numbers", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_seed() {
+        let rule: Box<dyn Rule> = Box::new(new_rule().with_seed(42));
+
+        assert_json_snapshot!("obfuscate_numbers_with_seed", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'obfuscate_numbers',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+
+    fn evaluate_generated_code(expression: &Expression) -> LuaValue {
+        let mut generator = DenseLuaGenerator::default();
+        generator.write_expression(expression);
+
+        let block = crate::Parser::default()
+            .parse(&format!("return {}", generator.into_string()))
+            .expect("unable to parse generated code");
+
+        match block.get_last_statement() {
+            Some(LastStatement::Return(statement)) => {
+                Evaluator::default().evaluate(statement.iter_expressions().next().unwrap())
+            }
+            _ => panic!("expected return statement"),
+        }
+    }
+
+    #[test]
+    fn generated_code_computes_the_same_value() {
+        let mut obfuscator = NumberObfuscator::new(0);
+
+        for value in [0.0, 1.0, 7.0, 100.0, 1234567.0, 0.1, 3.75, 1e-7, 2e15] {
+            for _ in 0..20 {
+                if let Some((expression, _)) = obfuscator.obfuscate(value) {
+                    pretty_assertions::assert_eq!(
+                        evaluate_generated_code(&expression),
+                        LuaValue::Number(value)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integers_are_obfuscated() {
+        let mut obfuscator = NumberObfuscator::new(0);
+
+        for value in [0.0, 1.0, 7.0, 100.0, 1234567.0, MAX_SAFE_INTEGER] {
+            assert!(obfuscator.obfuscate(value).is_some());
+        }
+    }
+
+    #[test]
+    fn large_numbers_are_not_obfuscated() {
+        let mut obfuscator = NumberObfuscator::new(0);
+
+        assert!(obfuscator.obfuscate(1e300).is_none());
+        assert!(obfuscator.obfuscate(f64::INFINITY).is_none());
+        assert!(obfuscator.obfuscate(f64::NAN).is_none());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::nodes::{
+    Arguments, Block, Expression, FunctionCall, IfStatement, LastStatement, ParentheseExpression,
+    Prefix, ReturnStatement, Statement, TupleArguments,
+};
+use crate::process::{BindingTracker, Evaluator, NodeProcessor, NodeVisitor, ScopeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+use super::variable_usage::get_prefix_root;
+
+// `self` is always a local variable, but it refers to the object of the method
+const SELF_IDENTIFIER: &str = "self";
+
+/// A call path like `print`, `self.logger:trace` or `debug.*`. A path ending with `.*` matches
+/// every function or method called on the given path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CallPattern {
+    path: String,
+    wildcard: bool,
+}
+
+impl CallPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let (path, wildcard) = match pattern.strip_suffix(".*") {
+            Some(path) => (path, true),
+            None => (pattern, false),
+        };
+
+        let is_valid = !path.is_empty()
+            && path
+                .split(['.', ':'])
+                .all(|name| !name.is_empty() && !name.contains('*'))
+            && path.matches(':').count() <= usize::from(!wildcard)
+            && path
+                .find(':')
+                .map(|index| !path[index..].contains('.'))
+                .unwrap_or(true);
+
+        if is_valid {
+            Ok(Self {
+                path: path.to_owned(),
+                wildcard,
+            })
+        } else {
+            Err(format!("invalid call path `{}`", pattern))
+        }
+    }
+
+    fn matches(&self, call_path: &str) -> bool {
+        if self.wildcard {
+            call_path
+                .strip_prefix(&self.path)
+                .filter(|rest| rest.starts_with('.') || rest.starts_with(':'))
+                .is_some()
+        } else {
+            call_path == self.path
+        }
+    }
+
+    fn as_str(&self) -> String {
+        if self.wildcard {
+            format!("{}.*", self.path)
+        } else {
+            self.path.clone()
+        }
+    }
+}
+
+fn get_prefix_path(prefix: &Prefix) -> Option<String> {
+    match prefix {
+        Prefix::Identifier(identifier) => Some(identifier.get_name().to_owned()),
+        Prefix::Field(field) => get_prefix_path(field.get_prefix())
+            .map(|path| format!("{}.{}", path, field.get_field().get_name())),
+        _ => None,
+    }
+}
+
+fn get_call_path(call: &FunctionCall) -> Option<String> {
+    let path = get_prefix_path(call.get_prefix())?;
+
+    Some(match call.get_method() {
+        Some(method) => format!("{}:{}", path, method.get_name()),
+        None => path,
+    })
+}
+
+struct CallRemover<'a> {
+    patterns: &'a [CallPattern],
+    evaluator: Evaluator,
+    bindings: BindingTracker,
+}
+
+impl<'a> CallRemover<'a> {
+    /// Returns true if the call matches one of the patterns and its path starts from a global
+    /// variable. The `block_locals` are the local variables declared in the current block
+    /// before the call, which are not tracked by the bindings yet.
+    fn should_remove(&self, call: &FunctionCall, block_locals: &[String]) -> bool {
+        let matches_pattern = get_call_path(call)
+            .map(|path| self.patterns.iter().any(|pattern| pattern.matches(&path)))
+            .unwrap_or(false);
+
+        matches_pattern
+            && get_prefix_root(call.get_prefix())
+                .map(|root| {
+                    root == SELF_IDENTIFIER
+                        || (!block_locals.iter().any(|name| name == root)
+                            && self.bindings.resolve(root).is_none())
+                })
+                .unwrap_or(false)
+    }
+
+    fn can_remove_expression(&self, call: &FunctionCall) -> bool {
+        self.should_remove(call, &[])
+            && !call
+                .get_arguments()
+                .clone()
+                .to_expressions()
+                .iter()
+                .any(|argument| self.evaluator.has_side_effects(argument))
+    }
+
+    /// Pushes the statements needed to keep the side effects of the arguments of a removed
+    /// call statement.
+    fn remove_call_statement(
+        &self,
+        call: FunctionCall,
+        block_locals: &[String],
+        statements: &mut Vec<Statement>,
+    ) {
+        for argument in call.get_arguments().clone().to_expressions() {
+            if !self.evaluator.has_side_effects(&argument) {
+                continue;
+            }
+
+            match argument {
+                Expression::Call(call) if self.should_remove(&call, block_locals) => {
+                    self.remove_call_statement(*call, block_locals, statements);
+                }
+                Expression::Call(call) => statements.push((*call).into()),
+                Expression::Parenthese(parenthese)
+                    if matches!(parenthese.inner_expression(), Expression::Call(_)) =>
+                {
+                    if let Expression::Call(call) = parenthese.into_inner_expression() {
+                        statements.push((*call).into());
+                    }
+                }
+                // the condition of an empty if statement evaluates the argument without
+                // declaring a new local variable
+                argument => statements.push(IfStatement::create(argument, Block::default()).into()),
+            }
+        }
+    }
+
+    /// Removes the last expression of a list of values if it is a call that can be removed.
+    /// Since the last value of a list can expand to multiple values, the new last value is
+    /// wrapped in parentheses when it can return multiple values.
+    fn remove_last_call(&self, expressions: &mut Vec<Expression>) -> bool {
+        if !matches!(
+            expressions.last(),
+            Some(Expression::Call(call)) if self.can_remove_expression(call)
+        ) {
+            return false;
+        }
+
+        expressions.pop();
+
+        if let Some(last) = expressions.last_mut() {
+            if matches!(last, Expression::Call(_) | Expression::VariableArguments(_)) {
+                let value = mem::replace(last, Expression::nil());
+                *last = ParentheseExpression::new(value).into();
+            }
+        }
+
+        true
+    }
+}
+
+fn push_declared_locals(statement: &Statement, block_locals: &mut Vec<String>) {
+    match statement {
+        Statement::LocalAssign(assign) => block_locals.extend(
+            assign
+                .iter_variables()
+                .map(|variable| variable.get_name().to_owned()),
+        ),
+        Statement::LocalFunction(function) => block_locals.push(function.get_name().to_owned()),
+        _ => {}
+    }
+}
+
+impl<'a> NodeProcessor for CallRemover<'a> {
+    // the statements of the block are not visited yet, so the local variables declared in the
+    // block are tracked here
+    fn process_block(&mut self, block: &mut Block) {
+        let mut block_locals = Vec::new();
+        let mut has_removed_calls = false;
+
+        for statement in block.iter_statements() {
+            if matches!(statement, Statement::Call(call) if self.should_remove(call, &block_locals))
+            {
+                has_removed_calls = true;
+                break;
+            }
+            push_declared_locals(statement, &mut block_locals);
+        }
+
+        if !has_removed_calls {
+            return;
+        }
+
+        let mut statements = Vec::new();
+        block_locals.clear();
+
+        for statement in block.take_statements() {
+            match statement {
+                Statement::Call(call) if self.should_remove(&call, &block_locals) => {
+                    self.remove_call_statement(call, &block_locals, &mut statements);
+                }
+                statement => {
+                    push_declared_locals(&statement, &mut block_locals);
+                    statements.push(statement);
+                }
+            }
+        }
+
+        block.set_statements(statements);
+    }
+
+    fn process_last_statement(&mut self, statement: &mut LastStatement) {
+        // a call at the end of a return statement can return no values, so it is removed
+        // instead of being replaced with `nil`
+        if let LastStatement::Return(return_statement) = statement {
+            let mut expressions: Vec<_> =
+                return_statement.clone().into_iter_expressions().collect();
+
+            if self.remove_last_call(&mut expressions) {
+                *return_statement = ReturnStatement::new(expressions);
+            }
+        }
+    }
+
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        // like return statements, a removed call in the last argument is removed instead of
+        // being replaced with `nil`, so that the number of arguments does not change
+        if let Arguments::Tuple(tuple) = call.get_arguments() {
+            let mut expressions = tuple.clone().to_expressions();
+
+            if self.remove_last_call(&mut expressions) {
+                call.set_arguments(TupleArguments::new(expressions).into());
+            }
+        }
+    }
+
+    fn process_expression(&mut self, expression: &mut Expression) {
+        if let Expression::Call(call) = expression {
+            if self.can_remove_expression(call) {
+                *expression = Expression::nil();
+            }
+        }
+    }
+}
+
+impl<'a> Deref for CallRemover<'a> {
+    type Target = BindingTracker;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bindings
+    }
+}
+
+impl<'a> DerefMut for CallRemover<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bindings
+    }
+}
+
+pub const REMOVE_CALLS_RULE_NAME: &str = "remove_calls";
+
+/// A rule that removes calls to the configured functions, like `print` or `Logger:debug`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RemoveCalls {
+    functions: Vec<CallPattern>,
+}
+
+impl RemoveCalls {
+    pub fn with_function(mut self, path: impl AsRef<str>) -> Result<Self, String> {
+        self.functions.push(CallPattern::parse(path.as_ref())?);
+        Ok(self)
+    }
+}
+
+impl FlawlessRule for RemoveCalls {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        if self.functions.is_empty() {
+            return;
+        }
+
+        let mut processor = CallRemover {
+            patterns: &self.functions,
+            evaluator: Evaluator::default(),
+            bindings: BindingTracker::default(),
+        };
+        ScopeVisitor::visit_block(block, &mut processor);
+    }
+}
+
+impl RuleConfiguration for RemoveCalls {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "functions" => match value {
+                    RulePropertyValue::StringList(list) => {
+                        self.functions = list
+                            .iter()
+                            .map(|path| CallPattern::parse(path))
+                            .collect::<Result<_, _>>()
+                            .map_err(|message| RuleConfigurationError::UnexpectedValue {
+                                property: key.clone(),
+                                message,
+                            })?;
+                    }
+                    _ => return Err(RuleConfigurationError::StringListExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        REMOVE_CALLS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if !self.functions.is_empty() {
+            properties.insert(
+                "functions".to_owned(),
+                RulePropertyValue::StringList(
+                    self.functions.iter().map(CallPattern::as_str).collect(),
+                ),
+            );
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> RemoveCalls {
+        RemoveCalls::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_remove_calls", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_functions() {
+        let rule: Box<dyn Rule> = Box::new(
+            new_rule()
+                .with_function("print")
+                .and_then(|rule| rule.with_function("self.logger:trace"))
+                .and_then(|rule| rule.with_function("debug.*"))
+                .unwrap(),
+        );
+
+        assert_json_snapshot!("remove_calls_with_functions", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'remove_calls',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+
+    #[test]
+    fn configure_with_invalid_path_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'remove_calls',
+            functions: ['a:b.c'],
+        }"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn wildcard_matches_fields_and_methods() {
+        let pattern = CallPattern::parse("debug.*").unwrap();
+
+        assert!(pattern.matches("debug.traceback"));
+        assert!(pattern.matches("debug:trace"));
+        assert!(pattern.matches("debug.profile.begin"));
+        assert!(!pattern.matches("debug"));
+        assert!(!pattern.matches("debugger.trace"));
+    }
+
+    #[test]
+    fn method_pattern_matches_method_call_only() {
+        let pattern = CallPattern::parse("Logger:debug").unwrap();
+
+        assert!(pattern.matches("Logger:debug"));
+        assert!(!pattern.matches("Logger.debug"));
+    }
+}
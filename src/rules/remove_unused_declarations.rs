@@ -0,0 +1,191 @@
+use crate::nodes::{
+    Block, Expression, Identifier, LastStatement, LocalFunctionStatement, Statement,
+};
+use crate::process::{BindingTracker, Evaluator, NodeProcessor, NodeVisitor, Scope, ScopeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+};
+
+use std::collections::{HashMap, HashSet};
+
+use super::verify_no_rule_properties;
+
+/// Builds the graph of references between the top-level statements of a block and the local
+/// variables declared at the top level.
+#[derive(Default)]
+struct ReferenceCollector {
+    tracker: BindingTracker,
+    depth: usize,
+    current_statement: usize,
+    // the statement index where each top-level local variable is declared
+    declarations: HashMap<usize, usize>,
+    // the top-level local variables referenced by each statement
+    references: HashMap<usize, HashSet<usize>>,
+}
+
+impl ReferenceCollector {
+    fn declare(&mut self, name: &str) {
+        let id = self.tracker.declare(name);
+
+        if self.depth == 1 {
+            self.declarations.insert(id, self.current_statement);
+        }
+    }
+}
+
+impl Scope for ReferenceCollector {
+    fn push(&mut self) {
+        self.depth += 1;
+        self.tracker.push();
+    }
+
+    fn pop(&mut self) {
+        self.depth -= 1;
+        self.tracker.pop();
+    }
+
+    fn insert(&mut self, identifier: &mut String) {
+        self.declare(identifier);
+    }
+
+    fn insert_local(&mut self, identifier: &mut String, _value: Option<&mut Expression>) {
+        self.declare(identifier);
+    }
+
+    fn insert_local_function(&mut self, function: &mut LocalFunctionStatement) {
+        self.declare(function.get_name());
+    }
+}
+
+impl NodeProcessor for ReferenceCollector {
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        if let Some(id) = self.tracker.resolve(identifier.get_name()) {
+            if self.declarations.contains_key(&id) {
+                self.references
+                    .entry(self.current_statement)
+                    .or_default()
+                    .insert(id);
+            }
+        }
+    }
+}
+
+pub const REMOVE_UNUSED_DECLARATIONS_RULE_NAME: &str = "remove_unused_declarations";
+
+/// A rule that removes top-level local variables and local functions that are never used.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RemoveUnusedDeclarations {}
+
+impl RemoveUnusedDeclarations {
+    /// Returns true if the statement must be kept even if the variables it declares are not
+    /// used.
+    fn is_root(&self, statement: &Statement, evaluator: &Evaluator) -> bool {
+        match statement {
+            Statement::LocalAssign(assign) => assign
+                .iter_values()
+                .any(|value| evaluator.has_side_effects(value)),
+            Statement::LocalFunction(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl FlawlessRule for RemoveUnusedDeclarations {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut collector = ReferenceCollector::default();
+        collector.push();
+
+        for (index, statement) in block.iter_mut_statements().enumerate() {
+            collector.current_statement = index;
+            ScopeVisitor::visit_statement(statement, &mut collector);
+        }
+
+        let statement_count = block.iter_statements().count();
+
+        if let Some(LastStatement::Return(statement)) = block.mutate_last_statement() {
+            collector.current_statement = statement_count;
+            statement
+                .iter_mut_expressions()
+                .for_each(|expression| ScopeVisitor::visit_expression(expression, &mut collector));
+        }
+
+        let evaluator = Evaluator::default();
+        let mut reachable_statements: HashSet<usize> = block
+            .iter_statements()
+            .enumerate()
+            .filter(|(_, statement)| self.is_root(statement, &evaluator))
+            .map(|(index, _)| index)
+            .chain(std::iter::once(statement_count))
+            .collect();
+
+        let mut pending: Vec<usize> = reachable_statements.iter().copied().collect();
+
+        while let Some(index) = pending.pop() {
+            for id in collector.references.get(&index).into_iter().flatten() {
+                if let Some(declaration) = collector.declarations.get(id) {
+                    if reachable_statements.insert(*declaration) {
+                        pending.push(*declaration);
+                    }
+                }
+            }
+        }
+
+        let mut index = 0;
+        block.filter_statements(|_| {
+            let keep = reachable_statements.contains(&index);
+            index += 1;
+            keep
+        });
+    }
+}
+
+impl RuleConfiguration for RemoveUnusedDeclarations {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        verify_no_rule_properties(&properties)?;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        REMOVE_UNUSED_DECLARATIONS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        RuleProperties::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> RemoveUnusedDeclarations {
+        RemoveUnusedDeclarations::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_remove_unused_declarations", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'remove_unused_declarations',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+}
@@ -0,0 +1,478 @@
+use crate::nodes::{
+    Block, Expression, FieldExpression, FunctionCall, FunctionStatement, Identifier,
+    IndexExpression, StringExpression, TableEntry, TableExpression,
+};
+use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor};
+use crate::rules::{
+    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    RulePropertyValue,
+};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+const DEFAULT_PREFIX: &str = "_";
+const HASHED_NAME_LENGTH: usize = 6;
+const NAME_CHARACTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+const METAMETHODS: &[&str] = &[
+    "__add",
+    "__band",
+    "__bnot",
+    "__bor",
+    "__bxor",
+    "__call",
+    "__close",
+    "__concat",
+    "__div",
+    "__eq",
+    "__gc",
+    "__idiv",
+    "__index",
+    "__ipairs",
+    "__iter",
+    "__le",
+    "__len",
+    "__lt",
+    "__metatable",
+    "__mod",
+    "__mode",
+    "__mul",
+    "__name",
+    "__namecall",
+    "__newindex",
+    "__pairs",
+    "__pow",
+    "__shl",
+    "__shr",
+    "__sub",
+    "__tostring",
+    "__type",
+    "__unm",
+];
+
+/// Returns the name generated for the field at the given index, starting with the given prefix:
+/// `_a`, `_b`, ..., `_9`, `_aa`, `_ab`, ...
+fn generate_name(prefix: &str, mut index: usize) -> String {
+    let mut characters = Vec::new();
+
+    loop {
+        characters.push(NAME_CHARACTERS[index % NAME_CHARACTERS.len()]);
+        if index < NAME_CHARACTERS.len() {
+            break;
+        }
+        index = index / NAME_CHARACTERS.len() - 1;
+    }
+
+    characters.reverse();
+
+    let name = String::from_utf8(characters).expect("generated names should be valid utf-8");
+    format!("{}{}", prefix, name)
+}
+
+/// Returns a name derived from the original name of the field, so that it does not depend on
+/// the order in which the fields and the files are processed. Each attempt gives a different
+/// name, to replace a name that is already used.
+fn generate_hashed_name(prefix: &str, original: &str, attempt: u64) -> String {
+    // 64-bit FNV-1a, which gives the same result on every run and platform
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in original.bytes().chain(attempt.to_le_bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    let mut name = prefix.to_owned();
+    let base = NAME_CHARACTERS.len() as u64;
+
+    for _ in 0..HASHED_NAME_LENGTH {
+        name.push(char::from(NAME_CHARACTERS[(hash % base) as usize]));
+        hash /= base;
+    }
+
+    name
+}
+
+/// The new names given to fields. It is kept by the rule between processed files, so that a
+/// field gets the same name in every file.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FieldNames {
+    names: HashMap<String, String>,
+    generated: HashSet<String>,
+    next_index: usize,
+}
+
+/// Collects the field names that are not renamed, so that generated names do not conflict with
+/// them.
+struct FieldCollector<'a> {
+    rule: &'a RenameFields,
+    names: HashSet<String>,
+}
+
+impl<'a> FieldCollector<'a> {
+    fn insert(&mut self, name: &str) {
+        if !self.rule.should_rename(name) {
+            self.names.insert(name.to_owned());
+        }
+    }
+}
+
+impl<'a> NodeProcessor for FieldCollector<'a> {
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        if let Some(method) = call.get_method() {
+            self.insert(method.get_name());
+        }
+    }
+
+    fn process_function_statement(&mut self, statement: &mut FunctionStatement) {
+        let name = statement.get_name();
+        for field in name.get_field_names().iter().chain(name.get_method()) {
+            self.insert(field.get_name());
+        }
+    }
+
+    fn process_field_expression(&mut self, field: &mut FieldExpression) {
+        self.insert(field.get_field().get_name());
+    }
+
+    fn process_index_expression(&mut self, index: &mut IndexExpression) {
+        if let Expression::String(string) = index.get_index() {
+            self.insert(string.get_value());
+        }
+    }
+
+    fn process_table_expression(&mut self, table: &mut TableExpression) {
+        for entry in table.iter_entries() {
+            match entry {
+                TableEntry::Field(entry) => self.insert(entry.get_field().get_name()),
+                TableEntry::Index(entry) => {
+                    if let Expression::String(string) = entry.get_key() {
+                        self.insert(string.get_value());
+                    }
+                }
+                TableEntry::Value(_) => {}
+            }
+        }
+    }
+}
+
+struct FieldRenamer<'a> {
+    rule: &'a RenameFields,
+    names: &'a mut FieldNames,
+    avoid_names: HashSet<String>,
+}
+
+impl<'a> FieldRenamer<'a> {
+    fn get_new_name(&mut self, name: &str) -> Option<String> {
+        if !self.rule.should_rename(name) {
+            return None;
+        }
+
+        if let Some(new_name) = self.names.names.get(name) {
+            return Some(new_name.clone());
+        }
+
+        let prefix = self.rule.get_generated_prefix();
+        let mut attempt = 0;
+
+        let new_name = loop {
+            let new_name = if self.rule.prefix.is_empty() {
+                attempt += 1;
+                generate_hashed_name(prefix, name, attempt - 1)
+            } else {
+                self.names.next_index += 1;
+                generate_name(prefix, self.names.next_index - 1)
+            };
+
+            if !self.avoid_names.contains(&new_name)
+                && !self.rule.is_reserved(&new_name)
+                && !self.names.generated.contains(&new_name)
+            {
+                break new_name;
+            }
+        };
+
+        self.names.names.insert(name.to_owned(), new_name.clone());
+        self.names.generated.insert(new_name.clone());
+        Some(new_name)
+    }
+
+    fn rename_identifier(&mut self, identifier: &mut Identifier) {
+        if let Some(new_name) = self.get_new_name(identifier.get_name()) {
+            identifier.set_name(new_name);
+        }
+    }
+
+    fn rename_string(&mut self, expression: &mut Expression) {
+        if let Expression::String(string) = expression {
+            if let Some(new_name) = self.get_new_name(string.get_value()) {
+                *expression = StringExpression::from_value(new_name).into();
+            }
+        }
+    }
+}
+
+impl<'a> NodeProcessor for FieldRenamer<'a> {
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        if let Some(method) = call.mutate_method() {
+            self.rename_identifier(method);
+        }
+    }
+
+    fn process_function_statement(&mut self, statement: &mut FunctionStatement) {
+        let name = statement.mutate_function_name();
+
+        for field in name.mutate_field_names() {
+            self.rename_identifier(field);
+        }
+
+        if let Some(method) = name.mutate_method() {
+            self.rename_identifier(method);
+        }
+    }
+
+    fn process_field_expression(&mut self, field: &mut FieldExpression) {
+        self.rename_identifier(field.mutate_field());
+    }
+
+    fn process_index_expression(&mut self, index: &mut IndexExpression) {
+        self.rename_string(index.mutate_index());
+    }
+
+    fn process_table_expression(&mut self, table: &mut TableExpression) {
+        for entry in table.iter_mut_entries() {
+            match entry {
+                TableEntry::Field(entry) => self.rename_identifier(entry.mutate_field()),
+                TableEntry::Index(entry) => self.rename_string(entry.mutate_key()),
+                TableEntry::Value(_) => {}
+            }
+        }
+    }
+}
+
+pub const RENAME_FIELDS_RULE_NAME: &str = "rename_fields";
+
+/// A rule that renames table fields and methods to short names. The fields are selected with a
+/// prefix or a list of names, and each field gets the same name in every processed file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RenameFields {
+    prefix: String,
+    fields: Vec<String>,
+    reserved: Vec<String>,
+    names: RefCell<FieldNames>,
+}
+
+impl Default for RenameFields {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_PREFIX.to_owned(),
+            fields: Vec::new(),
+            reserved: Vec::new(),
+            names: RefCell::default(),
+        }
+    }
+}
+
+impl RenameFields {
+    /// Rename the fields starting with the given prefix. An empty prefix does not match any
+    /// field.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn with_field(mut self, name: impl Into<String>) -> Self {
+        self.fields.push(name.into());
+        self
+    }
+
+    /// Never rename the given field, even if it starts with the prefix.
+    pub fn with_reserved(mut self, name: impl Into<String>) -> Self {
+        self.reserved.push(name.into());
+        self
+    }
+
+    /// Generated names start with the prefix, so that they can only conflict with fields that
+    /// are also renamed, in any processed file. With an empty prefix, names start with `_` and
+    /// are derived from the original names instead, since they could conflict with fields of
+    /// files that are processed later.
+    fn get_generated_prefix(&self) -> &str {
+        if self.prefix.is_empty() {
+            DEFAULT_PREFIX
+        } else {
+            &self.prefix
+        }
+    }
+
+    fn is_reserved(&self, name: &str) -> bool {
+        METAMETHODS.contains(&name) || self.reserved.iter().any(|reserved| reserved == name)
+    }
+
+    fn should_rename(&self, name: &str) -> bool {
+        !self.is_reserved(name)
+            && ((!self.prefix.is_empty() && name.starts_with(&self.prefix))
+                || self.fields.iter().any(|field| field == name))
+    }
+}
+
+impl FlawlessRule for RenameFields {
+    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+        let mut collector = FieldCollector {
+            rule: self,
+            names: HashSet::new(),
+        };
+        DefaultVisitor::visit_block(block, &mut collector);
+
+        let mut names = self.names.borrow_mut();
+        let mut renamer = FieldRenamer {
+            rule: self,
+            names: &mut names,
+            avoid_names: collector.names,
+        };
+        DefaultVisitor::visit_block(block, &mut renamer);
+    }
+}
+
+impl RuleConfiguration for RenameFields {
+    fn configure(&mut self, properties: RuleProperties) -> Result<(), RuleConfigurationError> {
+        for (key, value) in properties {
+            match key.as_str() {
+                "prefix" => match value {
+                    RulePropertyValue::String(prefix) => {
+                        self.prefix = prefix;
+                    }
+                    _ => return Err(RuleConfigurationError::StringExpected(key)),
+                },
+                "fields" => match value {
+                    RulePropertyValue::StringList(fields) => {
+                        self.fields = fields;
+                    }
+                    _ => return Err(RuleConfigurationError::StringListExpected(key)),
+                },
+                "reserved" => match value {
+                    RulePropertyValue::StringList(reserved) => {
+                        self.reserved = reserved;
+                    }
+                    _ => return Err(RuleConfigurationError::StringListExpected(key)),
+                },
+                _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        RENAME_FIELDS_RULE_NAME
+    }
+
+    fn serialize_to_properties(&self) -> RuleProperties {
+        let mut properties = RuleProperties::new();
+
+        if self.prefix != DEFAULT_PREFIX {
+            properties.insert("prefix".to_owned(), self.prefix.as_str().into());
+        }
+
+        if !self.fields.is_empty() {
+            properties.insert(
+                "fields".to_owned(),
+                RulePropertyValue::StringList(self.fields.clone()),
+            );
+        }
+
+        if !self.reserved.is_empty() {
+            properties.insert(
+                "reserved".to_owned(),
+                RulePropertyValue::StringList(self.reserved.clone()),
+            );
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rule;
+
+    use insta::assert_json_snapshot;
+
+    fn new_rule() -> RenameFields {
+        RenameFields::default()
+    }
+
+    #[test]
+    fn serialize_default_rule() {
+        let rule: Box<dyn Rule> = Box::new(new_rule());
+
+        assert_json_snapshot!("default_rename_fields", rule);
+    }
+
+    #[test]
+    fn serialize_rule_with_properties() {
+        let rule: Box<dyn Rule> = Box::new(
+            new_rule()
+                .with_prefix("m_")
+                .with_field("internalState")
+                .with_reserved("m_public"),
+        );
+
+        assert_json_snapshot!("rename_fields_with_properties", rule);
+    }
+
+    #[test]
+    fn configure_with_extra_field_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'rename_fields',
+            prop: "something",
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(err_message, "unexpected field 'prop'");
+    }
+
+    #[test]
+    fn generate_first_names() {
+        pretty_assertions::assert_eq!(generate_name("_", 0), "_a");
+        pretty_assertions::assert_eq!(generate_name("_", 1), "_b");
+        pretty_assertions::assert_eq!(generate_name("_", 61), "_9");
+        pretty_assertions::assert_eq!(generate_name("_", 62), "_aa");
+        pretty_assertions::assert_eq!(generate_name("_", 63), "_ab");
+        pretty_assertions::assert_eq!(generate_name("_", 62 + 62 * 62), "_aaa");
+    }
+
+    #[test]
+    fn generate_hashed_names() {
+        let name = generate_hashed_name("_", "internalState", 0);
+
+        pretty_assertions::assert_eq!(name.len(), 1 + HASHED_NAME_LENGTH);
+        pretty_assertions::assert_eq!(name, generate_hashed_name("_", "internalState", 0));
+        assert_ne!(name, generate_hashed_name("_", "internalState", 1));
+        assert_ne!(name, generate_hashed_name("_", "update", 0));
+    }
+
+    #[test]
+    fn generate_names_with_prefix() {
+        pretty_assertions::assert_eq!(generate_name("m_", 0), "m_a");
+        pretty_assertions::assert_eq!(generate_name("m_", 62), "m_aa");
+    }
+
+    #[test]
+    fn metamethods_are_not_renamed() {
+        assert!(!new_rule().should_rename("__index"));
+        assert!(new_rule().should_rename("__custom"));
+    }
+
+    #[test]
+    fn empty_prefix_does_not_rename_fields() {
+        let rule = new_rule().with_prefix("").with_field("state");
+
+        assert!(rule.should_rename("state"));
+        assert!(!rule.should_rename("_state"));
+    }
+}
@@ -1,3 +1,8 @@
+use crate::process::utils::is_valid_identifier;
+use crate::rules::RuleConfigurationError;
+
+use std::collections::HashSet;
+
 pub const DEFAULT: [&str; 40] = [
     "arg",
     "assert",
@@ -101,3 +106,96 @@ pub const ROBLOX: [&str; 58] = [
     "workspace",
     "ypcall",
 ];
+
+/// Globals that hold library tables: their fields are not expected to change.
+pub const LIBRARIES: [&str; 37] = [
+    "bit32",
+    "coroutine",
+    "debug",
+    "math",
+    "os",
+    "string",
+    "table",
+    "task",
+    "utf8",
+    "Axes",
+    "BrickColor",
+    "CFrame",
+    "Color3",
+    "ColorSequence",
+    "ColorSequenceKeypoint",
+    "DateTime",
+    "Enum",
+    "Faces",
+    "Instance",
+    "NumberRange",
+    "NumberSequence",
+    "NumberSequenceKeypoint",
+    "OverlapParams",
+    "PhysicalProperties",
+    "Random",
+    "Ray",
+    "RaycastParams",
+    "Rect",
+    "Region3",
+    "Region3int16",
+    "TweenInfo",
+    "UDim",
+    "UDim2",
+    "Vector2",
+    "Vector2int16",
+    "Vector3",
+    "Vector3int16",
+];
+
+/// Adds the given identifiers to a list of globals. The special values `$default` and `$roblox`
+/// are expanded to their list of globals.
+pub(crate) fn extend_globals(
+    globals: &mut Vec<String>,
+    list: Vec<String>,
+) -> Result<(), RuleConfigurationError> {
+    for value in list {
+        match value.as_str() {
+            "$default" => globals.extend(DEFAULT.iter().map(ToString::to_string)),
+            "$roblox" => globals.extend(ROBLOX.iter().map(ToString::to_string)),
+            identifier if !is_valid_identifier(identifier) => {
+                return Err(RuleConfigurationError::StringExpected("".to_owned()))
+            }
+            _ => globals.push(value),
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a list of globals back to the format used in the configuration, where `$default`
+/// and `$roblox` replace their list of globals.
+pub(crate) fn normalize_globals(globals: &[String]) -> Vec<String> {
+    let mut globals_set: HashSet<String> = globals.iter().cloned().collect();
+
+    let mut result = Vec::new();
+
+    if DEFAULT
+        .iter()
+        .all(|identifier| globals_set.contains(*identifier))
+    {
+        DEFAULT.iter().for_each(|identifier| {
+            globals_set.remove(*identifier);
+        });
+        result.push("$default".to_owned());
+    }
+
+    if ROBLOX
+        .iter()
+        .all(|identifier| globals_set.contains(*identifier))
+    {
+        ROBLOX.iter().for_each(|identifier| {
+            globals_set.remove(*identifier);
+        });
+        result.push("$roblox".to_owned());
+    }
+
+    result.extend(globals_set);
+    result.sort();
+    result
+}
@@ -1,19 +1,23 @@
 mod function_names;
-mod globals;
+pub(crate) mod globals;
+mod name_allocation;
 mod rename_processor;
+mod renamed_variable;
 
+use name_allocation::{parse_alphabet, shuffle_alphabet, BindingCollector};
 use rename_processor::RenameProcessor;
+pub use renamed_variable::RenamedVariable;
+pub(crate) use renamed_variable::MAIN_SCOPE_NAME;
 
 use crate::nodes::Block;
-use crate::process::utils::is_valid_identifier;
 use crate::process::{DefaultVisitor, NodeVisitor, ScopeVisitor};
 use crate::rules::{
-    Context, FlawlessRule, RuleConfiguration, RuleConfigurationError, RuleProperties,
+    Context, Rule, RuleConfiguration, RuleConfigurationError, RuleProcessResult, RuleProperties,
     RulePropertyValue,
 };
 
-use std::collections::HashSet;
 use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
 
 pub const RENAME_VARIABLES_RULE_NAME: &str = "rename_variables";
 
@@ -22,6 +26,9 @@ pub const RENAME_VARIABLES_RULE_NAME: &str = "rename_variables";
 pub struct RenameVariables {
     globals: Vec<String>,
     include_functions: bool,
+    mapping: Option<PathBuf>,
+    seed: Option<u64>,
+    alphabet: Option<String>,
 }
 
 impl RenameVariables {
@@ -29,6 +36,9 @@ impl RenameVariables {
         Self {
             globals: Vec::from_iter(iter),
             include_functions: false,
+            mapping: None,
+            seed: None,
+            alphabet: None,
         }
     }
 
@@ -37,53 +47,52 @@ impl RenameVariables {
         self
     }
 
-    fn set_globals(&mut self, list: Vec<String>) -> Result<(), RuleConfigurationError> {
-        for value in list {
-            match value.as_str() {
-                "$default" => self
-                    .globals
-                    .extend(globals::DEFAULT.iter().map(ToString::to_string)),
-                "$roblox" => self
-                    .globals
-                    .extend(globals::ROBLOX.iter().map(ToString::to_string)),
-                identifier if !is_valid_identifier(identifier) => {
-                    return Err(RuleConfigurationError::StringExpected("".to_owned()))
-                }
-                _ => self.globals.push(value),
-            }
-        }
+    /// Write the original name of each renamed variable to the given file.
+    pub fn with_mapping(mut self, path: impl Into<PathBuf>) -> Self {
+        self.mapping = Some(path.into());
+        self
+    }
 
-        Ok(())
+    pub fn get_mapping(&self) -> Option<&Path> {
+        self.mapping.as_deref()
     }
 
-    fn normalize_globals(&self) -> Vec<String> {
-        let mut globals_set: HashSet<String> = self.globals.iter().cloned().collect();
-
-        let mut result = Vec::new();
-
-        if globals::DEFAULT
-            .iter()
-            .all(|identifier| globals_set.contains(*identifier))
-        {
-            globals::DEFAULT.iter().for_each(|identifier| {
-                globals_set.remove(*identifier);
-            });
-            result.push("$default".to_owned());
-        }
+    /// Shuffle the characters used to generate names with the given seed, so that different
+    /// seeds produce different names. When configured, the seed is a non-negative integer that
+    /// fits in a `usize`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Generate names from the given characters, or from a predefined alphabet (`$default` or
+    /// `$confusable`).
+    pub fn with_alphabet(mut self, alphabet: impl Into<String>) -> Self {
+        self.alphabet = Some(alphabet.into());
+        self
+    }
 
-        if globals::ROBLOX
-            .iter()
-            .all(|identifier| globals_set.contains(*identifier))
-        {
-            globals::ROBLOX.iter().for_each(|identifier| {
-                globals_set.remove(*identifier);
-            });
-            result.push("$roblox".to_owned());
+    fn get_alphabet(&self) -> Result<Vec<char>, String> {
+        let mut alphabet = parse_alphabet(
+            self.alphabet
+                .as_deref()
+                .unwrap_or(name_allocation::DEFAULT_ALPHABET),
+        )
+        .map_err(|message| format!("invalid alphabet: {}", message))?;
+
+        if let Some(seed) = self.seed {
+            shuffle_alphabet(&mut alphabet, seed);
         }
 
-        result.extend(globals_set.into_iter());
-        result.sort();
-        result
+        Ok(alphabet)
+    }
+
+    fn set_globals(&mut self, list: Vec<String>) -> Result<(), RuleConfigurationError> {
+        globals::extend_globals(&mut self.globals, list)
+    }
+
+    fn normalize_globals(&self) -> Vec<String> {
+        globals::normalize_globals(&self.globals)
     }
 }
 
@@ -93,8 +102,10 @@ impl Default for RenameVariables {
     }
 }
 
-impl FlawlessRule for RenameVariables {
-    fn flawless_process(&self, block: &mut Block, _: &mut Context) {
+impl Rule for RenameVariables {
+    fn process(&self, block: &mut Block, context: &mut Context) -> RuleProcessResult {
+        let alphabet = self.get_alphabet()?;
+
         let avoid_identifiers = if self.include_functions {
             Vec::new()
         } else {
@@ -103,11 +114,29 @@ impl FlawlessRule for RenameVariables {
             collect_functions.into()
         };
 
-        let mut processor = RenameProcessor::new(
-            self.globals.clone().into_iter().chain(avoid_identifiers),
-            self.include_functions,
+        let mut collector = BindingCollector::new(self.include_functions);
+        ScopeVisitor::visit_block(block, &mut collector);
+        let global_identifiers = collector.take_global_identifiers();
+        let names = collector.allocate_names(
+            alphabet,
+            self.globals
+                .clone()
+                .into_iter()
+                .chain(avoid_identifiers)
+                .chain(global_identifiers),
         );
-        ScopeVisitor::visit_block(block, &mut processor);
+
+        let mut processor = RenameProcessor::new(names, self.include_functions);
+
+        if let Some(mapping) = &self.mapping {
+            processor = processor.with_renamed_variables();
+            ScopeVisitor::visit_block(block, &mut processor);
+            context.add_renamed_variables(mapping, processor.take_renamed_variables());
+        } else {
+            ScopeVisitor::visit_block(block, &mut processor);
+        }
+
+        Ok(())
     }
 }
 
@@ -125,6 +154,30 @@ impl RuleConfiguration for RenameVariables {
                     }
                     _ => return Err(RuleConfigurationError::BooleanExpected(key)),
                 },
+                "mapping" => match value {
+                    RulePropertyValue::String(path) => {
+                        self.mapping = Some(PathBuf::from(path));
+                    }
+                    _ => return Err(RuleConfigurationError::StringExpected(key)),
+                },
+                "seed" => match value {
+                    RulePropertyValue::Usize(seed) => {
+                        self.seed = Some(seed as u64);
+                    }
+                    _ => return Err(RuleConfigurationError::UsizeExpected(key)),
+                },
+                "alphabet" => match value {
+                    RulePropertyValue::String(alphabet) => {
+                        parse_alphabet(&alphabet).map_err(|message| {
+                            RuleConfigurationError::UnexpectedValue {
+                                property: key.clone(),
+                                message,
+                            }
+                        })?;
+                        self.alphabet = Some(alphabet);
+                    }
+                    _ => return Err(RuleConfigurationError::StringExpected(key)),
+                },
                 _ => return Err(RuleConfigurationError::UnexpectedProperty(key)),
             }
         }
@@ -154,6 +207,21 @@ impl RuleConfiguration for RenameVariables {
             );
         }
 
+        if let Some(mapping) = &self.mapping {
+            properties.insert(
+                "mapping".to_owned(),
+                RulePropertyValue::String(mapping.to_string_lossy().into_owned()),
+            );
+        }
+
+        if let Some(seed) = self.seed {
+            properties.insert("seed".to_owned(), (seed as usize).into());
+        }
+
+        if let Some(alphabet) = &self.alphabet {
+            properties.insert("alphabet".to_owned(), alphabet.as_str().into());
+        }
+
         properties
     }
 }
@@ -205,6 +273,49 @@ mod test {
         );
     }
 
+    #[test]
+    fn serialize_with_mapping() {
+        let rule = Box::new(
+            RenameVariables::new(globals::DEFAULT.iter().map(ToString::to_string))
+                .with_mapping("names.json"),
+        );
+
+        assert_json_snapshot!("rename_variables_with_mapping", rule as Box<dyn Rule>);
+    }
+
+    #[test]
+    fn serialize_with_seed_and_alphabet() {
+        let rule = Box::new(
+            RenameVariables::new(globals::DEFAULT.iter().map(ToString::to_string))
+                .with_seed(7)
+                .with_alphabet("$confusable"),
+        );
+
+        assert_json_snapshot!(
+            "rename_variables_with_seed_and_alphabet",
+            rule as Box<dyn Rule>
+        );
+    }
+
+    #[test]
+    fn configure_with_invalid_alphabet_error() {
+        let result = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'rename_variables',
+            alphabet: 'a-b',
+        }"#,
+        );
+        let err_message = match result {
+            Ok(_) => panic!("expected error when deserializing rule"),
+            Err(e) => e,
+        }
+        .to_string();
+        pretty_assertions::assert_eq!(
+            err_message,
+            "unexpected value for field 'alphabet': invalid character `-` (expected letters, digits or `_`)"
+        );
+    }
+
     #[test]
     fn serialize_skip_functions() {
         let rule = Box::new(RenameVariables::new(
@@ -213,4 +324,43 @@ mod test {
 
         assert_json_snapshot!("roblox_globals_rename_variables", rule as Box<dyn Rule>);
     }
+
+    #[test]
+    fn mapping_contains_scopes_and_lines() {
+        let code = r#"
+local Counter = {}
+function Counter:add(amount)
+    for i = 1, amount do
+        local callback = function(value) end
+    end
+end
+for key, value in pairs(Counter) do end
+"#;
+        let mut block = crate::Parser::default()
+            .preserve_tokens()
+            .parse(code)
+            .unwrap();
+        let mut context = Context::default();
+
+        RenameVariables::default()
+            .with_mapping("names.json")
+            .process(&mut block, &mut context)
+            .unwrap();
+
+        pretty_assertions::assert_eq!(
+            context.take_renamed_variables(),
+            vec![(
+                PathBuf::from("names.json"),
+                vec![
+                    RenamedVariable::new("a", "Counter", "<main>").with_line(2),
+                    RenamedVariable::new("b", "amount", "Counter:add").with_line(3),
+                    RenamedVariable::new("c", "i", "Counter:add").with_line(4),
+                    RenamedVariable::new("d", "value", "<anonymous>").with_line(5),
+                    RenamedVariable::new("d", "callback", "Counter:add").with_line(5),
+                    RenamedVariable::new("b", "key", "<main>").with_line(8),
+                    RenamedVariable::new("c", "value", "<main>").with_line(8),
+                ]
+            )]
+        );
+    }
 }
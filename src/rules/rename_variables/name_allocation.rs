@@ -0,0 +1,329 @@
+use crate::nodes::{Expression, Identifier, LocalFunctionStatement};
+use crate::process::utils::{
+    is_valid_identifier, Permutator, SeededRandom, IDENTIFIER_CHARACTERS, KEYWORDS,
+};
+use crate::process::{NodeProcessor, Scope};
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+pub(crate) const DEFAULT_ALPHABET: &str = "$default";
+const CONFUSABLE_ALPHABET: &str = "$confusable";
+const CONFUSABLE_CHARACTERS: &str = "lI1_";
+
+/// Returns the characters used to generate names from the `alphabet` property of the rule, which
+/// is either a list of characters or the name of a predefined alphabet.
+pub(crate) fn parse_alphabet(alphabet: &str) -> Result<Vec<char>, String> {
+    let characters = match alphabet {
+        DEFAULT_ALPHABET => IDENTIFIER_CHARACTERS,
+        CONFUSABLE_ALPHABET => CONFUSABLE_CHARACTERS,
+        _ => alphabet,
+    };
+
+    let mut result: Vec<char> = Vec::new();
+
+    for character in characters.chars() {
+        if !(character.is_ascii_alphanumeric() || character == '_') {
+            return Err(format!(
+                "invalid character `{}` (expected letters, digits or `_`)",
+                character
+            ));
+        }
+        if result.contains(&character) {
+            return Err(format!("character `{}` is repeated", character));
+        }
+        result.push(character);
+    }
+
+    if !result.iter().any(|character| !character.is_ascii_digit()) {
+        return Err("expected at least one letter or `_`".to_owned());
+    }
+
+    Ok(result)
+}
+
+/// Shuffles the characters of the alphabet, so that each seed gives different names.
+pub(crate) fn shuffle_alphabet(alphabet: &mut [char], seed: u64) {
+    let mut random = SeededRandom::new(seed);
+
+    for i in (1..alphabet.len()).rev() {
+        alphabet.swap(i, random.index(i + 1));
+    }
+}
+
+#[derive(Debug, Default)]
+struct Binding {
+    occurrences: usize,
+    // the bindings that are visible at the same time as this one, so they can not share a name
+    conflicts: Vec<usize>,
+}
+
+/// The first pass of the `rename_variables` rule. It finds each variable declaration (in the
+/// same order as the `RenameProcessor`) to count how many times each variable appears and
+/// which variables are visible at the same time.
+#[derive(Debug)]
+pub struct BindingCollector {
+    bindings: Vec<Binding>,
+    dictionaries: Vec<HashMap<String, usize>>,
+    visible: Vec<usize>,
+    scope_starts: Vec<usize>,
+    // the identifiers that do not refer to a declared variable
+    global_identifiers: HashSet<String>,
+    include_functions: bool,
+}
+
+impl BindingCollector {
+    pub fn new(include_functions: bool) -> Self {
+        Self {
+            bindings: Vec::new(),
+            dictionaries: Vec::new(),
+            visible: Vec::new(),
+            scope_starts: Vec::new(),
+            global_identifiers: HashSet::new(),
+            include_functions,
+        }
+    }
+
+    fn declare(&mut self, name: &str) {
+        let id = self.bindings.len();
+
+        for other in self.visible.iter().copied() {
+            self.bindings[other].conflicts.push(id);
+        }
+
+        self.bindings.push(Binding {
+            occurrences: 1,
+            conflicts: self.visible.clone(),
+        });
+        self.visible.push(id);
+
+        if let Some(dictionary) = self.dictionaries.last_mut() {
+            dictionary.insert(name.to_owned(), id);
+        } else {
+            let mut dictionary = HashMap::new();
+            dictionary.insert(name.to_owned(), id);
+            self.dictionaries.push(dictionary);
+        }
+    }
+
+    /// Returns the global variables used in the block, which must not be shadowed by the new
+    /// names.
+    pub fn take_global_identifiers(&mut self) -> HashSet<String> {
+        std::mem::take(&mut self.global_identifiers)
+    }
+
+    /// Gives a name to each binding, in the order they were declared. The variables that appear
+    /// the most get the shortest names, and bindings that are never visible at the same time
+    /// can share the same name.
+    pub fn allocate_names<I: IntoIterator<Item = String>>(
+        self,
+        alphabet: Vec<char>,
+        avoid: I,
+    ) -> Vec<String> {
+        let mut candidates = NameCandidates::new(alphabet, avoid);
+
+        let mut order: Vec<_> = (0..self.bindings.len()).collect();
+        order.sort_by_key(|id| (Reverse(self.bindings[*id].occurrences), *id));
+
+        let mut assigned: Vec<Option<usize>> = vec![None; self.bindings.len()];
+        let mut used = HashSet::new();
+
+        for id in order {
+            used.clear();
+            used.extend(
+                self.bindings[id]
+                    .conflicts
+                    .iter()
+                    .filter_map(|other| assigned[*other]),
+            );
+
+            let index = (0..)
+                .find(|index| !used.contains(index))
+                .expect("an unused name index should exist");
+            assigned[id] = Some(index);
+        }
+
+        assigned
+            .into_iter()
+            .map(|index| candidates.get(index.expect("every binding should have a name")))
+            .collect()
+    }
+}
+
+impl Scope for BindingCollector {
+    fn push(&mut self) {
+        self.dictionaries.push(HashMap::new());
+        self.scope_starts.push(self.visible.len());
+    }
+
+    fn pop(&mut self) {
+        self.dictionaries.pop();
+        if let Some(start) = self.scope_starts.pop() {
+            self.visible.truncate(start);
+        }
+    }
+
+    fn insert(&mut self, identifier: &mut String) {
+        self.declare(identifier);
+    }
+
+    fn insert_local(&mut self, identifier: &mut String, _value: Option<&mut Expression>) {
+        self.declare(identifier);
+    }
+
+    fn insert_local_function(&mut self, function: &mut LocalFunctionStatement) {
+        if self.include_functions {
+            self.declare(function.get_name());
+        }
+    }
+}
+
+impl NodeProcessor for BindingCollector {
+    fn process_variable_expression(&mut self, variable: &mut Identifier) {
+        let name = variable.get_name();
+        if let Some(id) = self
+            .dictionaries
+            .iter()
+            .rev()
+            .find_map(|dictionary| dictionary.get(name))
+        {
+            self.bindings[*id].occurrences += 1;
+        } else {
+            self.global_identifiers.insert(name.to_owned());
+        }
+    }
+}
+
+/// The valid names for variables, from the shortest to the longest.
+struct NameCandidates {
+    names: Vec<String>,
+    permutator: Permutator<std::vec::IntoIter<char>>,
+    avoid: HashSet<String>,
+}
+
+impl NameCandidates {
+    fn new<I: IntoIterator<Item = String>>(alphabet: Vec<char>, avoid: I) -> Self {
+        let mut avoid: HashSet<_> = avoid.into_iter().collect();
+        avoid.extend(KEYWORDS.iter().map(|keyword| (*keyword).to_owned()));
+
+        Self {
+            names: Vec::new(),
+            permutator: Permutator::new(alphabet.into_iter()),
+            avoid,
+        }
+    }
+
+    fn get(&mut self, index: usize) -> String {
+        while self.names.len() <= index {
+            let name = self
+                .permutator
+                .next()
+                .expect("the permutator should always produce a name");
+
+            if is_valid_identifier(&name) && !self.avoid.contains(&name) {
+                self.names.push(name);
+            }
+        }
+
+        self.names[index].clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::process::{NodeVisitor, ScopeVisitor};
+
+    fn allocate(code: &str) -> Vec<String> {
+        let mut block = crate::Parser::default().parse(code).unwrap();
+        let mut collector = BindingCollector::new(true);
+        ScopeVisitor::visit_block(&mut block, &mut collector);
+        collector.allocate_names(IDENTIFIER_CHARACTERS.chars().collect(), Vec::new())
+    }
+
+    #[test]
+    fn most_used_variable_gets_first_name() {
+        pretty_assertions::assert_eq!(
+            allocate("local rare local hot return hot + hot"),
+            vec!["b", "a"]
+        );
+    }
+
+    #[test]
+    fn sibling_scopes_share_names() {
+        pretty_assertions::assert_eq!(
+            allocate("do local a, b end do local c, d end"),
+            vec!["a", "b", "a", "b"]
+        );
+    }
+
+    #[test]
+    fn collect_global_identifiers() {
+        let mut block = crate::Parser::default()
+            .parse("local a = b function c(d) return a, d, e end")
+            .unwrap();
+        let mut collector = BindingCollector::new(true);
+        ScopeVisitor::visit_block(&mut block, &mut collector);
+
+        let mut globals: Vec<_> = collector.take_global_identifiers().into_iter().collect();
+        globals.sort();
+
+        pretty_assertions::assert_eq!(globals, vec!["b", "c", "e"]);
+    }
+
+    #[test]
+    fn avoided_names_are_skipped() {
+        let mut candidates = NameCandidates::new(
+            IDENTIFIER_CHARACTERS.chars().collect(),
+            vec!["a".to_owned()],
+        );
+
+        pretty_assertions::assert_eq!(candidates.get(0), "b");
+        pretty_assertions::assert_eq!(candidates.get(1), "c");
+    }
+
+    #[test]
+    fn confusable_names_skip_digits_and_keywords() {
+        let mut candidates =
+            NameCandidates::new(parse_alphabet("$confusable").unwrap(), Vec::new());
+        let names: Vec<_> = (0..6).map(|index| candidates.get(index)).collect();
+
+        pretty_assertions::assert_eq!(names, vec!["l", "I", "_", "ll", "lI", "l1"]);
+    }
+
+    #[test]
+    fn keywords_are_not_generated() {
+        let mut candidates = NameCandidates::new(parse_alphabet("dno").unwrap(), Vec::new());
+        let names: Vec<_> = (0..8).map(|index| candidates.get(index)).collect();
+
+        pretty_assertions::assert_eq!(names, vec!["d", "n", "o", "dd", "dn", "nd", "nn", "no"]);
+    }
+
+    #[test]
+    fn parse_alphabet_errors() {
+        pretty_assertions::assert_eq!(
+            parse_alphabet("a-b"),
+            Err("invalid character `-` (expected letters, digits or `_`)".to_owned())
+        );
+        pretty_assertions::assert_eq!(
+            parse_alphabet("aba"),
+            Err("character `a` is repeated".to_owned())
+        );
+        pretty_assertions::assert_eq!(
+            parse_alphabet("123"),
+            Err("expected at least one letter or `_`".to_owned())
+        );
+    }
+
+    #[test]
+    fn shuffle_alphabet_is_deterministic() {
+        let mut first = parse_alphabet("$default").unwrap();
+        let mut second = first.clone();
+
+        shuffle_alphabet(&mut first, 42);
+        shuffle_alphabet(&mut second, 42);
+
+        pretty_assertions::assert_eq!(first, second);
+        assert_ne!(first, parse_alphabet("$default").unwrap());
+    }
+}
@@ -1,34 +1,101 @@
-use crate::nodes::{Expression, Identifier, LocalFunctionStatement};
-use crate::process::utils::{identifier_permutator, CharPermutator};
-use crate::process::{utils::KEYWORDS, NodeProcessor, Scope};
+use crate::nodes::{
+    Expression, FunctionExpression, FunctionStatement, GenericForStatement, Identifier,
+    LocalAssignStatement, LocalFunctionStatement, NumericForStatement,
+};
+use crate::process::{NodeProcessor, Scope};
 
-use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
-use std::iter::FromIterator;
+use super::renamed_variable::{RenamedVariable, ANONYMOUS_SCOPE_NAME, MAIN_SCOPE_NAME};
+
+use std::collections::{HashMap, VecDeque};
+
+fn get_line(identifier: &Identifier) -> Option<usize> {
+    identifier
+        .get_token()
+        .and_then(|token| token.get_line_number())
+}
+
+/// Keeps track of the renamed variables with the function where they are declared and their
+/// line, to produce the name mapping of the rule.
+#[derive(Debug, Default)]
+struct RenamedVariables {
+    variables: Vec<RenamedVariable>,
+    // the lines of the identifiers that are about to be inserted in the scope. The processing
+    // methods are called before the identifiers are inserted, so each declaration pushes the
+    // lines of its identifiers that are then consumed in the same order
+    pending_lines: Vec<VecDeque<Option<usize>>>,
+    pending_function: Option<String>,
+    functions: Vec<Option<String>>,
+}
+
+impl RenamedVariables {
+    fn push_lines<'a>(&mut self, identifiers: impl Iterator<Item = &'a Identifier>) {
+        let lines: VecDeque<_> = identifiers.map(get_line).collect();
+        if !lines.is_empty() {
+            self.pending_lines.push(lines);
+        }
+    }
+
+    fn next_line(&mut self) -> Option<usize> {
+        let lines = self.pending_lines.last_mut()?;
+        let line = lines.pop_front().flatten();
+        if lines.is_empty() {
+            self.pending_lines.pop();
+        }
+        line
+    }
+
+    fn current_scope(&self) -> &str {
+        self.functions
+            .iter()
+            .rev()
+            .find_map(Option::as_deref)
+            .unwrap_or(MAIN_SCOPE_NAME)
+    }
+
+    fn insert(&mut self, name: &str, original: &str) {
+        let variable = RenamedVariable::new(name, original, self.current_scope());
+        let variable = match self.next_line() {
+            Some(line) => variable.with_line(line),
+            None => variable,
+        };
+        self.variables.push(variable);
+    }
+}
 
 #[derive(Debug)]
 pub struct RenameProcessor {
     real_to_obfuscated: Vec<HashMap<String, String>>,
-    permutator: CharPermutator,
-    avoid_identifier: HashSet<String>,
-    reuse_identifiers: Vec<String>,
+    names: std::vec::IntoIter<String>,
     include_functions: bool,
+    renamed_variables: Option<RenamedVariables>,
 }
 
 impl RenameProcessor {
-    pub fn new<I: IntoIterator<Item = String>>(iter: I, include_functions: bool) -> Self {
-        let mut avoid_identifier = HashSet::from_iter(iter);
-        avoid_identifier.extend(KEYWORDS.iter().map(|s| (*s).to_owned()));
-
+    /// Creates a processor that gives the names to the variables in the order they are declared.
+    /// The names are obtained from the `BindingCollector`.
+    pub fn new(names: Vec<String>, include_functions: bool) -> Self {
         Self {
             real_to_obfuscated: Vec::new(),
-            permutator: identifier_permutator(),
-            avoid_identifier,
-            reuse_identifiers: Vec::new(),
+            names: names.into_iter(),
             include_functions,
+            renamed_variables: None,
         }
     }
 
+    /// Keep track of each renamed variable, so that they can be obtained with
+    /// `take_renamed_variables` after the block is processed.
+    pub fn with_renamed_variables(mut self) -> Self {
+        self.renamed_variables = Some(RenamedVariables::default());
+        self
+    }
+
+    pub fn take_renamed_variables(&mut self) -> Vec<RenamedVariable> {
+        self.renamed_variables
+            .take()
+            .map(|renamed| renamed.variables)
+            .unwrap_or_default()
+    }
+
     pub fn add(&mut self, real: String, obfuscated: String) {
         if let Some(dictionary) = self.real_to_obfuscated.last_mut() {
             dictionary.insert(real, obfuscated);
@@ -47,88 +114,41 @@ impl RenameProcessor {
     }
 
     pub fn generate_identifier(&mut self) -> String {
-        if let Some(identifier) = self.reuse_identifiers.pop() {
-            identifier
-        } else {
-            let generated = self.permutator.next().unwrap();
-
-            if self.filter_identifier(&generated) {
-                generated
-            } else {
-                self.generate_identifier()
-            }
-        }
-    }
-
-    fn filter_identifier(&self, identifier: &str) -> bool {
-        !self.avoid_identifier.contains(identifier)
-            && !identifier.chars().next().unwrap().is_ascii_digit()
+        self.names
+            .next()
+            .expect("a name should be allocated for each variable")
     }
 
     fn replace_identifier(&mut self, identifier: &mut String) {
-        let original = identifier.drain(..).collect();
+        let original = std::mem::take(identifier);
         let obfuscated_name = self.generate_identifier();
 
         identifier.push_str(&obfuscated_name);
 
-        self.add(original, obfuscated_name);
-    }
-}
-
-fn sort_char(a: char, b: char) -> Ordering {
-    if a == b {
-        Ordering::Equal
-    } else if a.is_ascii_digit() && b.is_ascii_digit()
-        || a.is_lowercase() && b.is_lowercase()
-        || a.is_uppercase() && b.is_uppercase()
-    {
-        a.cmp(&b)
-    } else {
-        match (a, b) {
-            (a, _) if a.is_ascii_digit() => Ordering::Greater,
-            (_, b) if b.is_ascii_digit() => Ordering::Less,
-            ('_', _) => Ordering::Greater,
-            (_, '_') => Ordering::Less,
-            (a, _) if a.is_lowercase() => Ordering::Less,
-            (_, b) if b.is_lowercase() => Ordering::Greater,
-            _ => Ordering::Equal,
-        }
-    }
-}
-
-fn sort_identifiers(a: &str, b: &str) -> Ordering {
-    let mut b_chars = b.chars();
-
-    for a_char in a.chars() {
-        if let Some(b_char) = b_chars.next() {
-            match sort_char(a_char, b_char) {
-                Ordering::Less => return Ordering::Less,
-                Ordering::Greater => return Ordering::Greater,
-                Ordering::Equal => {}
-            }
-        } else {
-            return Ordering::Greater;
+        if let Some(renamed) = &mut self.renamed_variables {
+            renamed.insert(&obfuscated_name, &original);
         }
-    }
 
-    if b_chars.next().is_some() {
-        Ordering::Less
-    } else {
-        Ordering::Equal
+        self.add(original, obfuscated_name);
     }
 }
 
 impl Scope for RenameProcessor {
     fn push(&mut self) {
-        self.real_to_obfuscated.push(HashMap::new())
+        self.real_to_obfuscated.push(HashMap::new());
+
+        if let Some(renamed) = &mut self.renamed_variables {
+            let function = renamed.pending_function.take();
+            renamed.functions.push(function);
+        }
     }
 
     fn pop(&mut self) {
-        if let Some(dictionary) = self.real_to_obfuscated.pop() {
-            self.reuse_identifiers.extend(dictionary.into_values());
-            self.reuse_identifiers
-                .sort_by(|a, b| sort_identifiers(a, b).reverse());
+        if let Some(renamed) = &mut self.renamed_variables {
+            renamed.functions.pop();
         }
+
+        self.real_to_obfuscated.pop();
     }
 
     fn insert(&mut self, identifier: &mut String) {
@@ -152,6 +172,66 @@ impl NodeProcessor for RenameProcessor {
             variable.set_name(obfuscated_name);
         }
     }
+
+    fn process_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        if let Some(renamed) = &mut self.renamed_variables {
+            renamed.push_lines(statement.get_variables().iter());
+        }
+    }
+
+    fn process_function_expression(&mut self, function: &mut FunctionExpression) {
+        if let Some(renamed) = &mut self.renamed_variables {
+            renamed.pending_function = Some(ANONYMOUS_SCOPE_NAME.to_owned());
+            renamed.push_lines(function.get_parameters().iter());
+        }
+    }
+
+    fn process_function_statement(&mut self, statement: &mut FunctionStatement) {
+        if let Some(renamed) = &mut self.renamed_variables {
+            let name = statement.get_name();
+            let mut function_name = name.get_name().get_name().to_owned();
+            for field in name.get_field_names() {
+                function_name.push('.');
+                function_name.push_str(field.get_name());
+            }
+            if let Some(method) = name.get_method() {
+                function_name.push(':');
+                function_name.push_str(method.get_name());
+            }
+
+            renamed.pending_function = Some(function_name);
+            renamed.push_lines(statement.get_parameters().iter());
+        }
+    }
+
+    fn process_local_function_statement(&mut self, statement: &mut LocalFunctionStatement) {
+        if let Some(renamed) = &mut self.renamed_variables {
+            renamed.pending_function = Some(statement.get_name().to_owned());
+
+            let function_identifier = if self.include_functions {
+                Some(statement.get_identifier())
+            } else {
+                None
+            };
+            renamed.push_lines(
+                function_identifier
+                    .into_iter()
+                    .chain(statement.get_parameters().iter()),
+            );
+        }
+    }
+
+    fn process_generic_for_statement(&mut self, statement: &mut GenericForStatement) {
+        if let Some(renamed) = &mut self.renamed_variables {
+            renamed.push_lines(statement.iter_identifiers());
+        }
+    }
+
+    fn process_numeric_for_statement(&mut self, statement: &mut NumericForStatement) {
+        if let Some(renamed) = &mut self.renamed_variables {
+            renamed.push_lines(std::iter::once(statement.get_identifier()));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -159,7 +239,7 @@ mod test {
     use super::*;
 
     fn new_scope() -> RenameProcessor {
-        RenameProcessor::new(Vec::new().into_iter(), true)
+        RenameProcessor::new(Vec::new(), true)
     }
 
     #[test]
@@ -206,66 +286,4 @@ mod test {
 
         assert_eq!(&obfuscated, scope.get_obfuscated_name(&real).unwrap());
     }
-
-    #[test]
-    fn sort_char_digits() {
-        assert_eq!(sort_char('0', '1'), Ordering::Less);
-        assert_eq!(sort_char('1', '2'), Ordering::Less);
-        assert_eq!(sort_char('4', '2'), Ordering::Greater);
-        assert_eq!(sort_char('5', '5'), Ordering::Equal);
-    }
-
-    #[test]
-    fn sort_char_lowercase_letters() {
-        assert_eq!(sort_char('a', 'f'), Ordering::Less);
-        assert_eq!(sort_char('y', 'i'), Ordering::Greater);
-        assert_eq!(sort_char('t', 't'), Ordering::Equal);
-    }
-
-    #[test]
-    fn sort_char_uppercase_letters() {
-        assert_eq!(sort_char('A', 'F'), Ordering::Less);
-        assert_eq!(sort_char('Y', 'I'), Ordering::Greater);
-        assert_eq!(sort_char('T', 'T'), Ordering::Equal);
-    }
-
-    #[test]
-    fn sort_char_underscore_is_less_than_digit() {
-        for digit in "0123456789".chars() {
-            assert_eq!(sort_char('_', digit), Ordering::Less);
-        }
-    }
-
-    #[test]
-    fn sort_char_underscore_is_greather_than_letters() {
-        assert_eq!(sort_char('_', 'a'), Ordering::Greater);
-        assert_eq!(sort_char('_', 'A'), Ordering::Greater);
-        assert_eq!(sort_char('a', '_'), Ordering::Less);
-        assert_eq!(sort_char('A', '_'), Ordering::Less);
-    }
-
-    #[test]
-    fn sort_char_lowercase_is_less_than_uppercase() {
-        assert_eq!(sort_char('a', 'A'), Ordering::Less);
-        assert_eq!(sort_char('A', 'a'), Ordering::Greater);
-        assert_eq!(sort_char('z', 'A'), Ordering::Less);
-        assert_eq!(sort_char('A', 'z'), Ordering::Greater);
-    }
-
-    #[test]
-    fn sort_identifiers_compare_chars() {
-        assert_eq!(sort_identifiers("foo", "foo"), Ordering::Equal);
-        assert_eq!(sort_identifiers("aA", "ab"), Ordering::Greater);
-        assert_eq!(sort_identifiers("foo1", "foo9"), Ordering::Less);
-    }
-
-    #[test]
-    fn sort_identifiers_shorter_is_less() {
-        assert_eq!(sort_identifiers("foo", "fooo"), Ordering::Less);
-    }
-
-    #[test]
-    fn sort_identifiers_longer_is_greather() {
-        assert_eq!(sort_identifiers("foo", "fo"), Ordering::Greater);
-    }
 }
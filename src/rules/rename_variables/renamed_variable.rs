@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// The scope name given to variables declared outside of any function.
+pub(crate) const MAIN_SCOPE_NAME: &str = "<main>";
+/// The scope name given to variables declared in a function expression.
+pub(crate) const ANONYMOUS_SCOPE_NAME: &str = "<anonymous>";
+
+/// A variable renamed by the `rename_variables` rule, as written in the name mapping file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenamedVariable {
+    name: String,
+    original: String,
+    scope: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+}
+
+impl RenamedVariable {
+    pub fn new(
+        name: impl Into<String>,
+        original: impl Into<String>,
+        scope: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            original: original.into(),
+            scope: scope.into(),
+            line: None,
+        }
+    }
+
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    /// The name given to the variable by the rule.
+    #[inline]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn get_original(&self) -> &str {
+        &self.original
+    }
+
+    /// The name of the function where the variable is declared.
+    #[inline]
+    pub fn get_scope(&self) -> &str {
+        &self.scope
+    }
+
+    /// The line where the variable is declared. It is only available when the original lines
+    /// are preserved (with the `retain_lines` generator).
+    #[inline]
+    pub fn get_line(&self) -> Option<usize> {
+        self.line
+    }
+}
@@ -0,0 +1,473 @@
+use crate::nodes::{Arguments, Block, Expression, FunctionCall, Prefix};
+use crate::process::{DefaultVisitor, NodeProcessor, NodeVisitor};
+use crate::RojoProject;
+
+use std::path::{Component, Path, PathBuf};
+
+const MODULE_EXTENSIONS: [&str; 2] = ["lua", "luau"];
+/// The extensions of the data files that can be loaded with `require` calls.
+pub(crate) const DATA_EXTENSIONS: [&str; 3] = ["json", "toml", "txt"];
+/// The data files extensions that Rojo turns into module scripts, used to resolve instance
+/// paths to data files.
+const DATA_MODULE_EXTENSIONS: [&str; 2] = ["json", "toml"];
+
+/// Returns the argument given to a `require` call, or `None` if the function call is not a
+/// call to the `require` function with a single argument.
+pub(crate) fn get_require_argument(call: &FunctionCall) -> Option<&Expression> {
+    if call.get_method().is_some() {
+        return None;
+    }
+
+    match call.get_prefix() {
+        Prefix::Identifier(identifier) if identifier.get_name() == "require" => {}
+        _ => return None,
+    }
+
+    match call.get_arguments() {
+        Arguments::Tuple(tuple) if tuple.len() == 1 => tuple.iter_values().next(),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct RequireCollector {
+    arguments: Vec<Expression>,
+}
+
+impl NodeProcessor for RequireCollector {
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        if let Some(argument) = get_require_argument(call) {
+            self.arguments.push(argument.clone());
+        }
+    }
+}
+
+/// Returns the arguments of every `require` call found in the given block.
+pub(crate) fn collect_require_arguments(block: &Block) -> Vec<Expression> {
+    let mut collector = RequireCollector::default();
+    let mut block = block.clone();
+    DefaultVisitor::visit_block(&mut block, &mut collector);
+    collector.arguments
+}
+
+/// Returns the list of files that could be loaded by requiring the given expression from the
+/// `source` file, in the order they should be looked up. An empty list means the require
+/// argument cannot be resolved statically.
+pub(crate) fn find_module_candidates(
+    argument: &Expression,
+    source: &Path,
+    project: Option<&RojoProject>,
+) -> Vec<PathBuf> {
+    match argument {
+        Expression::String(string) => match get_relative_path(string.get_value(), source) {
+            Some(path) if has_module_extension(&path) => vec![path],
+            Some(path) => module_files(&path),
+            None => Vec::new(),
+        },
+        _ => get_instance_path(argument, source, project)
+            .map(|path| module_files(&path))
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns the list of data files that could be loaded by requiring the given expression from
+/// the `source` file. String paths must include the extension of the data file.
+pub(crate) fn find_data_file_candidates(
+    argument: &Expression,
+    source: &Path,
+    project: Option<&RojoProject>,
+) -> Vec<PathBuf> {
+    match argument {
+        Expression::String(string) => get_relative_path(string.get_value(), source)
+            .filter(|path| is_data_file(path))
+            .into_iter()
+            .collect(),
+        _ => get_instance_path(argument, source, project)
+            .map(|path| {
+                DATA_MODULE_EXTENSIONS
+                    .iter()
+                    .map(|extension| path.with_extension(extension))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns `true` if the path has the extension of a data file that can be required.
+pub(crate) fn is_data_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| DATA_EXTENSIONS.contains(&extension))
+}
+
+/// Returns the path required by a string path relative to the `source` file (starting with
+/// `./` or `../`), or `None` if the string is not a relative path.
+pub(crate) fn get_relative_path(value: &str, source: &Path) -> Option<PathBuf> {
+    if !value.starts_with("./") && !value.starts_with("../") {
+        return None;
+    }
+
+    Some(
+        source
+            .parent()
+            .map(|parent| parent.join(value))
+            .unwrap_or_else(|| PathBuf::from(value)),
+    )
+}
+
+/// Returns the path to the instance created from the given module file path: the extension is
+/// removed and `init` files are represented by their parent directory.
+pub(crate) fn get_module_instance_path(path: &Path) -> Option<PathBuf> {
+    let path = if has_module_extension(path) {
+        path.with_extension("")
+    } else {
+        path.to_path_buf()
+    };
+
+    if path.file_name().and_then(|name| name.to_str()) == Some("init") {
+        path.parent().map(Path::to_path_buf)
+    } else {
+        Some(path)
+    }
+}
+
+fn has_module_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| MODULE_EXTENSIONS.contains(&extension))
+}
+
+fn module_files(path: &Path) -> Vec<PathBuf> {
+    MODULE_EXTENSIONS
+        .iter()
+        .map(|extension| path.with_extension(extension))
+        .chain(
+            MODULE_EXTENSIONS
+                .iter()
+                .map(|extension| path.join("init").with_extension(extension)),
+        )
+        .collect()
+}
+
+/// Returns the path to the instance that represents the given file: `init` files represent
+/// their parent directory.
+pub(crate) fn get_script_path(source: &Path) -> Option<PathBuf> {
+    let is_init = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem == "init");
+
+    if is_init {
+        source.parent().map(Path::to_path_buf)
+    } else {
+        Some(source.with_extension(""))
+    }
+}
+
+/// A location in the instance tree while resolving a Roblox instance path. Without a Rojo
+/// project, instances are located using the file paths directly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum InstanceLocation {
+    File(PathBuf),
+    Instance(Vec<String>),
+}
+
+impl InstanceLocation {
+    fn parent(self) -> Option<Self> {
+        match self {
+            Self::File(path) => path.parent().map(|parent| Self::File(parent.to_path_buf())),
+            Self::Instance(mut names) => {
+                if names.len() > 1 {
+                    names.pop();
+                    Some(Self::Instance(names))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn child(self, name: &str) -> Option<Self> {
+        if name == "Parent" {
+            return self.parent();
+        }
+
+        Some(match self {
+            Self::File(path) => Self::File(path.join(name)),
+            Self::Instance(mut names) => {
+                names.push(name.to_owned());
+                Self::Instance(names)
+            }
+        })
+    }
+}
+
+/// Returns the path to the instance referenced by a Roblox expression starting from `script`,
+/// like `script.Parent.Module`. When a Rojo project is given, expressions starting from `game`
+/// can also be resolved.
+pub(crate) fn get_instance_path(
+    expression: &Expression,
+    source: &Path,
+    project: Option<&RojoProject>,
+) -> Option<PathBuf> {
+    let resolver = InstanceResolver { source, project };
+
+    match resolver.resolve_expression(expression)? {
+        InstanceLocation::File(path) => Some(path),
+        InstanceLocation::Instance(names) => project?.get_file_path(&names),
+    }
+}
+
+/// Returns the names of each component of the given path, or `None` if the path contains
+/// components that cannot be used as instance names.
+pub(crate) fn get_path_names(path: &Path) -> Option<Vec<String>> {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .map(|component| match component {
+            Component::Normal(name) => name.to_str().map(ToOwned::to_owned),
+            _ => None,
+        })
+        .collect()
+}
+
+struct InstanceResolver<'a> {
+    source: &'a Path,
+    project: Option<&'a RojoProject>,
+}
+
+impl<'a> InstanceResolver<'a> {
+    fn resolve_script(&self) -> Option<InstanceLocation> {
+        let script_path = get_script_path(self.source)?;
+
+        Some(
+            match self
+                .project
+                .and_then(|project| project.get_instance_path(&script_path))
+            {
+                Some(names) => InstanceLocation::Instance(names),
+                None => InstanceLocation::File(script_path),
+            },
+        )
+    }
+
+    fn resolve_identifier(&self, name: &str) -> Option<InstanceLocation> {
+        match name {
+            "script" => self.resolve_script(),
+            "game" if self.project.is_some() => {
+                Some(InstanceLocation::Instance(vec!["game".to_owned()]))
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve_expression(&self, expression: &Expression) -> Option<InstanceLocation> {
+        match expression {
+            Expression::Call(call) => self.resolve_call(call),
+            Expression::Field(field) => self
+                .resolve_prefix(field.get_prefix())?
+                .child(field.get_field().get_name()),
+            Expression::Identifier(identifier) => self.resolve_identifier(identifier.get_name()),
+            Expression::Index(index) => match index.get_index() {
+                Expression::String(string) => self
+                    .resolve_prefix(index.get_prefix())?
+                    .child(string.get_value()),
+                _ => None,
+            },
+            Expression::Parenthese(parenthese) => {
+                self.resolve_expression(parenthese.inner_expression())
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve_prefix(&self, prefix: &Prefix) -> Option<InstanceLocation> {
+        match prefix {
+            Prefix::Call(call) => self.resolve_call(call),
+            Prefix::Field(field) => self
+                .resolve_prefix(field.get_prefix())?
+                .child(field.get_field().get_name()),
+            Prefix::Identifier(identifier) => self.resolve_identifier(identifier.get_name()),
+            Prefix::Index(index) => match index.get_index() {
+                Expression::String(string) => self
+                    .resolve_prefix(index.get_prefix())?
+                    .child(string.get_value()),
+                _ => None,
+            },
+            Prefix::Parenthese(parenthese) => {
+                self.resolve_expression(parenthese.inner_expression())
+            }
+        }
+    }
+
+    fn resolve_call(&self, call: &FunctionCall) -> Option<InstanceLocation> {
+        let method = call.get_method()?.get_name();
+
+        if !matches!(
+            method.as_str(),
+            "FindFirstChild" | "WaitForChild" | "GetService"
+        ) {
+            return None;
+        }
+
+        let child_name = match call.get_arguments() {
+            Arguments::String(string) => string.get_value(),
+            Arguments::Tuple(tuple) => match tuple.iter_values().next() {
+                Some(Expression::String(string)) => string.get_value(),
+                _ => return None,
+            },
+            Arguments::Table(_) => return None,
+        };
+
+        let location = self.resolve_prefix(call.get_prefix())?;
+
+        if method == "GetService" && location != InstanceLocation::Instance(vec!["game".to_owned()])
+        {
+            return None;
+        }
+
+        location.child(child_name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::Parser;
+
+    fn candidates(code: &str, source: &str) -> Vec<PathBuf> {
+        let block = Parser::default()
+            .parse(&format!("return {}", code))
+            .expect("unable to parse code");
+        let expression = block
+            .get_last_statement()
+            .and_then(|last| match last {
+                crate::nodes::LastStatement::Return(statement) => {
+                    statement.iter_expressions().next().cloned()
+                }
+                _ => None,
+            })
+            .expect("expected return expression");
+
+        find_module_candidates(&expression, Path::new(source), None)
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn relative_path_with_extension() {
+        assert_eq!(
+            candidates("'./module.lua'", "src/main.lua"),
+            paths(&["src/./module.lua"])
+        );
+    }
+
+    #[test]
+    fn relative_path_without_extension() {
+        assert_eq!(
+            candidates("'../lib/module'", "src/main.lua"),
+            paths(&[
+                "src/../lib/module.lua",
+                "src/../lib/module.luau",
+                "src/../lib/module/init.lua",
+                "src/../lib/module/init.luau",
+            ])
+        );
+    }
+
+    #[test]
+    fn module_name_is_not_resolved() {
+        assert_eq!(
+            candidates("'module'", "src/main.lua"),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn script_parent_child() {
+        assert_eq!(
+            candidates("script.Parent.Module", "src/main.lua"),
+            paths(&[
+                "src/Module.lua",
+                "src/Module.luau",
+                "src/Module/init.lua",
+                "src/Module/init.luau",
+            ])
+        );
+    }
+
+    #[test]
+    fn script_child_from_init_file() {
+        assert_eq!(
+            candidates("script:WaitForChild('Module')", "src/init.lua"),
+            paths(&[
+                "src/Module.lua",
+                "src/Module.luau",
+                "src/Module/init.lua",
+                "src/Module/init.luau",
+            ])
+        );
+    }
+
+    #[test]
+    fn script_parent_index_child() {
+        assert_eq!(
+            candidates("script.Parent['Module']", "src/main.lua"),
+            paths(&[
+                "src/Module.lua",
+                "src/Module.luau",
+                "src/Module/init.lua",
+                "src/Module/init.luau",
+            ])
+        );
+    }
+
+    fn data_candidates(code: &str, source: &str) -> Vec<PathBuf> {
+        let block = Parser::default()
+            .parse(&format!("return {}", code))
+            .expect("unable to parse code");
+        let expression = match block.get_last_statement() {
+            Some(crate::nodes::LastStatement::Return(statement)) => {
+                statement.iter_expressions().next().cloned()
+            }
+            _ => None,
+        }
+        .expect("expected return expression");
+
+        find_data_file_candidates(&expression, Path::new(source), None)
+    }
+
+    #[test]
+    fn relative_data_file_path() {
+        assert_eq!(
+            data_candidates("'./strings.json'", "src/main.lua"),
+            paths(&["src/./strings.json"])
+        );
+    }
+
+    #[test]
+    fn relative_path_without_data_extension() {
+        assert_eq!(
+            data_candidates("'./module'", "src/main.lua"),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn script_parent_data_file() {
+        assert_eq!(
+            data_candidates("script.Parent.config", "src/main.lua"),
+            paths(&["src/config.json", "src/config.toml"])
+        );
+    }
+
+    #[test]
+    fn unknown_identifier_is_not_resolved() {
+        assert_eq!(
+            candidates("game.ReplicatedStorage.Module", "src/main.lua"),
+            Vec::<PathBuf>::new()
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +14,8 @@ pub enum RulePropertyValue {
     Usize(usize),
     Float(f64),
     StringList(Vec<String>),
+    List(Vec<RulePropertyValue>),
+    Map(BTreeMap<String, RulePropertyValue>),
     None,
 }
 
@@ -47,6 +49,18 @@ impl From<f64> for RulePropertyValue {
     }
 }
 
+impl From<Vec<RulePropertyValue>> for RulePropertyValue {
+    fn from(value: Vec<RulePropertyValue>) -> Self {
+        Self::List(value)
+    }
+}
+
+impl From<BTreeMap<String, RulePropertyValue>> for RulePropertyValue {
+    fn from(value: BTreeMap<String, RulePropertyValue>) -> Self {
+        Self::Map(value)
+    }
+}
+
 impl<T: Into<RulePropertyValue>> From<Option<T>> for RulePropertyValue {
     fn from(value: Option<T>) -> Self {
         match value {
@@ -102,6 +116,38 @@ mod test {
         assert_eq!(RulePropertyValue::from(1.0), RulePropertyValue::Float(1.0));
     }
 
+    #[test]
+    fn deserialize_string_list() {
+        assert_eq!(
+            json5::from_str::<RulePropertyValue>("['a', 'b']").unwrap(),
+            RulePropertyValue::StringList(vec!["a".to_owned(), "b".to_owned()])
+        );
+    }
+
+    #[test]
+    fn deserialize_mixed_list() {
+        assert_eq!(
+            json5::from_str::<RulePropertyValue>("['a', true, null]").unwrap(),
+            RulePropertyValue::List(vec![
+                RulePropertyValue::from("a"),
+                RulePropertyValue::from(true),
+                RulePropertyValue::None,
+            ])
+        );
+    }
+
+    #[test]
+    fn deserialize_map() {
+        let mut map = BTreeMap::new();
+        map.insert("enabled".to_owned(), RulePropertyValue::from(true));
+        map.insert("count".to_owned(), RulePropertyValue::from(2));
+
+        assert_eq!(
+            json5::from_str::<RulePropertyValue>("{ enabled: true, count: 2 }").unwrap(),
+            RulePropertyValue::Map(map)
+        );
+    }
+
     #[test]
     fn from_boolean_option_some() {
         let bool = Some(true);
@@ -0,0 +1,246 @@
+use crate::nodes::{
+    AssignStatement, Block, CompoundAssignStatement, Expression, FunctionCall, FunctionExpression,
+    FunctionStatement, GenericForStatement, Identifier, LocalAssignStatement,
+    LocalFunctionStatement, NumericForStatement, Prefix, Statement, UnaryOperator, Variable,
+};
+use crate::process::utils::is_valid_identifier;
+use crate::process::NodeProcessor;
+
+use std::collections::HashSet;
+
+/// The maximum number of local variables that can be active at the same time in a function.
+const MAX_LOCALS: usize = 200;
+/// The maximum number of upvalues a function can capture in Lua 5.1.
+pub(crate) const MAX_UPVALUES: usize = 60;
+// keeps room for the temporary values of expressions and for the variables added by other rules
+const LOCALS_SAFETY_MARGIN: usize = 10;
+// the hidden variables that hold the state of a for loop (like the limit and the step)
+const FOR_LOOP_HIDDEN_LOCALS: usize = 3;
+
+/// Collects the variables that are declared and assigned in a part of the code.
+#[derive(Debug, Default)]
+pub(crate) struct VariableUsage {
+    pub names: HashSet<String>,
+    pub declared: HashSet<String>,
+    pub assigned: HashSet<String>,
+    pub has_field_assignments: bool,
+    pub has_calls: bool,
+}
+
+impl VariableUsage {
+    fn declare(&mut self, identifier: &Identifier) {
+        self.names.insert(identifier.get_name().to_owned());
+        self.declared.insert(identifier.get_name().to_owned());
+    }
+
+    fn assign(&mut self, variable: &Variable) {
+        match variable {
+            Variable::Identifier(identifier) => {
+                self.assigned.insert(identifier.get_name().to_owned());
+            }
+            Variable::Field(_) | Variable::Index(_) => self.has_field_assignments = true,
+        }
+    }
+}
+
+impl NodeProcessor for VariableUsage {
+    fn process_assign_statement(&mut self, assign: &mut AssignStatement) {
+        assign
+            .iter_variables()
+            .for_each(|variable| self.assign(variable));
+    }
+
+    fn process_compound_assign_statement(&mut self, assign: &mut CompoundAssignStatement) {
+        self.assign(assign.get_variable());
+    }
+
+    fn process_function_statement(&mut self, function: &mut FunctionStatement) {
+        let name = function.get_name();
+
+        if name.get_field_names().is_empty() && name.get_method().is_none() {
+            self.assigned.insert(name.get_name().get_name().to_owned());
+        } else {
+            self.has_field_assignments = true;
+        }
+
+        function
+            .iter_parameters()
+            .for_each(|parameter| self.declare(parameter));
+    }
+
+    fn process_local_assign_statement(&mut self, assign: &mut LocalAssignStatement) {
+        assign
+            .iter_variables()
+            .for_each(|variable| self.declare(variable));
+    }
+
+    fn process_local_function_statement(&mut self, function: &mut LocalFunctionStatement) {
+        self.declare(function.get_identifier());
+        function
+            .get_parameters()
+            .iter()
+            .for_each(|parameter| self.declare(parameter));
+    }
+
+    fn process_function_expression(&mut self, function: &mut FunctionExpression) {
+        function
+            .get_parameters()
+            .iter()
+            .for_each(|parameter| self.declare(parameter));
+    }
+
+    fn process_generic_for_statement(&mut self, generic_for: &mut GenericForStatement) {
+        generic_for
+            .iter_identifiers()
+            .for_each(|identifier| self.declare(identifier));
+    }
+
+    fn process_numeric_for_statement(&mut self, numeric_for: &mut NumericForStatement) {
+        self.declare(numeric_for.get_identifier());
+    }
+
+    fn process_function_call(&mut self, _: &mut FunctionCall) {
+        self.has_calls = true;
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        self.names.insert(identifier.get_name().to_owned());
+    }
+}
+
+pub(crate) fn get_prefix_root(prefix: &Prefix) -> Option<&str> {
+    match prefix {
+        Prefix::Identifier(identifier) => Some(identifier.get_name()),
+        Prefix::Field(field) => get_prefix_root(field.get_prefix()),
+        Prefix::Index(index) => get_prefix_root(index.get_prefix()),
+        Prefix::Call(_) | Prefix::Parenthese(_) => None,
+    }
+}
+
+pub(crate) fn get_expression_root(expression: &Expression) -> Option<&str> {
+    match expression {
+        Expression::Identifier(identifier) => Some(identifier.get_name()),
+        Expression::Field(field) => get_prefix_root(field.get_prefix()),
+        Expression::Index(index) => get_prefix_root(index.get_prefix()),
+        _ => None,
+    }
+}
+
+/// Collects information about an expression to find if it can be moved to another location
+/// without changing its value.
+#[derive(Debug, Default)]
+pub(crate) struct ExpressionInspector {
+    pub identifiers: Vec<String>,
+    pub table_roots: Vec<Option<String>>,
+    pub has_unmovable_expression: bool,
+}
+
+impl NodeProcessor for ExpressionInspector {
+    fn process_expression(&mut self, expression: &mut Expression) {
+        match expression {
+            Expression::Call(_)
+            | Expression::Function(_)
+            | Expression::Table(_)
+            | Expression::VariableArguments(_) => self.has_unmovable_expression = true,
+            Expression::Field(field) => self
+                .table_roots
+                .push(get_prefix_root(field.get_prefix()).map(str::to_owned)),
+            Expression::Index(index) => self
+                .table_roots
+                .push(get_prefix_root(index.get_prefix()).map(str::to_owned)),
+            Expression::Unary(unary) if matches!(unary.operator(), UnaryOperator::Length) => self
+                .table_roots
+                .push(get_expression_root(unary.get_expression()).map(str::to_owned)),
+            _ => {}
+        }
+    }
+
+    fn process_function_call(&mut self, _: &mut FunctionCall) {
+        self.has_unmovable_expression = true;
+    }
+
+    fn process_variable_expression(&mut self, identifier: &mut Identifier) {
+        self.identifiers.push(identifier.get_name().to_owned());
+    }
+}
+
+/// Generates a name for a new variable that will hold the given expression. The name is made
+/// different from all the given names, and is added to them.
+pub(crate) fn generate_variable_name(
+    expression: &Expression,
+    names: &mut HashSet<String>,
+) -> String {
+    let prefix = match expression {
+        Expression::Field(field) => field.get_field().get_name().to_owned(),
+        Expression::Index(index) => match index.get_index() {
+            Expression::String(string) if is_valid_identifier(string.get_value()) => {
+                string.get_value().to_owned()
+            }
+            _ => "value".to_owned(),
+        },
+        _ => "value".to_owned(),
+    };
+
+    let mut name = prefix.clone();
+    let mut suffix = 1;
+
+    while names.contains(&name) || !is_valid_identifier(&name) {
+        name = format!("{}{}", prefix, suffix);
+        suffix += 1;
+    }
+
+    names.insert(name.clone());
+    name
+}
+
+/// Returns the number of local variables that can be declared at the beginning of the main
+/// chunk without going over the limit of local variables active at the same time.
+pub(crate) fn get_available_locals(block: &Block) -> usize {
+    MAX_LOCALS.saturating_sub(count_active_locals(block) + LOCALS_SAFETY_MARGIN)
+}
+
+/// Returns the maximum number of local variables active at the same time in a block, including
+/// the variables of nested blocks and loops. The variables of nested functions are not counted,
+/// since each function has its own limit.
+fn count_active_locals(block: &Block) -> usize {
+    let mut active = 0;
+    let mut maximum = 0;
+
+    for statement in block.iter_statements() {
+        let nested = match statement {
+            Statement::LocalAssign(assign) => {
+                active += assign.variables_len();
+                0
+            }
+            Statement::LocalFunction(_) => {
+                active += 1;
+                0
+            }
+            Statement::Do(statement) => count_active_locals(statement.get_block()),
+            Statement::If(statement) => statement
+                .iter_branches()
+                .map(|branch| count_active_locals(branch.get_block()))
+                .chain(statement.get_else_block().map(count_active_locals))
+                .max()
+                .unwrap_or(0),
+            Statement::While(statement) => count_active_locals(statement.get_block()),
+            Statement::Repeat(statement) => count_active_locals(statement.get_block()),
+            Statement::NumericFor(statement) => {
+                FOR_LOOP_HIDDEN_LOCALS + 1 + count_active_locals(statement.get_block())
+            }
+            Statement::GenericFor(statement) => {
+                FOR_LOOP_HIDDEN_LOCALS
+                    + statement.identifiers_len()
+                    + count_active_locals(statement.get_block())
+            }
+            Statement::Assign(_)
+            | Statement::Call(_)
+            | Statement::CompoundAssign(_)
+            | Statement::Function(_) => 0,
+        };
+
+        maximum = maximum.max(active + nested);
+    }
+
+    maximum
+}
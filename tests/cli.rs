@@ -206,6 +206,52 @@ fn snapshot_minify_help_command() {
         .snapshot_command("minify_help_command");
 }
 
+#[test]
+fn snapshot_dependencies_help_command() {
+    Context::default()
+        .arg("dependencies")
+        .arg("--help")
+        .snapshot_command("dependencies_help_command");
+}
+
+#[test]
+fn run_dependencies_command() {
+    Context::default()
+        .write_file("src/init.lua", "return require('./value')\n")
+        .write_file("src/value.lua", "return 1\n")
+        .arg("dependencies")
+        .arg("src")
+        .expect_success()
+        .snapshot_command("run_dependencies_command");
+}
+
+#[test]
+fn run_dependencies_command_with_dot_output() {
+    Context::default()
+        .write_file("src/init.lua", "return require('./value')\n")
+        .write_file("src/value.lua", "return 1\n")
+        .arg("dependencies")
+        .arg("src")
+        .arg("--format")
+        .arg("dot")
+        .arg("--output")
+        .arg("graph.dot")
+        .expect_success()
+        .snapshot_file("run_dependencies_command_with_dot_output", "graph.dot");
+}
+
+#[test]
+fn run_dependencies_command_with_cycle() {
+    Context::default()
+        .write_file("src/a.lua", "return require('./b')\n")
+        .write_file("src/b.lua", "return require('./a')\n")
+        .arg("dependencies")
+        .arg("src")
+        .arg("--format")
+        .arg("dot")
+        .snapshot_command("run_dependencies_command_with_cycle");
+}
+
 #[test]
 fn run_minify_command() {
     Context::default()
@@ -326,3 +372,29 @@ fn run_process_single_file_custom_config_command_deprecated_config_path() {
         .snapshot_command("run_process_single_file_custom_config")
         .snapshot_file("run_process_custom_config_command_out", "out.lua");
 }
+
+#[test]
+fn snapshot_demangle_help_command() {
+    Context::default()
+        .arg("demangle")
+        .arg("--help")
+        .snapshot_command("demangle_help_command");
+}
+
+#[test]
+fn run_demangle_command() {
+    Context::default()
+        .write_file(
+            "names.json",
+            r#"{"files":{"out/main.lua":[{"name":"a","original":"count","scope":"<main>","line":1}]}}"#,
+        )
+        .write_file(
+            "trace.txt",
+            "out/main.lua:3: attempt to perform arithmetic on a nil value (local 'a')\n",
+        )
+        .arg("demangle")
+        .arg("names.json")
+        .arg("trace.txt")
+        .expect_success()
+        .snapshot_command("run_demangle_command");
+}
@@ -141,14 +141,764 @@ fn use_default_json5_config_in_place() {
     assert_eq!(resources.get("src/test.lua").unwrap(), "return 'Hello'");
 }
 
-mod errors {
+#[test]
+fn inject_table_and_remove_unused_branches() {
+    let resources = memory_resources!(
+        "src/main.lua" => "if FLAGS.newShop then openShop() else openLegacyShop() end\nif FLAGS.missing then debug() end\n",
+        ".darklua.json5" => r#"{
+            generator: "readable",
+            rules: [
+                { rule: "inject_global_value", identifier: "FLAGS", value: { newShop: true } },
+                "remove_unused_if_branch",
+            ],
+        }"#,
+    );
+
+    process(&resources, Options::new("src")).result().unwrap();
+
+    assert_eq!(
+        resources.get("src/main.lua").unwrap(),
+        "do\n    openShop()\nend\n"
+    );
+}
+
+#[test]
+fn inject_value_from_file_relative_to_configuration() {
+    let resources = memory_resources!(
+        "src/main.lua" => "return BUILD.version",
+        "config/build.toml" => "version = \"1.2.0\"",
+        "config/darklua.json5" => r#"{
+            rules: [
+                { rule: "inject_global_value", identifier: "BUILD", file: "build.toml" },
+                "compute_expression",
+            ],
+        }"#,
+    );
+
+    process(
+        &resources,
+        Options::new("src").with_configuration_at("config/darklua.json5"),
+    )
+    .result()
+    .unwrap();
+
+    assert_eq!(resources.get("src/main.lua").unwrap(), "return '1.2.0'");
+}
+
+#[test]
+fn inject_value_from_missing_file_errors() {
+    let resources = memory_resources!(
+        "src/main.lua" => "return BUILD",
+        ".darklua.json5" => "{ rules: [{ rule: 'inject_global_value', identifier: 'BUILD', file: 'build.json' }] }",
+    );
+
+    assert!(process(&resources, Options::new("src")).result().is_err());
+}
+
+mod conditional_compilation {
+    use darklua_core::{process, Options, Resources};
+
+    use pretty_assertions::assert_eq;
+
+    const CONFIGURATION: &str = r#"{
+        generator: "retain-lines",
+        rules: [
+            { rule: "inject_global_value", identifier: "DEBUG", value: false },
+            { rule: "inject_global_value", identifier: "PLATFORM", value: "mobile" },
+        ],
+    }"#;
+
+    fn process_main(resources: &Resources) -> String {
+        process(resources, Options::new("src")).result().unwrap();
+
+        resources.get("src/main.lua").unwrap()
+    }
+
+    #[test]
+    fn excluded_section_is_removed() {
+        let resources = memory_resources!(
+            ".darklua.json5" => CONFIGURATION,
+            "src/main.lua" => "--#if DEBUG\nprint('debug')\n--#end\nreturn 1\n",
+        );
+
+        assert_eq!(
+            process_main(&resources),
+            "--#if DEBUG\n\n--#end\nreturn 1\n"
+        );
+    }
+
+    #[test]
+    fn excluded_section_with_invalid_code() {
+        let resources = memory_resources!(
+            ".darklua.json5" => CONFIGURATION,
+            "src/main.lua" => concat!(
+                "--#if PLATFORM == 'pc'\n",
+                "local value = @pc_only\n",
+                "--#else\n",
+                "local value = 'mobile'\n",
+                "--#end\n",
+                "return value\n",
+            ),
+        );
+
+        assert_eq!(
+            process_main(&resources),
+            "--#if PLATFORM == 'pc'\n\n--#else\nlocal value = 'mobile'\n--#end\nreturn value\n"
+        );
+    }
+
+    #[test]
+    fn condition_with_value_from_file() {
+        let resources = memory_resources!(
+            ".darklua.json5" => r#"{
+                generator: "retain-lines",
+                rules: [{ rule: "inject_global_value", identifier: "BUILD", file: "build.json" }],
+            }"#,
+            "build.json" => "{ debug: true }",
+            "src/main.lua" => "--#if BUILD.debug\nprint('debug')\n--#end\nreturn 1\n",
+        );
+
+        assert_eq!(
+            process_main(&resources),
+            "--#if BUILD.debug\nprint('debug')\n--#end\nreturn 1\n"
+        );
+    }
+
+    #[test]
+    fn snapshot_unclosed_section_error() {
+        let resources = memory_resources!(
+            ".darklua.json5" => CONFIGURATION,
+            "src/main.lua" => "--#if DEBUG\nreturn 1\n",
+        );
+
+        let errors = process(&resources, Options::new("src"))
+            .result()
+            .unwrap_err();
+
+        let errors_display = errors
+            .into_iter()
+            .map(|err| format!("- {}", err))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!("unclosed_section_error", errors_display);
+    }
+}
+
+mod bundle_requires {
+    use darklua_core::{
+        process,
+        rules::{BundleRequires, Rule},
+        Configuration, GeneratorParameters, Options, Resources,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    fn process_bundle(resources: &Resources, input: &str) {
+        let rule: Box<dyn Rule> = Box::new(BundleRequires::default());
+        let configuration = Configuration::empty()
+            .with_rule(rule)
+            .with_generator(GeneratorParameters::default_readable());
+
+        process(
+            resources,
+            Options::new(input).with_configuration(configuration),
+        )
+        .result()
+        .unwrap();
+    }
+
+    #[test]
+    fn bundle_relative_require() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local value = require('./value')\nreturn value",
+            "src/value.lua" => "return true",
+        );
+
+        process_bundle(&resources, "src");
+
+        insta::assert_snapshot!(
+            "bundle_relative_require",
+            resources.get("src/main.lua").unwrap()
+        );
+    }
+
+    #[test]
+    fn bundle_roblox_require() {
+        let resources = memory_resources!(
+            "src/init.lua" => "local Value = require(script.Value)\nreturn Value",
+            "src/Value/init.lua" => "return require(script.Parent.Constant) + 1",
+            "src/Constant.lua" => "return 10",
+        );
+
+        process_bundle(&resources, "src");
+
+        insta::assert_snapshot!(
+            "bundle_roblox_require",
+            resources.get("src/init.lua").unwrap()
+        );
+    }
+
+    #[test]
+    fn bundle_nested_requires_loads_each_module_once() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local a = require('./a')\nlocal b = require('./b')\nreturn a + b",
+            "src/a.lua" => "return require('./b') + 1",
+            "src/b.lua" => "return 2",
+        );
+
+        process_bundle(&resources, "src");
+
+        insta::assert_snapshot!(
+            "bundle_nested_requires_loads_each_module_once",
+            resources.get("src/main.lua").unwrap()
+        );
+    }
+
+    #[test]
+    fn bundle_module_using_variadic_arguments() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local name = require('./name')\nreturn name",
+            "src/name.lua" => "local name = ...\nreturn name",
+        );
+
+        process_bundle(&resources, "src");
+
+        insta::assert_snapshot!(
+            "bundle_module_using_variadic_arguments",
+            resources.get("src/main.lua").unwrap()
+        );
+    }
+
+    #[test]
+    fn bundle_single_file_with_require_outside_inputs() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local value = require('./value')\nreturn value",
+            "src/value.lua" => "return true",
+        );
+
+        let rule: Box<dyn Rule> = Box::new(BundleRequires::default());
+        let errors = process(
+            &resources,
+            Options::new("src/main.lua").with_configuration(Configuration::empty().with_rule(rule)),
+        )
+        .result()
+        .unwrap_err();
+
+        let errors_display: Vec<_> = errors
+            .into_iter()
+            .map(|err| err.to_string().replace('\\', "/"))
+            .collect();
+
+        assert_eq!(
+            errors_display,
+            vec![
+                "required file `src/value.lua` not found in inputs (required by `src/main.lua`)"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn bundle_require_from_rojo_project() {
+        let resources = memory_resources!(
+            "default.project.json" => r#"{
+                "name": "place",
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": {
+                        "Shared": { "$path": "src/shared" }
+                    },
+                    "ServerScriptService": {
+                        "Server": { "$path": "src/server" }
+                    }
+                }
+            }"#,
+            "src/server/main.lua" => "return require(game:GetService('ReplicatedStorage').Shared.value)",
+            "src/shared/value.lua" => "return true",
+        );
+
+        let rule: Box<dyn Rule> = Box::new(BundleRequires::default());
+        let configuration = Configuration::empty()
+            .with_rule(rule)
+            .with_generator(GeneratorParameters::default_readable());
+
+        process(
+            &resources,
+            Options::new("src")
+                .with_configuration(configuration)
+                .with_project("default.project.json"),
+        )
+        .result()
+        .unwrap();
+
+        insta::assert_snapshot!(
+            "bundle_require_from_rojo_project",
+            resources.get("src/server/main.lua").unwrap()
+        );
+    }
+
+    #[test]
+    fn unresolved_require_is_kept() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('./missing'), require('module')",
+        );
+
+        process_bundle(&resources, "src");
+
+        assert_eq!(
+            resources.get("src/main.lua").unwrap(),
+            "return require('./missing'), require('module')\n"
+        );
+    }
+}
+
+mod convert_require {
+    use darklua_core::{
+        process,
+        rules::{ConvertRequire, RequireMode, Rule},
+        Configuration, GeneratorParameters, Options, Resources,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    fn process_convert(resources: &Resources, current: RequireMode, target: RequireMode) {
+        let rule: Box<dyn Rule> = Box::new(ConvertRequire::new(current, target));
+        let configuration = Configuration::empty()
+            .with_rule(rule)
+            .with_generator(GeneratorParameters::default_dense());
+
+        process(
+            resources,
+            Options::new("src").with_configuration(configuration),
+        )
+        .result()
+        .unwrap();
+    }
+
+    macro_rules! test_convert {
+        ($($name:ident ($current:ident => $target:ident, $path:literal, $input:literal) => $output:literal),* $(,)?) => {
+            $(
+                #[test]
+                fn $name() {
+                    let resources = memory_resources!(
+                        $path => $input,
+                    );
+
+                    process_convert(&resources, RequireMode::$current, RequireMode::$target);
+
+                    assert_eq!(resources.get($path).unwrap(), $output);
+                }
+            )*
+        };
+    }
+
+    const PROJECT: &str = r#"{
+        "name": "place",
+        "tree": {
+            "$className": "DataModel",
+            "ReplicatedStorage": {
+                "Shared": { "$path": "src/shared" },
+                "Config": { "$path": "src/config.luau" }
+            },
+            "ServerScriptService": {
+                "Server": { "$path": "src/server" }
+            }
+        }
+    }"#;
+
+    fn process_convert_with_project(
+        resources: &Resources,
+        current: RequireMode,
+        target: RequireMode,
+    ) {
+        let rule: Box<dyn Rule> = Box::new(ConvertRequire::new(current, target));
+        let configuration = Configuration::empty()
+            .with_rule(rule)
+            .with_generator(GeneratorParameters::default_dense());
+
+        process(
+            resources,
+            Options::new("src")
+                .with_configuration(configuration)
+                .with_project("default.project.json"),
+        )
+        .result()
+        .unwrap();
+    }
+
+    #[test]
+    fn path_to_roblox_in_other_service_with_project() {
+        let resources = memory_resources!(
+            "default.project.json" => PROJECT,
+            "src/server/main.lua" => "return require('../shared/value')",
+        );
+
+        process_convert_with_project(&resources, RequireMode::Path, RequireMode::Roblox);
+
+        assert_eq!(
+            resources.get("src/server/main.lua").unwrap(),
+            "return require(game:GetService('ReplicatedStorage').Shared.value)"
+        );
+    }
+
+    #[test]
+    fn path_to_roblox_in_same_service_with_project() {
+        let resources = memory_resources!(
+            "default.project.json" => PROJECT,
+            "src/server/main.lua" => "return require('./utils/init')",
+        );
+
+        process_convert_with_project(&resources, RequireMode::Path, RequireMode::Roblox);
+
+        assert_eq!(
+            resources.get("src/server/main.lua").unwrap(),
+            "return require(script.Parent.utils)"
+        );
+    }
+
+    #[test]
+    fn roblox_service_to_path_with_project() {
+        let resources = memory_resources!(
+            "default.project.json" => PROJECT,
+            "src/server/main.lua" => "return require(game.ReplicatedStorage.Shared.value)",
+        );
+
+        process_convert_with_project(&resources, RequireMode::Roblox, RequireMode::Path);
+
+        assert_eq!(
+            resources.get("src/server/main.lua").unwrap(),
+            "return require('../shared/value')"
+        );
+    }
+
+    #[test]
+    fn roblox_single_file_to_path_with_project() {
+        let resources = memory_resources!(
+            "default.project.json" => PROJECT,
+            "src/server/main.lua" => "return require(game.ReplicatedStorage.Config)",
+        );
+
+        process_convert_with_project(&resources, RequireMode::Roblox, RequireMode::Path);
+
+        assert_eq!(
+            resources.get("src/server/main.lua").unwrap(),
+            "return require('../config')"
+        );
+    }
+
+    test_convert!(
+        sibling_path_to_roblox(Path => Roblox, "src/main.lua", "return require('./module')")
+            => "return require(script.Parent.module)",
+        sibling_path_with_extension_to_roblox(Path => Roblox, "src/main.lua", "return require('./module.lua')")
+            => "return require(script.Parent.module)",
+        child_path_from_init_to_roblox(Path => Roblox, "src/init.lua", "return require('./module')")
+            => "return require(script.module)",
+        parent_path_to_roblox(Path => Roblox, "src/lib/main.lua", "return require('../utils/init')")
+            => "return require(script.Parent.Parent.utils)",
+        path_with_invalid_identifier_to_roblox(Path => Roblox, "src/main.lua", "return require('./my-module')")
+            => "return require(script.Parent['my-module'])",
+        module_name_is_not_converted_to_roblox(Path => Roblox, "src/main.lua", "return require('module')")
+            => "return require('module')",
+        roblox_sibling_to_path(Roblox => Path, "src/main.lua", "return require(script.Parent.module)")
+            => "return require('./module')",
+        roblox_child_from_init_to_path(Roblox => Path, "src/init.lua", "return require(script:WaitForChild('module'))")
+            => "return require('./module')",
+        roblox_parent_to_path(Roblox => Path, "src/lib/main.lua", "return require(script.Parent.Parent.utils.format)")
+            => "return require('../utils/format')",
+        roblox_from_game_is_not_converted_to_path(Roblox => Path, "src/main.lua", "return require(game.ReplicatedStorage.module)")
+            => "return require(game.ReplicatedStorage.module)",
+    );
+}
+
+mod embed_data_files {
+    use darklua_core::{
+        process,
+        rules::{EmbedDataFiles, Rule},
+        Configuration, GeneratorParameters, Options, Resources,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    fn process_embed(resources: &Resources) -> Result<(), String> {
+        let rule: Box<dyn Rule> = Box::new(EmbedDataFiles::default());
+        let configuration = Configuration::empty()
+            .with_rule(rule)
+            .with_generator(GeneratorParameters::default_dense());
+
+        process(
+            resources,
+            Options::new("src").with_configuration(configuration),
+        )
+        .result()
+        .map_err(|errors| {
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    #[test]
+    fn embed_json_file() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('./strings.json')",
+            "src/strings.json" => r#"{ "title": "Shop", "buy-button": "Buy" }"#,
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(
+            resources.get("src/main.lua").unwrap(),
+            "return{['buy-button']='Buy',title='Shop'}"
+        );
+    }
+
+    #[test]
+    fn embed_json_file_field() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('./strings.json').title",
+            "src/strings.json" => r#"{ "title": "Shop" }"#,
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(
+            resources.get("src/main.lua").unwrap(),
+            "return({title='Shop'}).title"
+        );
+    }
+
+    #[test]
+    fn embed_toml_file() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local config = require('../config.toml')\nreturn config",
+            "config.toml" => "players = 8\n[shop]\nenabled = true\n",
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(
+            resources.get("src/main.lua").unwrap(),
+            "local config={players=8,shop={enabled=true}}return config"
+        );
+    }
+
+    #[test]
+    fn embed_text_file() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('./license.txt')",
+            "src/license.txt" => "MIT",
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(resources.get("src/main.lua").unwrap(), "return'MIT'");
+    }
+
+    #[test]
+    fn embed_roblox_json_module() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require(script.Parent.config)",
+            "src/config.json" => "[1, 2, 3]",
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(resources.get("src/main.lua").unwrap(), "return{1,2,3}");
+    }
+
+    #[test]
+    fn remove_data_file_require_statement() {
+        let resources = memory_resources!(
+            "src/main.lua" => "require('./data.json')\nreturn 1",
+            "src/data.json" => "{}",
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(resources.get("src/main.lua").unwrap(), "return 1");
+    }
+
+    #[test]
+    fn lua_module_require_is_not_replaced() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('./value')",
+            "src/value.lua" => "return true",
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(
+            resources.get("src/main.lua").unwrap(),
+            "return require('./value')"
+        );
+    }
+
+    #[test]
+    fn missing_data_file_is_not_replaced() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('./missing.json')",
+        );
+
+        process_embed(&resources).unwrap();
+
+        assert_eq!(
+            resources.get("src/main.lua").unwrap(),
+            "return require('./missing.json')"
+        );
+    }
+
+    #[test]
+    fn invalid_json_file_errors() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('./data.json')",
+            "src/data.json" => "{ invalid",
+        );
+
+        insta::assert_snapshot!(
+            "invalid_json_file_errors",
+            process_embed(&resources).unwrap_err()
+        );
+    }
+}
+
+mod dependency_graph {
     use std::path::{Path, PathBuf};
 
+    use darklua_core::{dependency_graph, Options, Resources};
+    use pretty_assertions::assert_eq;
+
+    fn dependencies<'a>(graph: &'a darklua_core::DependencyGraph, module: &str) -> Vec<&'a Path> {
+        graph.iter_dependencies(module).collect()
+    }
+
+    #[test]
+    fn graph_of_relative_requires() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local a = require('./a') local b = require('./b/init.luau') return a + b",
+            "src/a.lua" => "return require('./b')",
+            "src/b/init.luau" => "return 1",
+        );
+
+        let graph = dependency_graph(&resources, &Options::new("src")).unwrap();
+
+        assert_eq!(
+            graph.iter_modules().collect::<Vec<_>>(),
+            vec![
+                Path::new("src/a.lua"),
+                Path::new("src/b/init.luau"),
+                Path::new("src/main.lua")
+            ]
+        );
+        assert_eq!(
+            dependencies(&graph, "src/main.lua"),
+            vec![Path::new("src/a.lua"), Path::new("src/b/init.luau")]
+        );
+        assert_eq!(
+            dependencies(&graph, "src/a.lua"),
+            vec![Path::new("src/b/init.luau")]
+        );
+        assert!(graph.find_cycles().is_empty());
+    }
+
+    #[test]
+    fn graph_follows_requires_outside_of_input() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return require('../lib/value')",
+            "lib/value.lua" => "return require('./constant')",
+            "lib/constant.lua" => "return 1",
+        );
+
+        let graph = dependency_graph(&resources, &Options::new("src/main.lua")).unwrap();
+
+        assert_eq!(
+            graph.iter_modules().collect::<Vec<_>>(),
+            vec![
+                Path::new("lib/constant.lua"),
+                Path::new("lib/value.lua"),
+                Path::new("src/main.lua")
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_of_roblox_requires_with_project() {
+        let resources = memory_resources!(
+            "default.project.json" => r#"{
+                "name": "place",
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": {
+                        "Shared": { "$path": "src/shared" }
+                    },
+                    "ServerScriptService": {
+                        "Server": { "$path": "src/server" }
+                    }
+                }
+            }"#,
+            "src/server/main.lua" => "return require(game:GetService('ReplicatedStorage').Shared.value)",
+            "src/shared/value.lua" => "return true",
+        );
+
+        let graph = dependency_graph(
+            &resources,
+            &Options::new("src").with_project("default.project.json"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            dependencies(&graph, "src/server/main.lua"),
+            vec![Path::new("src/shared/value.lua")]
+        );
+    }
+
+    #[test]
+    fn graph_reports_every_cycle() {
+        let resources = memory_resources!(
+            "src/a.lua" => "return require('./b')",
+            "src/b.lua" => "return require('./c')",
+            "src/c.lua" => "return require('./a'), require('./b')",
+        );
+
+        let graph = dependency_graph(&resources, &Options::new("src")).unwrap();
+
+        insta::assert_snapshot!("dependency_graph_reports_every_cycle", graph.to_json());
+    }
+
+    #[test]
+    fn graph_reports_module_requiring_itself() {
+        let resources = memory_resources!(
+            "src/a.lua" => "return require('./a')",
+        );
+
+        let graph = dependency_graph(&resources, &Options::new("src")).unwrap();
+
+        assert_eq!(
+            dependencies(&graph, "src/a.lua"),
+            vec![Path::new("src/a.lua")]
+        );
+        assert_eq!(graph.find_cycles(), vec![vec![PathBuf::from("src/a.lua")]]);
+    }
+
+    #[test]
+    fn missing_input_errors() {
+        let resources = memory_resources!(
+            "src/a.lua" => "return 1",
+        );
+
+        assert!(dependency_graph(&resources, &Options::new("lib")).is_err());
+    }
+}
+
+mod errors {
+    use std::path::PathBuf;
+
     use darklua_core::{
         nodes::Block,
         rules::{
-            Context, Rule, RuleConfiguration, RuleConfigurationError, RuleProcessResult,
-            RuleProperties,
+            BundleRequires, Context, Rule, RuleConfiguration, RuleConfigurationError,
+            RuleProcessResult, RuleProperties,
         },
         Configuration,
     };
@@ -186,7 +936,7 @@ mod errors {
                 Ok(())
             }
 
-            fn require_content(&self, _: &Path, _: &Block) -> Vec<PathBuf> {
+            fn require_content(&self, _: &Context, _: &Block) -> Vec<PathBuf> {
                 vec!["src/a.lua".into(), "src/b.lua".into()]
             }
         }
@@ -206,4 +956,128 @@ mod errors {
             .join("\n");
         insta::assert_snapshot!("simple_cyclic_work_error", errors_display);
     }
+
+    #[test]
+    fn snapshot_invalid_project_error() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return true",
+            "default.project.json" => "{ \"tree\": {} }",
+        );
+
+        let errors = process(
+            &resources,
+            Options::new("src").with_project("default.project.json"),
+        )
+        .result()
+        .unwrap_err();
+
+        let errors_display = errors
+            .into_iter()
+            .map(|err| format!("- {}", err))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!("invalid_project_error", errors_display);
+    }
+
+    #[test]
+    fn missing_project_error() {
+        let resources = memory_resources!(
+            "src/main.lua" => "return true",
+        );
+
+        let errors = process(
+            &resources,
+            Options::new("src").with_project("default.project.json"),
+        )
+        .result()
+        .unwrap_err();
+
+        assert!(errors.len() == 1);
+    }
+
+    #[test]
+    fn snapshot_cyclic_bundle_requires_error() {
+        let resources = memory_resources!(
+            "src/a.lua" => "return require('./b')",
+            "src/b.lua" => "return require('./a')",
+        );
+
+        let rule: Box<dyn Rule> = Box::new(BundleRequires::default());
+        let errors = process(
+            &resources,
+            Options::new("src").with_configuration(Configuration::empty().with_rule(rule)),
+        )
+        .result()
+        .unwrap_err();
+
+        let errors_display = errors
+            .into_iter()
+            .map(|err| format!("- {}", err).replace('\\', "/"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!("cyclic_bundle_requires_error", errors_display);
+    }
+}
+
+mod rename_variables_mapping {
+    use darklua_core::{
+        process,
+        rules::{RenameVariables, RenamedVariable, Rule},
+        Configuration, GeneratorParameters, NameMap, Options, Resources,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn write_name_map() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local count = 0\nlocal function increment(amount)\n    count = count + amount\nend\nreturn increment\n",
+        );
+        let rule: Box<dyn Rule> = Box::new(RenameVariables::default().with_mapping("names.json"));
+        let configuration = Configuration::empty()
+            .with_rule(rule)
+            .with_generator(GeneratorParameters::RetainLines);
+
+        process(
+            &resources,
+            Options::new("src")
+                .with_output("out")
+                .with_configuration(configuration),
+        )
+        .result()
+        .unwrap();
+
+        let name_map = NameMap::from_json(&resources.get("names.json").unwrap()).unwrap();
+
+        assert_eq!(
+            name_map.get_variables("out/main.lua").unwrap(),
+            &[
+                RenamedVariable::new("a", "count", "<main>").with_line(1),
+                RenamedVariable::new("b", "amount", "increment").with_line(2),
+            ]
+        );
+        assert_eq!(
+            name_map.demangle(
+                "out/main.lua:3: attempt to perform arithmetic on a nil value (local 'b')"
+            ),
+            "out/main.lua:3: attempt to perform arithmetic on a nil value (local 'amount')"
+        );
+    }
+
+    #[test]
+    fn name_map_is_not_written_without_mapping() {
+        let resources = memory_resources!(
+            "src/main.lua" => "local count = 0\nreturn count\n",
+        );
+        let rule: Box<dyn Rule> = Box::<RenameVariables>::default();
+
+        process(
+            &resources,
+            Options::new("src").with_configuration(Configuration::empty().with_rule(rule)),
+        )
+        .result()
+        .unwrap();
+
+        assert!(!resources.exists("names.json").unwrap());
+    }
 }
@@ -34,11 +34,29 @@ test_rule!(
         => "return 'is equal'",
     if_expression_elseif_always_false("return if false then 'is true' elseif 1 == 2 then 'is equal' else nil")
         => "return nil",
+    table_field("return ({ a = 1 }).a") => "return 1",
+    table_field_missing("return ({ a = 1 }).b") => "return nil",
+    table_nested_field("return ({ a = { b = 'ok' } }).a.b") => "return 'ok'",
+    table_index_string("return ({ a = true })['a']") => "return true",
+    table_index_array("return ({ 'a', 'b', 'c' })[2]") => "return 'b'",
+    table_index_computed_key("return ({ [1 + 1] = 'two' })[2]") => "return 'two'",
+    table_length("return #{ 1, 2, 3 }") => "return 3",
+    table_length_with_fields("return #{ 1, 2, a = 3 }") => "return 2",
+    table_field_in_condition("return if ({ debug = false }).debug then 'a' else 'b'") => "return 'b'",
 );
 
-test_rule_wihout_effects!(if_expression_unknown_condition(
-    "return if condition then func() else func2()"
-),);
+test_rule_wihout_effects!(
+    ComputeExpression::default(),
+    if_expression_unknown_condition("return if condition then func() else func2()"),
+    table_field_with_call_entry("return ({ a = 1, b = call() }).a"),
+    table_field_with_unknown_value("return ({ a = var }).a"),
+    table_field_with_unknown_key("return ({ [key] = 1 }).a"),
+    table_index_after_call("return ({ 'a', call() })[2]"),
+    table_index_after_variadic("return ({ 'a', ... })[2]"),
+    table_length_with_hole("return #{ 1, nil, 3 }"),
+    table_length_with_variadic("return #{ ... }"),
+    field_of_variable("return var.a"),
+);
 
 #[test]
 fn deserialize_from_object_notation() {
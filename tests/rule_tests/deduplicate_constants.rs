@@ -0,0 +1,84 @@
+use darklua_core::rules::{DeduplicateConstants, Rule};
+
+test_rule!(
+    deduplicate_constants,
+    DeduplicateConstants::default(),
+    repeated_long_string("f('rbxassetid://1234') g('rbxassetid://1234')")
+        => "local a = 'rbxassetid://1234' f(a) g(a)",
+    repeated_string_call_argument("f'rbxassetid://1234' g'rbxassetid://1234'")
+        => "local a = 'rbxassetid://1234' f(a) g(a)",
+    repeated_number("return 123456789, 123456789, 123456789")
+        => "local a = 123456789 return a, a, a",
+    repeated_table_key("return { ['attribute-name'] = 1 }, { ['attribute-name'] = 2 }")
+        => "local a = 'attribute-name' return { [a] = 1 }, { [a] = 2 }",
+    repeated_in_functions(
+        "local function f() return 'some-long-value' end \
+        local function g() return 'some-long-value' end"
+    ) => "local a = 'some-long-value' local function f() return a end \
+        local function g() return a end",
+    name_already_used("local a = 'hello world!' print('hello world!', a)")
+        => "local b = 'hello world!' local a = b print(b, a)",
+    most_savings_get_first_name(
+        "f('short-str', 'short-str', 'short-str', 'a-much-longer-string', 'a-much-longer-string')"
+    ) => "local a = 'a-much-longer-string' local b = 'short-str' f(b, b, b, a, a)",
+    break_even_string_is_kept("f('short-str', 'short-str', 'a-much-longer-string', 'a-much-longer-string')")
+        => "local a = 'a-much-longer-string' f('short-str', 'short-str', a, a)",
+    same_string_with_different_quotes("f('some-long-value', \"some-long-value\")")
+        => "local a = 'some-long-value' f(a, a)",
+);
+
+test_rule_wihout_effects!(
+    DeduplicateConstants::default(),
+    single_occurrence("return 'rbxassetid://1234'"),
+    short_repeated_string("f('a', 'a', 'a')"),
+    small_repeated_number("return 1, 1, 1, 1"),
+    field_names("return a.value, b.value, c.value"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'deduplicate_constants',
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'deduplicate_constants'").unwrap();
+}
+
+fn assert_no_effects(code: &str) {
+    let mut block = crate::utils::parse_input(code);
+    let expected = block.clone();
+
+    DeduplicateConstants::default()
+        .process(&mut block, &mut darklua_core::rules::Context::default())
+        .expect("rule should suceed");
+
+    pretty_assertions::assert_eq!(block, expected);
+}
+
+#[test]
+fn does_not_exceed_locals_limit_with_nested_locals() {
+    let locals: String = (0..195).map(|i| format!("local v{} = {} ", i, i)).collect();
+
+    assert_no_effects(&format!(
+        "{}f('some-long-value', 'some-long-value') for i = 1, 2 do local z = i end",
+        locals
+    ));
+}
+
+#[test]
+fn does_not_exceed_upvalues_limit() {
+    let locals: String = (0..60).map(|i| format!("local v{} = {} ", i, i)).collect();
+    let upvalues: Vec<String> = (0..60).map(|i| format!("v{}", i)).collect();
+
+    assert_no_effects(&format!(
+        "{}local function f() return {}, 'some-long-value', 'some-long-value' end",
+        locals,
+        upvalues.join(", ")
+    ));
+}
@@ -0,0 +1,86 @@
+use darklua_core::rules::{EliminateCommonSubexpressions, Rule};
+
+test_rule!(
+    eliminate_common_subexpressions,
+    EliminateCommonSubexpressions::default().with_pure_metamethods(true),
+    repeated_field_prefix("return a.b.c.x + a.b.c.y + a.b.c.z")
+        => "local c = a.b.c return c.x + c.y + c.z",
+    repeated_binary_expression("print(a * b, a * b)")
+        => "local value = a * b print(value, value)",
+    repeated_across_statements("local x = a.b + 1 local y = a.b + 2")
+        => "local b = a.b local x = b + 1 local y = b + 2",
+    repeated_in_return("local x = t.size * 2 return t.size * 2, x")
+        => "local value = t.size * 2 local x = value return value, x",
+    repeated_string_index("print(t['key'], t['key'])")
+        => "local key = t['key'] print(key, key)",
+    repeated_in_call_arguments("print(a.b, a.b)") => "local b = a.b print(b, b)",
+    repeated_before_call_in_statement("local x = t.v + 1 local y = t.v + 1 modify(t)")
+        => "local value = t.v + 1 local x = value local y = value modify(t)",
+    stops_before_call_in_statement("print(t.v, t.v, modify(t), t.v)")
+        => "local v = t.v print(v, v, modify(t), t.v)",
+    local_read_across_call("local a = 1 print(a + 1) update() print(a + 1)")
+        => "local a = 1 local value = a + 1 print(value) update() print(value)",
+    largest_expression_first("print(a.b.c, a.b.c, a.b.d)")
+        => "local b = a.b local c = b.c print(c, c, b.d)",
+    name_already_used("local value = 1 print(a + b, a + b)")
+        => "local value = 1 local value1 = a + b print(value1, value1)",
+    repeated_in_if_condition("local x = a.b if a.b then end")
+        => "local b = a.b local x = b if b then end",
+    repeated_in_nested_block("do print(a + b, a + b) end")
+        => "do local value = a + b print(value, value) end",
+    stops_before_assignment("local x = a + b local y = a + b a = 1 local z = a + b")
+        => "local value = a + b local x = value local y = value a = 1 local z = a + b",
+    stops_before_field_assignment("print(t.x, t.x) t.x = 1 print(t.x)")
+        => "local x = t.x print(x, x) t.x = 1 print(t.x)",
+);
+
+test_rule_wihout_effects!(
+    EliminateCommonSubexpressions::default().with_pure_metamethods(true),
+    single_occurrence("return a.b"),
+    repeated_identifier("print(a, a)"),
+    repeated_constant("print(1 + 2, 1 + 2)"),
+    repeated_call("print(f(), f())"),
+    repeated_call_argument("print(f(a) + 1, f(a) + 1)"),
+    separated_by_assignment("print(a + b) a = 2 print(a + b)"),
+    separated_by_field_assignment("print(t.x) t.x = 2 print(t.x)"),
+    separated_by_local_declaration("print(a + b) local a = 2 print(a + b)"),
+    local_table_field_separated_by_call("local t = {} print(t.x) update() print(t.x)"),
+    field_separated_by_call("local x = t.v + 1; modify(t); local y = t.v + 1"),
+    global_field_separated_by_call("print(math.pi * 2) print(math.pi * 2)"),
+    global_separated_by_call("local x = a + b update() local y = a + b"),
+    separated_by_call_in_and_operand("local x = t.v + (a and f() or 1) local y = t.v"),
+    first_occurrence_after_call("print(f(), t.v, t.v)"),
+    right_operand_of_and("print(a.b and c.d, c.d)"),
+    while_condition("local x = a.b while a.b do end"),
+    inside_function("local x = a.b return function() return a.b end"),
+    with_variadic_arguments("print(select(1, ...) + 1, select(1, ...) + 1)"),
+);
+
+test_rule_wihout_effects!(
+    EliminateCommonSubexpressions::default(),
+    field_without_pure_metamethods("print(a.b, a.b)"),
+    binary_without_pure_metamethods("print(a + b, a + b)"),
+);
+
+test_rule!(
+    eliminate_common_subexpressions_without_pure_metamethods,
+    EliminateCommonSubexpressions::default(),
+    logical_operators("print(a and not b, a and not b)")
+        => "local value = a and not b print(value, value)",
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'eliminate_common_subexpressions',
+        assume_pure_metamethods: true,
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'eliminate_common_subexpressions'").unwrap();
+}
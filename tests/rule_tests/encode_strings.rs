@@ -0,0 +1,163 @@
+use darklua_core::rules::Rule;
+
+test_rule!(
+    encode_strings,
+    json5::from_str::<Box<dyn Rule>>("'encode_strings'").unwrap(),
+    return_string("return 'hi'")
+        => "local __DARKLUA_CHAR = string.char return __DARKLUA_CHAR(104, 105)",
+    local_assign_string("local name = 'hi'")
+        => "local __DARKLUA_CHAR = string.char local name = __DARKLUA_CHAR(104, 105)",
+    string_with_escapes("return 'a\\n\\0'")
+        => "local __DARKLUA_CHAR = string.char return __DARKLUA_CHAR(97, 10, 0)",
+    string_argument_call("print 'hi'")
+        => "local __DARKLUA_CHAR = string.char print(__DARKLUA_CHAR(104, 105))",
+    string_argument_method_call("object:method 'hi'")
+        => "local __DARKLUA_CHAR = string.char object:method(__DARKLUA_CHAR(104, 105))",
+    index_with_non_identifier_string("return value['a b']")
+        => "local __DARKLUA_CHAR = string.char return value[__DARKLUA_CHAR(97, 32, 98)]",
+    index_with_keyword_string("return value['end']")
+        => "local __DARKLUA_CHAR = string.char return value[__DARKLUA_CHAR(101, 110, 100)]",
+    table_field_value("return { name = 'hi' }")
+        => "local __DARKLUA_CHAR = string.char return { name = __DARKLUA_CHAR(104, 105) }",
+    table_entry_value_with_field_key("return { ['name'] = 'hi' }")
+        => "local __DARKLUA_CHAR = string.char return { ['name'] = __DARKLUA_CHAR(104, 105) }",
+    table_entry_with_non_identifier_key("return { ['a-b'] = true }")
+        => "local __DARKLUA_CHAR = string.char return { [__DARKLUA_CHAR(97, 45, 98)] = true }",
+    table_array_value("return { 'a' }")
+        => "local __DARKLUA_CHAR = string.char return { __DARKLUA_CHAR(97) }",
+    assign_to_index_with_non_identifier_string("value['a b'] = true")
+        => "local __DARKLUA_CHAR = string.char value[__DARKLUA_CHAR(97, 32, 98)] = true",
+    string_in_nested_function("local function run() return 'a' end")
+        => "local __DARKLUA_CHAR = string.char local function run() return __DARKLUA_CHAR(97) end",
+    parenthese_string_method_call("return ('a'):rep(2)")
+        => "local __DARKLUA_CHAR = string.char return (__DARKLUA_CHAR(97)):rep(2)",
+    require_call_with_string_expression("return require(value .. 'a')")
+        => "local __DARKLUA_CHAR = string.char return require(value .. __DARKLUA_CHAR(97))",
+    require_method_call("return loader:require('a')")
+        => "local __DARKLUA_CHAR = string.char return loader:require(__DARKLUA_CHAR(97))",
+    string_in_require_call_arguments("return require(script:FindFirstChild('a'))")
+        => "local __DARKLUA_CHAR = string.char return require(script:FindFirstChild(__DARKLUA_CHAR(97)))",
+);
+
+test_rule!(
+    encode_strings_xor,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'encode_strings',
+        method: 'xor',
+    }"#,
+    )
+    .unwrap(),
+    return_string("return 'hi'") => r#"
+local __DARKLUA_CHAR = string.char
+local __DARKLUA_CONCAT = table.concat
+local function __DARKLUA_DECODE_STRING(bytes, key)
+    local characters = {}
+    for i = 1, #bytes do
+        local a, b, value, bit = bytes[i], (key + i) % 256, 0, 1
+        while a > 0 or b > 0 do
+            if a % 2 ~= b % 2 then
+                value = value + bit
+            end
+            a, b, bit = (a - a % 2) / 2, (b - b % 2) / 2, bit * 2
+        end
+        characters[i] = __DARKLUA_CHAR(value)
+    end
+    return __DARKLUA_CONCAT(characters)
+end
+return __DARKLUA_DECODE_STRING({ 94, 94 }, 53)
+"#,
+    table_reassigned("table = nil return 'hi'") => r#"
+local __DARKLUA_CHAR = string.char
+local __DARKLUA_CONCAT = table.concat
+local function __DARKLUA_DECODE_STRING(bytes, key)
+    local characters = {}
+    for i = 1, #bytes do
+        local a, b, value, bit = bytes[i], (key + i) % 256, 0, 1
+        while a > 0 or b > 0 do
+            if a % 2 ~= b % 2 then
+                value = value + bit
+            end
+            a, b, bit = (a - a % 2) / 2, (b - b % 2) / 2, bit * 2
+        end
+        characters[i] = __DARKLUA_CHAR(value)
+    end
+    return __DARKLUA_CONCAT(characters)
+end
+table = nil
+return __DARKLUA_DECODE_STRING({ 94, 94 }, 53)
+"#,
+);
+
+test_rule!(
+    encode_strings_with_include_and_exclude,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'encode_strings',
+        include: ['Remote*'],
+        exclude: ['*Debug'],
+    }"#,
+    )
+    .unwrap(),
+    encode_included_string("return 'Remote', 'Other', 'RemoteDebug'")
+        => "local __DARKLUA_CHAR = string.char return __DARKLUA_CHAR(82, 101, 109, 111, 116, 101), 'Other', 'RemoteDebug'",
+);
+
+test_rule_wihout_effects!(
+    json5::from_str::<Box<dyn Rule>>("'encode_strings'").unwrap(),
+    empty_string("return ''"),
+    index_with_identifier_string("return value['name']"),
+    assign_to_index_with_identifier_string("value['name'] = true"),
+    call_on_index_with_identifier_string("value['name']()"),
+    table_entry_with_identifier_key("return { ['name'] = true }"),
+    empty_string_argument("print ''"),
+    require_call_with_string("local module = require('./module')"),
+    require_call_with_string_argument("local module = require './module'"),
+    no_strings("return 1 + value"),
+);
+
+test_rule_wihout_effects!(
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'encode_strings',
+        exclude: ['*'],
+    }"#,
+    )
+    .unwrap(),
+    excluded_string("return 'hello'"),
+    excluded_string_argument("print 'hello'"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'encode_strings',
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'encode_strings'").unwrap();
+}
+
+#[test]
+fn long_string_is_split_in_multiple_calls() {
+    use darklua_core::generator::{DenseLuaGenerator, LuaGenerator};
+
+    let rule = json5::from_str::<Box<dyn Rule>>("'encode_strings'").unwrap();
+    let mut block = crate::utils::parse_input(&format!("return '{}'", "a".repeat(150)));
+
+    rule.process(&mut block, &mut darklua_core::rules::Context::default())
+        .expect("rule should suceed");
+
+    let mut generator = DenseLuaGenerator::default();
+    generator.write_block(&block);
+    let code = generator.into_string();
+
+    pretty_assertions::assert_eq!(code.matches("__DARKLUA_CHAR(").count(), 2);
+    pretty_assertions::assert_eq!(code.matches("97").count(), 150);
+    assert!(code.contains(")..__DARKLUA_CHAR("));
+}
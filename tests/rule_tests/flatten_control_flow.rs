@@ -0,0 +1,104 @@
+use darklua_core::rules::{FlattenControlFlow, Rule};
+
+test_rule!(
+    flatten_control_flow,
+    FlattenControlFlow::default(),
+    flatten_local_function("local function run() a() b() end")
+        => "local function run() local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then a() __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then b() __DARKLUA_STATE = nil end end end",
+    flatten_with_return("function run() a() return b() end")
+        => "function run() local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then a() __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then return b() end end end",
+    hoist_local_variables("return function(a) local b = a + 1 local c return b, c end")
+        => "return function(a) local b, c local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then b = a + 1 __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then c = nil __DARKLUA_STATE = 3 \
+            elseif __DARKLUA_STATE == 3 then return b, c end end end",
+    hoist_local_function("local function run() local function f(n) return n > 0 and f(n - 1) end return f(3) end")
+        => "local function run() local f local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then f = function(n) return n > 0 and f(n - 1) end __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then return f(3) end end end",
+    avoid_state_name_conflict("local function run(__DARKLUA_STATE) a(__DARKLUA_STATE) b() end")
+        => "local function run(__DARKLUA_STATE) local __DARKLUA_STATE_2 = 1 while __DARKLUA_STATE_2 do \
+            if __DARKLUA_STATE_2 == 1 then a(__DARKLUA_STATE) __DARKLUA_STATE_2 = 2 \
+            elseif __DARKLUA_STATE_2 == 2 then b() __DARKLUA_STATE_2 = nil end end end",
+    flatten_nested_function("local function run() return function() a() b() end end")
+        => "local function run() return function() local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then a() __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then b() __DARKLUA_STATE = nil end end end end",
+);
+
+test_rule!(
+    flatten_control_flow_with_max_depth,
+    FlattenControlFlow::default().with_max_depth(2),
+    flatten_nested_blocks("local function run() if c then a() b() end end")
+        => "local function run() if c then local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then a() __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then b() __DARKLUA_STATE = nil end end end end",
+    nested_state_variables_are_distinct("local function run() a() do b() c() end end")
+        => "local function run() local __DARKLUA_STATE_2 = 1 while __DARKLUA_STATE_2 do \
+            if __DARKLUA_STATE_2 == 1 then a() __DARKLUA_STATE_2 = 2 \
+            elseif __DARKLUA_STATE_2 == 2 then \
+                do local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+                    if __DARKLUA_STATE == 1 then b() __DARKLUA_STATE = 2 \
+                    elseif __DARKLUA_STATE == 2 then c() __DARKLUA_STATE = nil end end end \
+                __DARKLUA_STATE_2 = nil end end end",
+);
+
+test_rule!(
+    flatten_control_flow_with_functions,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'flatten_control_flow',
+        functions: ['selected', 'Class:method'],
+    }"#,
+    )
+    .unwrap(),
+    flatten_selected_local_function("local function selected() a() b() end local function other() a() b() end")
+        => "local function selected() local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then a() __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then b() __DARKLUA_STATE = nil end end end \
+            local function other() a() b() end",
+    flatten_selected_method("function Class:method() a() b() end function Class.method() a() b() end")
+        => "function Class:method() local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then a() __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then b() __DARKLUA_STATE = nil end end end \
+            function Class.method() a() b() end",
+    flatten_selected_assigned_function("local selected = function() a() b() end")
+        => "local selected = function() local __DARKLUA_STATE = 1 while __DARKLUA_STATE do \
+            if __DARKLUA_STATE == 1 then a() __DARKLUA_STATE = 2 \
+            elseif __DARKLUA_STATE == 2 then b() __DARKLUA_STATE = nil end end end",
+);
+
+test_rule_wihout_effects!(
+    FlattenControlFlow::default(),
+    single_statement_function("local function run() return 1 end"),
+    top_level_block_is_not_flattened("a() b()"),
+    local_shadowing_upvalue("local x = 1 local function run() local y = x local x = 2 return x + y end"),
+    local_referenced_in_own_value("local function run() local value = value or 1 return value end"),
+    local_referenced_before_in_function("local function run() local function f() return g() end local function g() end return f end"),
+);
+
+test_rule_wihout_effects!(
+    FlattenControlFlow::default().with_max_depth(3),
+    loop_body_with_break("local function run() while true do if c then break end a() end end"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'flatten_control_flow',
+        functions: ['run'],
+        max_depth: 2,
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'flatten_control_flow'").unwrap();
+}
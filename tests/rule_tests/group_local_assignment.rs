@@ -7,13 +7,21 @@ test_rule!(
     two_locals("local foo = 1 local bar = 2") => "local foo, bar = 1, 2",
     three_locals("local foo = 1 local bar = 2 local baz = 3") => "local foo, bar, baz = 1, 2, 3",
     local_with_no_value_and_local_with_value("local a local b = 7") => "local a, b = nil, 7",
-    local_with_no_values_are_set_to_nil("local a local b = true local c") => "local a, b, c = nil, true, nil"
+    local_with_no_values_are_set_to_nil("local a local b = true local c") => "local a, b, c = nil, true, nil",
+    local_with_missing_value("local a, b = 1 local c = 2") => "local a, b, c = 1, nil, 2",
+    local_with_missing_value_after_field("local a, b = t.field local c = 2")
+        => "local a, b, c = t.field, nil, 2",
+    local_with_missing_value_after_parenthese_call("local a, b = (call()) local c = 2")
+        => "local a, b, c = (call()), nil, 2",
+    local_with_call_after_local("local a = 1 local b, c = call()") => "local a, b, c = 1, call()"
 );
 
 test_rule_wihout_effects!(
     GroupLocalAssignment::default(),
     two_local_using_the_other("local foo = 1 local bar = foo"),
-    multiple_return_values("local a, b = call() local c = 0")
+    multiple_return_values("local a, b = call() local c = 0"),
+    variadic_values("local a, b = ... local c = 0"),
+    extra_values("local a = 1, 2 local b = 3")
 );
 
 #[test]
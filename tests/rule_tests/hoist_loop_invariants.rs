@@ -0,0 +1,107 @@
+use darklua_core::rules::{HoistLoopInvariants, Rule};
+
+test_rule!(
+    hoist_loop_invariants,
+    HoistLoopInvariants::default().with_pure_metamethods(true),
+    numeric_for_binary_expression("for i = 1, 10 do t[i] = a * b end")
+        => "local value = a * b for i = 1, 10 do t[i] = value end",
+    numeric_for_global_field("for i = 1, 10 do x = math.pi * 2 end")
+        => "local value = math.pi * 2 for i = 1, 10 do x = value end",
+    numeric_for_field_with_parameter(
+        "return function(list, radius) for i = 1, 10 do list[i].Size = radius * 2 end end"
+    ) => "return function(list, radius) local value = radius * 2 for i = 1, 10 do list[i].Size = value end end",
+    numeric_for_with_negative_step("for i = 10, 1, -1 do x = a.b end")
+        => "local b = a.b for i = 10, 1, -1 do x = b end",
+    while_condition("while a.value > 0 do n = n + 1 end")
+        => "local value = a.value > 0 while value do n = n + 1 end",
+    repeat_condition("repeat local x = 1 until a.done")
+        => "local done = a.done repeat local x = 1 until done",
+    same_expression_used_twice("for i = 1, 10 do x, y = a + b, a + b end")
+        => "local value = a + b for i = 1, 10 do x, y = value, value end",
+    name_already_used("local value = 1 for i = 1, 10 do x = a + b end")
+        => "local value = 1 local value1 = a + b for i = 1, 10 do x = value1 end",
+    local_declared_before_loop("local a = 2 for i = 1, 10 do print(i * (a + 1)) end")
+        => "local a = 2 local value = a + 1 for i = 1, 10 do print(i * value) end",
+    string_index_name("for i = 1, 10 do x = t['key'] end")
+        => "local key = t['key'] for i = 1, 10 do x = key end",
+    left_operand_of_and("for i = 1, 10 do x = a.b and i end")
+        => "local b = a.b for i = 1, 10 do x = b and i end",
+    nested_loop_body("for i = 1, 10 do for j = 1, 10 do x = j + a.b end end")
+        => "for i = 1, 10 do local b = a.b for j = 1, 10 do x = j + b end end",
+    generic_for_value_that_can_not_fail("for _, v in list do x = a and b end")
+        => "local value = a and b for _, v in list do x = value end",
+    before_call_with_side_effects(
+        "return function(a) for i = 1, 10 do x = a + 1 print(i) end end"
+    ) => "return function(a) local value = a + 1 for i = 1, 10 do x = value print(i) end end",
+);
+
+test_rule!(
+    hoist_loop_invariants_with_impure_metamethods,
+    HoistLoopInvariants::default(),
+    value_that_can_not_call_metamethods("for _, v in list do x = a and b end")
+        => "local value = a and b for _, v in list do x = value end",
+);
+
+test_rule_wihout_effects!(
+    HoistLoopInvariants::default(),
+    binary_expression_with_impure_metamethods("for i = 1, 10 do t[i] = a * b end"),
+    field_with_impure_metamethods("for i = 1, 10 do x = a.b end"),
+);
+
+test_rule_wihout_effects!(
+    HoistLoopInvariants::default().with_pure_metamethods(true),
+    uses_loop_variable("for i = 1, 10 do print(i * 2) end"),
+    uses_local_declared_in_loop("for i = 1, 10 do local a = i print(a * 2) end"),
+    variable_assigned_in_loop("for i = 1, 10 do print(a * 2) a = i end"),
+    variable_assigned_with_compound_assignment("while true do print(a * 2) a += 1 end"),
+    field_assigned_in_loop("for i = 1, 10 do print(t.count + 1) t.count = i end"),
+    local_table_read_in_loop_with_call("local t = {} for i = 1, 10 do update() print(t.value) end"),
+    variable_assigned_elsewhere_and_call_in_loop(
+        "local a = 1 function set() a = 2 end for i = 1, 10 do set() print(a * 2) end"
+    ),
+    constant_expression("for i = 1, 10 do print(1 + 2) end"),
+    single_identifier("for i = 1, 10 do print(a) end"),
+    function_call("for i = 1, 10 do print(getValue()) end"),
+    binary_with_function_call("for i = 1, 10 do print(getValue() + 1) end"),
+    after_conditional_exit("for i = 1, 10 do if i > 5 then break end print(a + b) end"),
+    inside_if_block("for i = 1, 10 do if i > 5 then print(a + b) end end"),
+    right_operand_of_or("for i = 1, 10 do print(i or a.b) end"),
+    inside_function("for i = 1, 10 do connect(function() print(a + b) end) end"),
+    variadic_arguments("for i = 1, 10 do print(select('#', ...) + 1) end"),
+    global_read_with_call_in_loop("for i = 1, 3 do step() print(counter + 1) end"),
+    table_read_with_call_in_loop(
+        "for i = 1, 3 do table.insert(Items, i) print(#Items, Items[1]) end"
+    ),
+    global_field_with_call_in_loop("for i = 1, 10 do print(math.pi * 2) end"),
+    field_prefix_of_call("for i = 1, 10 do game.Workspace.Parts:Clear(i) end"),
+    function_call_prefix("for i = 1, 10 do print(Vector3.new(1, 2, 3)) end"),
+    numeric_for_that_never_runs("for i = 1, 0 do x = y .. 'z' end"),
+    numeric_for_with_unknown_end("for i = 1, n do x = y .. 'z' end"),
+    generic_for_body("for _, v in list do x = y .. 'z' end"),
+    generic_for_field_with_parameter(
+        "return function(radius) for _, v in list do v.Size = radius * 2 end end"
+    ),
+    while_body("while running do x = a.b end"),
+    after_call_with_side_effects("return function(a) for i = 1, 10 do print(i) x = a + 1 end end"),
+    after_assignment("return function(a) for i = 1, 10 do x = i y = a + 1 end end"),
+    after_call_in_same_statement("return function(a) for i = 1, 10 do print(f(i), a + 1) end end"),
+    after_nested_loop(
+        "return function(a) for i = 1, 10 do for j = 1, 2 do x = j end y = a + 1 end end"
+    ),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'hoist_loop_invariants',
+        assume_pure_metamethods: true,
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'hoist_loop_invariants'").unwrap();
+}
@@ -46,6 +46,36 @@ test_rule!(
     inject_negative_integer_from_global_table("return _G.foo + 1") => "return -1 + 1",
 );
 
+test_rule!(
+    inject_global_table,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inject_global_value',
+        identifier: 'FLAGS',
+        value: { newShop: true, 'beta-users': ['a', 'b'], limits: { count: 3, ratio: 0.5 } },
+    }"#,
+    )
+    .unwrap(),
+    inject_table("return FLAGS")
+        => "return { ['beta-users'] = { 'a', 'b' }, limits = { count = 3, ratio = 0.5 }, newShop = true }",
+    inject_table_field("if FLAGS.newShop then end")
+        => "if ({ ['beta-users'] = { 'a', 'b' }, limits = { count = 3, ratio = 0.5 }, newShop = true }).newShop then end",
+);
+
+test_rule!(
+    inject_global_list,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inject_global_value',
+        identifier: 'VALUES',
+        value: [1, 'two', false, null],
+    }"#,
+    )
+    .unwrap(),
+    inject_list("return VALUES") => "return { 1, 'two', false, nil }",
+    inject_list_from_global_table("return _G.VALUES") => "return { 1, 'two', false, nil }",
+);
+
 test_rule_wihout_effects!(
     InjectGlobalValue::nil("foo"),
     does_not_override_local_variable("local foo return foo"),
@@ -134,3 +164,294 @@ fn deserialize_from_object_notation_with_float_value() {
     )
     .unwrap();
 }
+
+#[test]
+fn deserialize_from_object_notation_with_object_value() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inject_global_value',
+        identifier: 'foo',
+        value: { enabled: true },
+    }"#,
+    )
+    .unwrap();
+}
+
+fn inject_file(path: &str) -> Box<dyn Rule> {
+    json5::from_str::<Box<dyn Rule>>(&format!(
+        "{{ rule: 'inject_global_value', identifier: 'BUILD', file: '{}' }}",
+        path
+    ))
+    .unwrap()
+}
+
+#[test]
+fn inject_value_from_data_file_relative_to_configuration() {
+    let rule = inject_file("build.json");
+    let data_block = crate::utils::parse_input("return { debug = false, version = '1.2.0' }");
+
+    let mut context_builder = darklua_core::rules::ContextBuilder::new("src/main.lua")
+        .with_configuration_location(std::path::Path::new("config"));
+    context_builder.insert_block("config/build.json", &data_block);
+    let mut context = context_builder.build();
+
+    let mut block = crate::utils::parse_input("return BUILD.version");
+    rule.process(&mut block, &mut context)
+        .expect("rule should succeed");
+
+    pretty_assertions::assert_eq!(
+        block,
+        crate::utils::parse_input("return ({ debug = false, version = '1.2.0' }).version")
+    );
+}
+
+#[test]
+fn require_content_of_file_is_relative_to_configuration() {
+    let rule = inject_file("../data/build.toml");
+    let context = darklua_core::rules::ContextBuilder::new("src/main.lua")
+        .with_configuration_location(std::path::Path::new("config"))
+        .build();
+
+    pretty_assertions::assert_eq!(
+        rule.require_content(&context, &crate::utils::parse_input("")),
+        vec![std::path::PathBuf::from("data/build.toml")]
+    );
+}
+
+#[test]
+fn process_without_file_content_should_error() {
+    let rule = inject_file("does-not-exist.json");
+
+    let mut block = crate::utils::parse_input("return BUILD");
+    let result = rule.process(&mut block, &mut darklua_core::rules::Context::default());
+
+    pretty_assertions::assert_eq!(
+        result,
+        Err("unable to read file `does-not-exist.json`".to_owned())
+    );
+}
+
+#[test]
+fn deserialize_with_file_that_is_not_a_data_file_should_error() {
+    let result = json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inject_global_value',
+        identifier: 'foo',
+        file: 'build.lua',
+    }"#,
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn deserialize_with_value_and_file_should_error() {
+    let result = json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inject_global_value',
+        identifier: 'foo',
+        value: true,
+        file: 'build.json',
+    }"#,
+    );
+
+    assert!(result.is_err());
+}
+
+fn inject_env(variable: &str, value: Option<&str>, options: &str) -> Result<Box<dyn Rule>, String> {
+    match value {
+        Some(value) => std::env::set_var(variable, value),
+        None => std::env::remove_var(variable),
+    }
+
+    json5::from_str::<Box<dyn Rule>>(&format!(
+        "{{ rule: 'inject_global_value', identifier: 'VALUE', env: '{}', {} }}",
+        variable, options
+    ))
+    .map_err(|err| err.to_string())
+}
+
+fn assert_env_injection(variable: &str, value: Option<&str>, options: &str, expected: &str) {
+    let rule = inject_env(variable, value, options).expect("rule should be valid");
+
+    let mut block = crate::utils::parse_input("return VALUE");
+    rule.process(&mut block, &mut darklua_core::rules::Context::default())
+        .expect("rule should succeed");
+
+    pretty_assertions::assert_eq!(
+        block,
+        crate::utils::parse_input(&format!("return {}", expected))
+    );
+}
+
+#[test]
+fn inject_env_without_type_as_string() {
+    assert_env_injection("DARKLUA_TEST_ENV_STRING", Some("false"), "", "'false'");
+}
+
+#[test]
+fn inject_env_as_boolean() {
+    assert_env_injection(
+        "DARKLUA_TEST_ENV_BOOLEAN",
+        Some("false"),
+        "type: 'boolean'",
+        "false",
+    );
+}
+
+#[test]
+fn inject_env_as_boolean_from_digit() {
+    assert_env_injection(
+        "DARKLUA_TEST_ENV_BOOLEAN_DIGIT",
+        Some("1"),
+        "type: 'boolean'",
+        "true",
+    );
+}
+
+#[test]
+fn inject_env_as_number() {
+    assert_env_injection(
+        "DARKLUA_TEST_ENV_NUMBER",
+        Some(" 12.5 "),
+        "type: 'number'",
+        "12.5",
+    );
+}
+
+#[test]
+fn inject_env_as_json() {
+    assert_env_injection(
+        "DARKLUA_TEST_ENV_JSON",
+        Some(r#"{ "debug": true, "levels": [1, 2] }"#),
+        "type: 'json'",
+        "{ debug = true, levels = { 1, 2 } }",
+    );
+}
+
+#[test]
+fn inject_default_when_env_is_not_defined() {
+    assert_env_injection(
+        "DARKLUA_TEST_ENV_UNDEFINED_WITH_DEFAULT",
+        None,
+        "type: 'boolean', default: true",
+        "true",
+    );
+}
+
+#[test]
+fn inject_env_instead_of_default_when_defined() {
+    assert_env_injection(
+        "DARKLUA_TEST_ENV_DEFINED_WITH_DEFAULT",
+        Some("0"),
+        "type: 'number', default: 8",
+        "0",
+    );
+}
+
+#[test]
+fn inject_nil_when_env_is_not_defined_without_default() {
+    assert_env_injection("DARKLUA_TEST_ENV_UNDEFINED", None, "type: 'number'", "nil");
+}
+
+#[test]
+fn invalid_boolean_env_should_error() {
+    let err_message = match inject_env(
+        "DARKLUA_TEST_ENV_INVALID_BOOLEAN",
+        Some("yes"),
+        "type: 'boolean'",
+    ) {
+        Ok(_) => panic!("expected error when deserializing rule"),
+        Err(err) => err,
+    };
+
+    pretty_assertions::assert_eq!(
+        err_message,
+        "unexpected value for field 'env': unable to parse the `DARKLUA_TEST_ENV_INVALID_BOOLEAN` \
+        environment variable: expected `true` or `false` but got `yes`"
+    );
+}
+
+#[test]
+fn invalid_number_env_should_error() {
+    assert!(inject_env(
+        "DARKLUA_TEST_ENV_INVALID_NUMBER",
+        Some("1O"),
+        "type: 'number'"
+    )
+    .is_err());
+}
+
+#[test]
+fn invalid_json_env_should_error() {
+    assert!(inject_env(
+        "DARKLUA_TEST_ENV_INVALID_JSON",
+        Some("{ a: "),
+        "type: 'json'"
+    )
+    .is_err());
+}
+
+#[test]
+fn deserialize_with_default_not_matching_type_should_error() {
+    let err_message = match inject_env(
+        "DARKLUA_TEST_ENV_DEFAULT_NOT_MATCHING_TYPE",
+        None,
+        "type: 'number', default: 'eight'",
+    ) {
+        Ok(_) => panic!("expected error when deserializing rule"),
+        Err(err) => err,
+    };
+
+    pretty_assertions::assert_eq!(
+        err_message,
+        "unexpected value for field 'default': expected a value of type `number`"
+    );
+}
+
+#[test]
+fn deserialize_with_default_of_any_json_type() {
+    assert_env_injection(
+        "DARKLUA_TEST_ENV_UNDEFINED_WITH_JSON_DEFAULT",
+        None,
+        "type: 'json', default: ['a']",
+        "{ 'a' }",
+    );
+}
+
+#[test]
+fn deserialize_with_invalid_type_should_error() {
+    assert!(inject_env(
+        "DARKLUA_TEST_ENV_INVALID_TYPE",
+        Some("1"),
+        "type: 'integer'"
+    )
+    .is_err());
+}
+
+#[test]
+fn deserialize_type_without_env_should_error() {
+    let result = json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inject_global_value',
+        identifier: 'foo',
+        value: true,
+        type: 'boolean',
+    }"#,
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn deserialize_default_without_env_should_error() {
+    let result = json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inject_global_value',
+        identifier: 'foo',
+        default: true,
+    }"#,
+    );
+
+    assert!(result.is_err());
+}
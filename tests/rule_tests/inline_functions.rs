@@ -0,0 +1,109 @@
+use darklua_core::rules::{InlineFunctions, Rule};
+
+test_rule!(
+    inline_functions,
+    InlineFunctions::default(),
+    constant_function("local function get() return 1 end return get()")
+        => "local function get() return 1 end return 1",
+    identity_function("local function id(value) return value end return id(true)")
+        => "local function id(value) return value end return true",
+    function_with_binary_expression("local function add(a, b) return a + b end return add(2, 1)")
+        => "local function add(a, b) return a + b end return 2 + 1",
+    function_with_binary_expression_and_local_argument(
+        "local x = ... local function add(a, b) return a + b end return add(x, 1)"
+    ) => "local x = ... local function add(a, b) return a + b end return x + 1",
+    function_using_parameter_twice("local function double(a) return a + a end return double(2)")
+        => "local function double(a) return a + a end return 2 + 2",
+    missing_arguments_are_nil("local function second(a, b) return b end return second(1)")
+        => "local function second(a, b) return b end return nil",
+    unused_extra_arguments("local function first(a) return a end return first(1, 2)")
+        => "local function first(a) return a end return 1",
+    call_used_in_statement("local function log(msg) return print(msg) end log('hello')")
+        => "local function log(msg) return print(msg) end print('hello')",
+    call_used_as_prefix("local function get(t) return t end return get(t).field")
+        => "local function get(t) return t end return t.field",
+    variadic_argument("local function first(a) return a end return function(...) return first(...) end")
+        => "local function first(a) return a end return function(...) return (...) end",
+    variadic_argument_in_middle("local function first(a) return a end return function(...) return first(..., 1) end")
+        => "local function first(a) return a end return function(...) return (...) end",
+    nested_calls("local function a() return 1 end local function b() return a() end return b()")
+        => "local function a() return 1 end local function b() return 1 end return 1",
+    function_returning_call("local function wrap(v) return call(v) end local x = wrap(1)")
+        => "local function wrap(v) return call(v) end local x = call(1)",
+    function_with_side_effects_and_stable_argument("local function wrap(v) return call(v) end local a = 1 return wrap(a)")
+        => "local function wrap(v) return call(v) end local a = 1 return call(a)",
+    function_using_upvalue("local n = 2 local function get() return n end return get()")
+        => "local n = 2 local function get() return n end return n",
+);
+
+test_rule_wihout_effects!(
+    InlineFunctions::default(),
+    function_with_statements("local function f(a) local b = a return b end return f(1)"),
+    function_with_early_return(
+        "local function f(a) if a then return 1 end return 2 end return f(x)"
+    ),
+    function_returning_multiple_values("local function f(a) return a, a end return f(1)"),
+    function_without_return("local function f(a) print(a) end f(1)"),
+    recursive_function("local function f(a) return f(a) end return f(1)"),
+    reassigned_function("local function f(a) return a end f = print return f(1)"),
+    function_using_variadic_arguments("local function f(...) return ... end return f(1)"),
+    function_returning_function("local function f() return function() end end return f()"),
+    argument_with_side_effects("local function f(a) return a end return f(call())"),
+    argument_with_function("local function f(a) return a end return f(function() end)"),
+    parameter_used_twice_with_complex_argument(
+        "local function f(a) return a * a end return f(b + c)"
+    ),
+    upvalue_shadowed_at_call_site(
+        "local n = 1 local function f() return n end local n = 2 return f()"
+    ),
+    unstable_argument_with_side_effects("local function f(a) return call(a) end return f(value)"),
+    reassigned_argument_with_side_effects(
+        "local function f(a) return call(a) end local v = 1 v = 2 return f(v)"
+    ),
+    variadic_argument_spread_over_parameters(
+        "local function f(a, b) return b end return function(...) return f(...) end"
+    ),
+    method_call("local function f(a) return a end return t:f(1)"),
+    global_function("function f(a) return a end return f(1)"),
+    function_larger_than_max_size(
+        "local function f(a) return a + a + a + a + a + a + a + a + a + a + a end return f(1)"
+    ),
+);
+
+test_rule!(
+    inline_functions_with_max_size,
+    InlineFunctions::default().with_max_size(3),
+    small_function("local function f(a) return a + 1 end return f(2)")
+        => "local function f(a) return a + 1 end return 2 + 1",
+);
+
+test_rule_wihout_effects!(
+    InlineFunctions::default().with_max_size(2),
+    function_larger_than_custom_max_size("local function f(a) return a + 1 end return f(x)"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inline_functions',
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'inline_functions'").unwrap();
+}
+
+#[test]
+fn deserialize_with_max_size() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inline_functions',
+        max_size: 5,
+    }"#,
+    )
+    .unwrap();
+}
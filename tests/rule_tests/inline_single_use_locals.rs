@@ -0,0 +1,86 @@
+use darklua_core::rules::{InlineSingleUseLocals, Rule};
+
+test_rule!(
+    inline_single_use_locals,
+    InlineSingleUseLocals::default(),
+    return_call_result("local result = compute() return result") => "return (compute())",
+    return_constant("local value = 1 return value") => "return 1",
+    call_argument("local message = format(value) print(message)") => "print((format(value)))",
+    first_call_argument_with_constant("local message = format(value) print(message, 1)")
+        => "print((format(value)), 1)",
+    binary_expression("local a = 1 local b = a + 2 return b") => "return 1 + 2",
+    local_in_if_condition("local enabled = isEnabled() if enabled then print('ok') end")
+        => "if (isEnabled()) then print('ok') end",
+    constant_used_in_loop("local step = 2 for i = 1, 10 do print(step) end")
+        => "for i = 1, 10 do print(2) end",
+    constant_used_in_function("local name = 'darklua' return function() return name end")
+        => "return function() return 'darklua' end",
+    constant_moved_across_statements("local value = true print('a') return value")
+        => "print('a') return true",
+    stable_local_moved_across_statements(
+        "return function(a) local b = a print('a') return b end"
+    ) => "return function(a) print('a') return a end",
+    chained_locals("local a = call() local b = a return b") => "return (call())",
+    value_shadowed_at_use("local a = 1 local b = a local a = 2 print('x') return b")
+        => "local b = 1 local a = 2 print('x') return b",
+    table_used_next("local t = { 1, 2 } return t") => "return { 1, 2 }",
+    function_used_next("local callback = function() end connect(callback)")
+        => "connect(function() end)",
+    variadic_value_used_next("local first = ... return first") => "return (...)",
+    field_of_local("local config = getConfig() return config.value") => "return (getConfig()).value",
+    method_call_on_local("local object = create() object:update()") => "(create()):update()",
+    use_after_constant_arguments("local value = call() return 1, 'a', value")
+        => "return 1, 'a', (call())",
+    use_after_stable_local("return function(a) local b = call() return a + b end")
+        => "return function(a) return a + (call()) end",
+    global_read_across_local("local a = counter local b = 1 print(a, b)")
+        => "print(counter, 1)",
+    global_read_used_next("local a = counter print(a)") => "print(counter)",
+    nested_block("do local value = call() return value end") => "do return (call()) end",
+    local_inside_function("return function() local value = call() return value end")
+        => "return function() return (call()) end",
+);
+
+test_rule_wihout_effects!(
+    InlineSingleUseLocals::default(),
+    used_twice("local value = call() print(value, value)"),
+    not_used("local value = call()"),
+    reassigned("local value = call() value = 1 return value"),
+    reassigned_with_compound_assignment("local value = 1 value += 1 return value"),
+    multiple_variables("local a, b = call() return a"),
+    side_effect_between_declaration_and_use("local value = call() print('a') return value"),
+    call_before_use("local value = call() return other(), value"),
+    global_read_before_use_is_assigned("other = 1 local value = call() return other .. value"),
+    conditional_use("local value = call() return condition and value"),
+    if_expression_branch_use("local value = call() return if condition then value else nil"),
+    use_in_loop_with_side_effects("local value = call() while true do print(value) end"),
+    table_used_in_loop("local t = {} for i = 1, 10 do print(t) end"),
+    function_used_in_function("local f = function() end return function() return f end"),
+    variadic_used_in_function("local a = ... return function() return a end"),
+    global_shadowed_at_use("local x = value local value = 1 return x"),
+    used_in_repeat_condition("repeat local done = call() until done"),
+    used_as_function_name("local module = {} function module.run() end"),
+    field_read_before_use("local value = call() return t.field + value"),
+    unstable_local_moved_across_statements("return function(a) local b = a a = nil return b end"),
+    global_read_across_call("local a = counter; bump(); print(a)"),
+    global_read_across_method_call("local a = counter object:bump() print(a)"),
+    global_read_across_field_assignment("local a = counter t.value = 1 print(a)"),
+    global_read_across_loop("local a = counter while running do step() end print(a)"),
+    global_read_in_loop("local a = counter for i = 1, 3 do bump() print(a) end"),
+    global_read_in_function("local a = counter return function() return a end"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'inline_single_use_locals',
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'inline_single_use_locals'").unwrap();
+}
@@ -0,0 +1,116 @@
+use darklua_core::rules::{LocalizeGlobals, Rule};
+
+test_rule!(
+    localize_globals,
+    LocalizeGlobals::default(),
+    global_function("print(1) print(2) print(3)")
+        => "local print = print print(1) print(2) print(3)",
+    library_field("local a = math.floor(x) local b = math.floor(y) return math.floor(z)")
+        => "local floor = math.floor local a = floor(x) local b = floor(y) return floor(z)",
+    library_field_and_library(
+        "local a = string.format(x) local b = string.format(y) local c = string.format(z) \
+        return string.rep, string.len, string.sub"
+    ) => "local string = string local format = string.format \
+        local a = format(x) local b = format(y) local c = format(z) \
+        return string.rep, string.len, string.sub",
+    field_name_already_used(
+        "local floor = 1 local a = math.floor(x) local b = math.floor(y) return math.floor(z)"
+    ) => "local floor1 = math.floor local floor = 1 local a = floor1(x) local b = floor1(y) \
+        return floor1(z)",
+    uses_inside_functions(
+        "local function a() return type(x) end local function b() return type(y) end \
+        return type(z)"
+    ) => "local type = type local function a() return type(x) end \
+        local function b() return type(y) end return type(z)",
+    multiple_globals("print(type(a)) print(type(b)) print(type(c))")
+        => "local print = print local type = type \
+        print(type(a)) print(type(b)) print(type(c))",
+    shadowed_global_not_counted(
+        "print(1) print(2) print(3) local function f(math) return math.abs(1) end"
+    ) => "local print = print print(1) print(2) print(3) \
+        local function f(math) return math.abs(1) end",
+);
+
+test_rule!(
+    localize_globals_with_min_uses,
+    LocalizeGlobals::default().with_min_uses(2),
+    used_twice("print(1) print(2)") => "local print = print print(1) print(2)",
+    library_field_used_twice("return math.pi, math.pi")
+        => "local pi = math.pi return pi, pi",
+);
+
+test_rule!(
+    localize_globals_with_roblox_globals,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'localize_globals',
+        globals: ['$default', '$roblox'],
+    }"#,
+    )
+    .unwrap(),
+    roblox_library_field(
+        "local a = Vector3.new(1, 0, 0) local b = Vector3.new(0, 1, 0) \
+        local c = Vector3.new(0, 0, 1)"
+    ) => "local new = Vector3.new local a = new(1, 0, 0) local b = new(0, 1, 0) \
+        local c = new(0, 0, 1)",
+    enum_item("f(Enum.KeyCode.W) f(Enum.KeyCode.W) f(Enum.KeyCode.W)")
+        => "local W = Enum.KeyCode.W f(W) f(W) f(W)",
+);
+
+test_rule!(
+    localize_globals_with_field_assignments,
+    LocalizeGlobals::default(),
+    library_field_assigned(
+        "local a = math.floor(x) local b = math.floor(y) local c = math.floor(z) math.x = 1"
+    ) => "local math = math local a = math.floor(x) local b = math.floor(y) \
+        local c = math.floor(z) math.x = 1",
+    library_field_assigned_with_function(
+        "local a = math.floor(x) local b = math.floor(y) local c = math.floor(z) \
+        function math.round() end"
+    ) => "local math = math local a = math.floor(x) local b = math.floor(y) \
+        local c = math.floor(z) function math.round() end",
+);
+
+test_rule_wihout_effects!(
+    LocalizeGlobals::default(),
+    not_used_enough("print(1) print(2)"),
+    global_assigned("print(1) print(2) print(3) print = nil"),
+    global_assigned_with_function("print(1) print(2) print(3) function print() end"),
+    shadowed_by_local("local print = f print(1) print(2) print(3)"),
+    unknown_global("foo(1) foo(2) foo(3)"),
+    require_calls("local a = require('a') local b = require('b') local c = require('c')"),
+    field_of_non_library_global("foo.bar(1) foo.bar(2) foo.bar(3)"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'localize_globals',
+        globals: ['$default', 'foo'],
+        min_uses: 5,
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'localize_globals'").unwrap();
+}
+
+#[test]
+fn does_not_exceed_locals_limit_with_nested_locals() {
+    let locals: String = (0..195).map(|i| format!("local v{} = {} ", i, i)).collect();
+    let mut block = crate::utils::parse_input(&format!(
+        "{}print(1) print(2) print(3) for i = 1, 2 do local z = i end",
+        locals
+    ));
+    let expected = block.clone();
+
+    LocalizeGlobals::default()
+        .process(&mut block, &mut darklua_core::rules::Context::default())
+        .expect("rule should suceed");
+
+    pretty_assertions::assert_eq!(block, expected);
+}
@@ -145,16 +145,28 @@ macro_rules! test_rule_wihout_effects {
 
 mod compute_expression;
 mod convert_index_to_field;
+mod deduplicate_constants;
+mod eliminate_common_subexpressions;
+mod encode_strings;
 mod filter_early_return;
+mod flatten_control_flow;
 mod group_local_assignment;
+mod hoist_loop_invariants;
 mod inject_value;
+mod inline_functions;
+mod inline_single_use_locals;
+mod localize_globals;
 mod no_local_function;
+mod obfuscate_numbers;
 mod remove_call_parens;
+mod remove_calls;
 mod remove_comments;
 mod remove_compound_assignment;
 mod remove_empty_do;
 mod remove_method_definition;
 mod remove_nil_declaration;
+mod remove_unused_declarations;
 mod remove_unused_if_branch;
 mod remove_unused_while;
+mod rename_fields;
 mod rename_variables;
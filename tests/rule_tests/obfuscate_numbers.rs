@@ -0,0 +1,94 @@
+use darklua_core::rules::Rule;
+
+test_rule!(
+    obfuscate_numbers,
+    json5::from_str::<Box<dyn Rule>>("'obfuscate_numbers'").unwrap(),
+    return_integer("return 250") => "return 5951 - 5701",
+    return_hex_integer("return 0x10") => "return 5717 - 5701",
+    local_assign_float("local price = 250 local ratio = 0.75")
+        => "local price = 5951 - 5701 local ratio = 2445.75 - 2445",
+    table_values("local values = { 1, 2, 3 }")
+        => "local values = { 5702 - 5701, 2447 - 2445, 2094 - 2091 }",
+);
+
+test_rule!(
+    obfuscate_numbers_with_seed,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'obfuscate_numbers',
+        seed: 7,
+    }"#,
+    )
+    .unwrap(),
+    local_assign_float("local price = 250 local ratio = 0.75")
+        => "local price = 25 + 225 local ratio = 0 + 0.75",
+);
+
+test_rule_wihout_effects!(
+    json5::from_str::<Box<dyn Rule>>("'obfuscate_numbers'").unwrap(),
+    large_number("return 1e300"),
+    no_numbers("return value, 'text', true"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'obfuscate_numbers',
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'obfuscate_numbers'").unwrap();
+}
+
+#[test]
+fn obfuscated_expressions_keep_their_value() {
+    use darklua_core::{
+        generator::{DenseLuaGenerator, LuaGenerator},
+        nodes::LastStatement,
+        process::{Evaluator, LuaValue},
+    };
+
+    let code = "return 2 * 3 + 4, -7.5, 2 ^ 10 % 9, 0.1 + 0.2";
+
+    let expected_values: Vec<LuaValue> = match crate::utils::parse_input(code).get_last_statement()
+    {
+        Some(LastStatement::Return(statement)) => statement
+            .iter_expressions()
+            .map(|expression| Evaluator::default().evaluate(expression))
+            .collect(),
+        _ => unreachable!(),
+    };
+
+    for seed in 0..20 {
+        let rule = json5::from_str::<Box<dyn Rule>>(&format!(
+            "{{ rule: 'obfuscate_numbers', seed: {} }}",
+            seed
+        ))
+        .unwrap();
+
+        let mut block = crate::utils::parse_input(code);
+        rule.process(&mut block, &mut darklua_core::rules::Context::default())
+            .expect("rule should succeed");
+
+        let mut generator = DenseLuaGenerator::default();
+        generator.write_block(&block);
+        let generated_code = generator.into_string();
+
+        let values: Vec<LuaValue> =
+            match crate::utils::parse_input(&generated_code).get_last_statement() {
+                Some(LastStatement::Return(statement)) => statement
+                    .iter_expressions()
+                    .map(|expression| Evaluator::default().evaluate(expression))
+                    .collect(),
+                _ => unreachable!(),
+            };
+
+        assert_ne!(generated_code, code);
+        pretty_assertions::assert_eq!(values, expected_values, "code: {}", generated_code);
+    }
+}
@@ -0,0 +1,69 @@
+use darklua_core::rules::Rule;
+
+test_rule!(
+    remove_calls,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'remove_calls',
+        functions: ['print', 'warn', 'self.logger:trace', 'Logger:debug', 'debug.*'],
+    }"#,
+    )
+    .unwrap(),
+    remove_print("print('hello') return 1") => "return 1",
+    remove_string_call("print 'hello' return 1") => "return 1",
+    remove_method_call("self.logger:trace('value', value) return 1") => "return 1",
+    remove_module_method_call("Logger:debug('value') return 1") => "return 1",
+    remove_wildcard_field_call("debug.profilebegin('label') return 1") => "return 1",
+    remove_wildcard_nested_call("debug.profile.begin('label') return 1") => "return 1",
+    remove_in_nested_block("if condition then warn('oops') end")
+        => "if condition then end",
+    remove_in_function("local function run() print('run') return true end")
+        => "local function run() return true end",
+    keep_side_effect_argument_call("print(compute())") => "compute()",
+    keep_side_effect_arguments_in_order("print(a(), 'x', b())") => "a() b()",
+    keep_side_effect_parenthese_argument("print((compute()))") => "compute()",
+    keep_side_effect_argument_without_local("print(object.field) local _ = 1")
+        => "if object.field then end local _ = 1",
+    keep_arguments_of_nested_removed_calls("print(warn(compute()))") => "compute()",
+    replace_expression_call_with_nil("local value = print('hello')") => "local value = nil",
+    remove_returned_call("return print('hello')") => "return",
+    remove_last_returned_call("return true, print('hello')") => "return true",
+    replace_returned_call_before_last("return print('hello'), true") => "return nil, true",
+    remove_call_after_local_scope("do local print = log end print('hello') return 1")
+        => "do local print = log end return 1",
+    replace_call_in_argument_with_nil("call(print('hello'), true)") => "call(nil, true)",
+    remove_call_in_last_argument("call(true, print('hello'))") => "call(true)",
+    remove_call_in_only_argument("call(print('hello'))") => "call()",
+    remove_call_in_last_argument_after_call("call(f(), print('hello'))") => "call((f()))",
+    remove_call_in_last_argument_after_variable_arguments("call(..., print('hello'))")
+        => "call((...))",
+    remove_returned_call_after_call("return f(), print('hello')") => "return (f())",
+    remove_returned_call_after_variable_arguments("return ..., print('hello')") => "return (...)",
+);
+
+test_rule_wihout_effects!(
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'remove_calls',
+        functions: ['print', 'Logger:debug', 'debug.*'],
+    }"#,
+    )
+    .unwrap(),
+    other_function_call("printf('hello')"),
+    field_call_of_method_pattern("Logger.debug('value')"),
+    wildcard_root_call("debug('value')"),
+    call_on_parenthese_prefix("(print)('hello')"),
+    expression_call_with_side_effect_argument("local value = print(compute())"),
+    local_shadowing_function("local print = myPrint print('hello')"),
+    local_function_shadowing_function("local function print() end print('hello')"),
+    parameter_shadowing_function("local function run(print) print('hello') end"),
+    local_shadowing_in_nested_block("local print = myPrint do print('hello') end"),
+    local_shadowing_expression_call("local print = myPrint local value = print('hello')"),
+    local_shadowing_field_call("local debug = {} debug.profilebegin('label')"),
+    for_variable_shadowing_function("for _, print in list do print('hello') end"),
+);
+
+test_rule_wihout_effects!(
+    json5::from_str::<Box<dyn Rule>>("'remove_calls'").unwrap(),
+    no_configured_functions("print('hello')"),
+);
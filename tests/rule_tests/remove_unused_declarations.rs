@@ -0,0 +1,60 @@
+use darklua_core::rules::{RemoveUnusedDeclarations, Rule};
+
+test_rule!(
+    remove_unused_declarations,
+    RemoveUnusedDeclarations::default(),
+    unused_local_function("local function helper() end return 1") => "return 1",
+    unused_local_constant("local value = 1 return 2") => "return 2",
+    unused_local_without_value("local value return 2") => "return 2",
+    unused_local_function_expression("local helper = function() end print('ok')")
+        => "print('ok')",
+    function_used_by_unused_function(
+        "local function a() end local function b() return a() end return 1"
+    ) => "return 1",
+    keeps_function_used_in_return(
+        "local function a() end local function b() return a() end return b"
+    ) => "local function a() end local function b() return a() end return b",
+    keeps_function_used_in_call(
+        "local function unused() end local function used() end used()"
+    ) => "local function used() end used()",
+    keeps_module_table(
+        "local module = {} local function helper() end function module.run() end return module"
+    ) => "local module = {} function module.run() end return module",
+    shadowed_declaration("local a = 1 local a = 2 return a") => "local a = 2 return a",
+    unused_recursive_function("local function loop() return loop() end return 1") => "return 1",
+    keeps_local_assigned_later(
+        "local a local function b() return a() end a = nil return 1"
+    ) => "local a a = nil return 1",
+    keeps_local_used_in_nested_function(
+        "local value = 1 local unused = 2 return function() return value end"
+    ) => "local value = 1 return function() return value end",
+    nested_local_with_same_name(
+        "local value = 1 return function() local value = 2 return value end"
+    ) => "return function() local value = 2 return value end",
+);
+
+test_rule_wihout_effects!(
+    RemoveUnusedDeclarations::default(),
+    local_with_side_effects("local value = call()"),
+    local_with_field_access("local players = game.Players"),
+    local_used_in_if_statement("local value = 1 if value then print(value) end"),
+    local_assigned_later("local value value = 1"),
+    nested_unused_local("do local value = 1 end"),
+    unused_local_in_function("return function() local value = 1 end"),
+    multiple_values_with_call("local a, b = 1, call()"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'remove_unused_declarations',
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'remove_unused_declarations'").unwrap();
+}
@@ -0,0 +1,193 @@
+use darklua_core::rules::Rule;
+
+test_rule!(
+    rename_fields,
+    json5::from_str::<Box<dyn Rule>>("'rename_fields'").unwrap(),
+    field_expression("return self._internalState") => "return self._a",
+    same_field_gets_same_name("self._state = self._state + 1") => "self._a = self._a + 1",
+    different_fields("return self._first, self._second") => "return self._a, self._b",
+    assign_to_field("object._value = true") => "object._a = true",
+    index_with_string("return self['_state']") => "return self['_a']",
+    assign_to_index_with_string("self['_state'] = true") => "self['_a'] = true",
+    table_field_key("return { _state = 1 }") => "return { _a = 1 }",
+    table_string_key("return { ['_state'] = 1 }") => "return { ['_a'] = 1 }",
+    method_call("self:_update()") => "self:_a()",
+    method_definition("function Class:_update() end") => "function Class:_a() end",
+    function_field_definition("function Class._helper() end") => "function Class._a() end",
+    nested_function_fields("function Class._private.helper() end")
+        => "function Class._a.helper() end",
+    field_prefix_call("self._logger.info('message')") => "self._a.info('message')",
+    field_and_table_key_match("local object = { _count = 0 } object._count = object._count + 1")
+        => "local object = { _a = 0 } object._a = object._a + 1",
+    fields_with_generated_names("return object._b, object._a")
+        => "return object._a, object._b",
+);
+
+test_rule!(
+    rename_fields_with_list,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_fields',
+        prefix: '',
+        fields: ['internalState', 'update'],
+    }"#,
+    )
+    .unwrap(),
+    listed_field("return self.internalState, self.other") => "return self._1sH0Ah, self.other",
+    listed_method("function Class:update() self:update() end")
+        => "function Class:_Yl8JgQ() self:_Yl8JgQ() end",
+    unlisted_field_with_generated_name("return self._a, self.update")
+        => "return self._a, self._Yl8JgQ",
+);
+
+test_rule!(
+    rename_fields_with_prefix,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_fields',
+        prefix: 'm_',
+        fields: ['internalState'],
+    }"#,
+    )
+    .unwrap(),
+    field_with_prefix("return self.m_health, self._a") => "return self.m_a, self._a",
+    listed_field_with_prefix("return self.internalState") => "return self.m_a",
+);
+
+test_rule!(
+    rename_fields_with_reserved,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_fields',
+        reserved: ['_public', '_a'],
+    }"#,
+    )
+    .unwrap(),
+    skip_reserved_name("return self._public, self._private") => "return self._public, self._b",
+);
+
+test_rule_wihout_effects!(
+    json5::from_str::<Box<dyn Rule>>("'rename_fields'").unwrap(),
+    field_without_prefix("return self.state"),
+    variable_with_prefix("local _value = 1 return _value"),
+    metamethods("local mt = { __index = {} } mt.__newindex = nil return mt['__call']"),
+    string_value("return '_state'"),
+    method_without_prefix("self:update()"),
+);
+
+test_rule_wihout_effects!(
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_fields',
+        prefix: '',
+    }"#,
+    )
+    .unwrap(),
+    empty_prefix("return self._state"),
+);
+
+#[test]
+fn deserialize_from_object_notation() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_fields',
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_from_string() {
+    json5::from_str::<Box<dyn Rule>>("'rename_fields'").unwrap();
+}
+
+#[test]
+fn fields_are_renamed_consistently_across_files() {
+    use darklua_core::rules::Context;
+
+    let rule = json5::from_str::<Box<dyn Rule>>("'rename_fields'").unwrap();
+
+    let mut first = crate::utils::parse_input("return { _state = 0, _count = 1 }");
+    let mut second = crate::utils::parse_input("return module._count, module._other");
+
+    rule.process(&mut first, &mut Context::default())
+        .expect("rule should succeed");
+    rule.process(&mut second, &mut Context::default())
+        .expect("rule should succeed");
+
+    pretty_assertions::assert_eq!(
+        first,
+        crate::utils::parse_input("return { _a = 0, _b = 1 }")
+    );
+    pretty_assertions::assert_eq!(
+        second,
+        crate::utils::parse_input("return module._b, module._c")
+    );
+}
+
+#[test]
+fn generated_names_do_not_conflict_with_fields_of_other_files() {
+    use darklua_core::rules::Context;
+
+    let rule = json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_fields',
+        prefix: 'm_',
+        fields: ['internalState'],
+    }"#,
+    )
+    .unwrap();
+
+    let mut first = crate::utils::parse_input("return self.m_health, self.internalState");
+    let mut second = crate::utils::parse_input("return self._a, self._b, self.m_health");
+
+    rule.process(&mut first, &mut Context::default())
+        .expect("rule should succeed");
+    rule.process(&mut second, &mut Context::default())
+        .expect("rule should succeed");
+
+    pretty_assertions::assert_eq!(
+        first,
+        crate::utils::parse_input("return self.m_a, self.m_b")
+    );
+    pretty_assertions::assert_eq!(
+        second,
+        crate::utils::parse_input("return self._a, self._b, self.m_a")
+    );
+}
+
+#[test]
+fn generated_names_with_empty_prefix_do_not_depend_on_file_order() {
+    use darklua_core::rules::Context;
+
+    let first_code = "return self.internalState";
+    let second_code = "return self._a, self.update, self.internalState";
+    let expected_first = crate::utils::parse_input("return self._1sH0Ah");
+    let expected_second = crate::utils::parse_input("return self._a, self._Yl8JgQ, self._1sH0Ah");
+
+    for reverse in [false, true] {
+        let rule = json5::from_str::<Box<dyn Rule>>(
+            r#"{
+            rule: 'rename_fields',
+            prefix: '',
+            fields: ['internalState', 'update'],
+        }"#,
+        )
+        .unwrap();
+
+        let mut first = crate::utils::parse_input(first_code);
+        let mut second = crate::utils::parse_input(second_code);
+
+        let mut blocks = vec![&mut first, &mut second];
+        if reverse {
+            blocks.reverse();
+        }
+        for block in blocks {
+            rule.process(block, &mut Context::default())
+                .expect("rule should succeed");
+        }
+
+        pretty_assertions::assert_eq!(first, expected_first);
+        pretty_assertions::assert_eq!(second, expected_second);
+    }
+}
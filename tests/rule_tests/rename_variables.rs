@@ -25,7 +25,7 @@ test_rule!(
     generic_for_identifiers("for key, value in t do return key end")
         => "for a, b in t do return a end",
     repeat_condition_is_from_block("local foo repeat local bar until bar")
-        => "local a repeat local b until b",
+        => "local b repeat local a until a",
     while_statement("local foo while foo do local foo end") => "local a while a do local b end",
     if_statement("local foo if foo then return foo end") => "local a if a then return a end",
     if_with_else("local foo if foo then local foo else return foo end")
@@ -58,8 +58,10 @@ test_rule!(
     numeric_for_identifier("for i=1, 10 do foo = i end") => "for a=1, 10 do foo=a end",
     generic_for_identifiers("for key, value in t do return key end")
         => "for a, b in t do return a end",
+    generic_for_identifiers_are_scoped_to_loop("local key for key in t do end return key")
+        => "local a for b in t do end return a",
     repeat_condition_is_from_block("local foo repeat local bar until bar")
-        => "local a repeat local b until b",
+        => "local b repeat local a until a",
     while_statement("local foo while foo do local foo end") => "local a while a do local b end",
     if_statement("local foo if foo then return foo end") => "local a if a then return a end",
     if_with_else("local foo if foo then local foo else return foo end")
@@ -74,6 +76,61 @@ test_rule!(
     function_expression_parameters_reference("return function(foo, bar) return foo + bar end")
         => "return function(a, b) return a + b end",
     recycle_previous_identifiers("do local foo end local foo") => "do local a end local a",
+    most_referenced_variable_gets_shortest_name("local rare local hot return hot + hot")
+        => "local b local a return a + a",
+    sibling_scopes_reuse_names(
+        "local function f(x, y) return x + y end local function g(z) return z end"
+    ) => "local function f(a, b) return a + b end local function g(a) return a end",
+    shadowed_variables_get_different_names("local foo local function f(bar) return bar, foo end")
+        => "local a local function f(b) return b, a end",
+    unlisted_global_read_is_not_shadowed("local foo return a + foo")
+        => "local b return a + b",
+    unlisted_global_assignment_is_not_shadowed("local foo a = foo")
+        => "local b a = b",
+    unlisted_global_in_nested_function_is_not_shadowed("local foo return function() return a(foo) end")
+        => "local b return function() return a(b) end",
+);
+
+test_rule!(
+    rename_variables_with_seed,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_variables',
+        seed: 1,
+    }"#,
+    ).unwrap(),
+    local_assign_and_numeric_for(
+        "local count, total = 0, 0 for index = 1, 3 do count = count + index end return count, total"
+    ) => "local p, t = 0, 0 for Q = 1, 3 do p = p + Q end return p, t",
+);
+
+test_rule!(
+    rename_variables_with_confusable_alphabet,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_variables',
+        alphabet: '$confusable',
+    }"#,
+    ).unwrap(),
+    local_assign_and_numeric_for(
+        "local count, total = 0, 0 for index = 1, 3 do count = count + index end return count, total"
+    ) => "local l, I = 0, 0 for _ = 1, 3 do l = l + _ end return l, I",
+    longer_names_do_not_start_with_digits("local a, b, c, d, e")
+        => "local l, I, _, ll, lI",
+    unlisted_globals_are_not_shadowed("local count = 1 return l + count, I")
+        => "local _ = 1 return l + _, I",
+);
+
+test_rule!(
+    rename_variables_with_custom_alphabet_and_globals,
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_variables',
+        alphabet: 'xy',
+        globals: ['$default', 'x'],
+    }"#,
+    ).unwrap(),
+    globals_are_avoided("local a, b, c return x") => "local y, xx, xy return x",
 );
 
 test_rule_wihout_effects!(
@@ -114,3 +171,39 @@ fn deserialize_with_special_roblox_globals() {
     )
     .unwrap();
 }
+
+#[test]
+fn deserialize_with_large_seed() {
+    json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_variables',
+        seed: 4294967296,
+    }"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn deserialize_with_negative_seed_errors() {
+    let result = json5::from_str::<Box<dyn Rule>>(
+        r#"{
+        rule: 'rename_variables',
+        seed: -1,
+    }"#,
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn process_with_invalid_alphabet_errors() {
+    use darklua_core::rules::Context;
+
+    let rule = RenameVariables::default().with_alphabet("123");
+    let mut block = crate::utils::parse_input("local foo");
+
+    pretty_assertions::assert_eq!(
+        rule.process(&mut block, &mut Context::default()),
+        Err("invalid alphabet: expected at least one letter or `_`".to_owned())
+    );
+}